@@ -1,6 +1,9 @@
+use std::collections::BTreeMap;
+
 use bitcoin::address::NetworkUnchecked;
+use chrono::NaiveDateTime;
 use fedimint_core::config::FederationId;
-use fedimint_core::Amount;
+use fedimint_core::{Amount, PeerId, TransactionId};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +27,54 @@ pub struct FederationSummary {
 pub struct FederationRating {
     pub count: u64,
     pub avg: Option<f64>,
+    /// Average weighted by how trusted the authors are and how recent the
+    /// ratings are, harder to skew with fresh keys than `avg`
+    pub weighted_avg: Option<f64>,
+    pub weights: RatingWeights,
+}
+
+/// Components of [`FederationRating::weighted_avg`]. Each rating is weighted by
+/// `trust * 0.5^(age / half_life)`, where trust is `1` for the configured
+/// trusted pubkeys and everyone they follow and `untrusted_weight` otherwise.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RatingWeights {
+    /// Ratings by trusted authors
+    pub trusted_count: u64,
+    pub untrusted_weight: f64,
+    pub half_life_days: u32,
+    /// Sum of the weights of all ratings, i.e. how many fresh trusted ratings
+    /// the weighted average is worth
+    pub effective_count: f64,
+}
+
+/// A single Nostr rating of a federation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FederationReview {
+    /// Hex encoded Nostr event id
+    pub event_id: String,
+    /// Hex encoded public key of the author
+    pub author: String,
+    pub star_vote: Option<u8>,
+    /// Review text without the star rating prefix
+    pub comment: String,
+    pub created_at: NaiveDateTime,
+    pub author_profile: Option<NostrProfile>,
+}
+
+/// Subset of the NIP-01 kind 0 profile metadata
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NostrProfile {
+    pub name: Option<String>,
+    pub display_name: Option<String>,
+    pub picture: Option<String>,
+    pub nip05: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FederationReviews {
+    /// Number of reviews of the federation, not just on this page
+    pub total: u64,
+    pub reviews: Vec<FederationReview>,
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
@@ -38,3 +89,768 @@ pub struct FederationUtxo {
     pub out_point: bitcoin::OutPoint,
     pub amount: Amount,
 }
+
+/// A single hit returned by the `/search` endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SearchMatch {
+    Federation {
+        federation_id: FederationId,
+        name: Option<String>,
+        observed: bool,
+    },
+    Transaction {
+        federation_id: FederationId,
+        txid: TransactionId,
+        session_index: u64,
+    },
+    Session {
+        federation_id: FederationId,
+        session_index: u64,
+    },
+    LightningContract {
+        federation_id: FederationId,
+        contract_id: String,
+        payment_hash: String,
+        contract_type: String,
+    },
+    PegIn {
+        federation_id: FederationId,
+        on_chain_txid: String,
+        on_chain_vout: u32,
+        txid: TransactionId,
+    },
+    PegOut {
+        federation_id: FederationId,
+        on_chain_txid: String,
+        txid: Option<TransactionId>,
+    },
+    DepositAddress {
+        federation_id: FederationId,
+        address: String,
+    },
+    WithdrawalAddress {
+        federation_id: FederationId,
+        address: String,
+        txid: TransactionId,
+    },
+}
+
+impl SearchMatch {
+    pub fn federation_id(&self) -> FederationId {
+        match self {
+            SearchMatch::Federation { federation_id, .. }
+            | SearchMatch::Transaction { federation_id, .. }
+            | SearchMatch::Session { federation_id, .. }
+            | SearchMatch::LightningContract { federation_id, .. }
+            | SearchMatch::PegIn { federation_id, .. }
+            | SearchMatch::PegOut { federation_id, .. }
+            | SearchMatch::DepositAddress { federation_id, .. }
+            | SearchMatch::WithdrawalAddress { federation_id, .. } => *federation_id,
+        }
+    }
+}
+
+/// Compares what a federation holds on-chain to what it owes its users
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FederationBalanceSheet {
+    /// On-chain funds controlled by the federation
+    pub assets: Amount,
+    /// E-cash issued by the mint module that hasn't been redeemed yet
+    pub ecash_liabilities: Amount,
+    /// Lightning contracts that were funded but not yet claimed or refunded
+    pub lightning_liabilities: Amount,
+    /// `assets - (ecash_liabilities + lightning_liabilities)` in msat, negative
+    /// if the federation owes more than it holds
+    pub surplus_msat: i64,
+}
+
+/// Fees collected by a federation, both as observed in the transaction log and
+/// as expected from the fee parameters in the client config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FederationFees {
+    /// Sum of `inputs - outputs` over all transactions
+    pub collected: Amount,
+    pub transactions: u64,
+    /// What should have been collected according to `fee_params`
+    pub expected: Amount,
+    /// Fee parameters by module kind, modules we can't interpret are missing
+    pub fee_params: BTreeMap<String, ModuleFeeParams>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ModuleFeeParams {
+    pub input: Amount,
+    pub output: Amount,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeActivity {
+    pub collected: Amount,
+    /// Fee revenue per module kind, estimated by applying the module's fee
+    /// parameters to its inputs and outputs since fees are only observable per
+    /// transaction
+    pub by_module: BTreeMap<String, Amount>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FederationWithdrawal {
+    pub on_chain_txid: String,
+    /// Fedimint transaction that requested the peg-out, unknown till the
+    /// on-chain transaction was matched to it
+    pub txid: Option<TransactionId>,
+    pub fee_sat: Option<u64>,
+    pub feerate_sat_per_vb: Option<f64>,
+    /// Time from the session containing the peg-out request to the session in
+    /// which the threshold of guardian signatures was reached
+    pub signing_time_secs: Option<i64>,
+    /// Time from the session containing the peg-out request to the first
+    /// on-chain confirmation
+    pub confirmation_time_secs: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WithdrawalStatistics {
+    pub count: u64,
+    pub fee_sat: Percentiles,
+    pub feerate_sat_per_vb: Percentiles,
+    pub signing_time_secs: Percentiles,
+    pub confirmation_time_secs: Percentiles,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Percentiles {
+    /// Number of values the percentiles were calculated from
+    pub samples: u64,
+    pub p50: Option<f64>,
+    pub p90: Option<f64>,
+    pub p99: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// Feerate votes of a federation's guardians, used to fund peg-out
+/// transactions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FederationFeerates {
+    /// Median of the latest vote of each guardian
+    pub consensus_sats_per_kvb: Option<u64>,
+    /// Latest vote of each guardian that ever voted
+    pub guardians: BTreeMap<PeerId, GuardianFeerateVote>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GuardianFeerateVote {
+    pub session_index: u64,
+    pub sats_per_kvb: u64,
+    /// Relative deviation from the consensus feerate, e.g. `0.5` if the vote
+    /// is 50% higher
+    pub deviation: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeerateActivity {
+    pub consensus_sats_per_kvb: Option<u64>,
+    /// Last vote of each guardian on that day
+    pub guardians: BTreeMap<PeerId, u64>,
+}
+
+/// Block heights the guardians' bitcoind instances reported through their
+/// consensus votes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FederationBlockHeights {
+    /// Median of the latest vote of each guardian
+    pub consensus_height: Option<u32>,
+    pub guardians: BTreeMap<PeerId, GuardianBlockHeight>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GuardianBlockHeight {
+    /// Latest height vote, `None` if the guardian never voted
+    pub height: Option<u32>,
+    pub session_index: Option<u64>,
+    /// Blocks the latest vote is behind the consensus height
+    pub lag_blocks: Option<u32>,
+    pub status: BlockHeightStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockHeightStatus {
+    InSync,
+    Lagging,
+    /// The guardian's bitcoind hasn't followed the chain for about an hour or
+    /// more
+    Stuck,
+    /// The guardian never voted, or there is no consensus height to compare
+    /// its vote to
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockHeightActivity {
+    /// Median of each guardian's latest vote at the end of the day
+    pub consensus_height: u32,
+    /// Blocks each guardian's latest vote was behind the consensus height at
+    /// the end of the day
+    pub lag_blocks: BTreeMap<PeerId, u32>,
+}
+
+/// How much each guardian contributed to consensus, derived from the proposer
+/// of every accepted consensus item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FederationParticipation {
+    /// Sessions containing at least one consensus item
+    pub active_sessions: u64,
+    pub guardians: BTreeMap<PeerId, GuardianParticipation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuardianParticipation {
+    pub items: u64,
+    /// Share of all accepted consensus items proposed by this guardian
+    pub proposal_share: f64,
+    /// Active sessions containing at least one item proposed by this guardian
+    pub sessions_contributed: u64,
+    /// Stretches of consecutive active sessions without any contribution from
+    /// this guardian, most recent first
+    pub inactive_ranges: Vec<SessionRange>,
+}
+
+/// Inclusive range of session indices
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SessionRange {
+    pub start_session_index: u64,
+    pub end_session_index: u64,
+    /// Number of active sessions in the range
+    pub sessions: u64,
+}
+
+/// Progress of rebuilding a federation's derived tables from its stored
+/// sessions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReindexJob {
+    pub federation_id: FederationId,
+    /// All sessions before this one were already reindexed
+    pub next_session_index: u64,
+    /// Number of sessions stored for the federation
+    pub session_count: u64,
+    pub started_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+/// Pushed to clients of the `/federations/events` streams as new data is
+/// ingested
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FederationEvent {
+    Session {
+        federation_id: FederationId,
+        session_index: u64,
+        transactions: u64,
+    },
+    Transaction {
+        federation_id: FederationId,
+        txid: TransactionId,
+        session_index: u64,
+        /// Sum of all inputs
+        amount: Amount,
+    },
+    PegIn {
+        federation_id: FederationId,
+        on_chain_txid: String,
+        on_chain_vout: u32,
+        txid: TransactionId,
+        amount: Amount,
+    },
+    PegOut {
+        federation_id: FederationId,
+        txid: TransactionId,
+        address: String,
+        amount: Amount,
+    },
+    GuardianStatus {
+        federation_id: FederationId,
+        peer_id: PeerId,
+        online: bool,
+        block_height: Option<u32>,
+    },
+    /// No new session was observed for a while, published once per stall
+    ObserverStalled {
+        federation_id: FederationId,
+        /// Session the observer is waiting for
+        session_index: u64,
+        stalled_secs: u64,
+    },
+    /// The client config served by the guardians differs from the last
+    /// fetched one
+    ConfigChanged { federation_id: FederationId },
+    /// The federation's meta fields, including the override file, changed
+    MetaChanged {
+        federation_id: FederationId,
+        changed_keys: Vec<String>,
+    },
+    Rating {
+        federation_id: FederationId,
+        event_id: String,
+        star_vote: Option<u8>,
+    },
+    /// An alert started firing or was resolved
+    Alert {
+        federation_id: FederationId,
+        rule_id: u32,
+        rule_name: String,
+        /// `None` for federation-wide alerts
+        peer_id: Option<PeerId>,
+        state: AlertState,
+        message: String,
+    },
+}
+
+impl FederationEvent {
+    pub fn federation_id(&self) -> FederationId {
+        match self {
+            FederationEvent::Session { federation_id, .. }
+            | FederationEvent::Transaction { federation_id, .. }
+            | FederationEvent::PegIn { federation_id, .. }
+            | FederationEvent::PegOut { federation_id, .. }
+            | FederationEvent::GuardianStatus { federation_id, .. }
+            | FederationEvent::ObserverStalled { federation_id, .. }
+            | FederationEvent::ConfigChanged { federation_id, .. }
+            | FederationEvent::MetaChanged { federation_id, .. }
+            | FederationEvent::Rating { federation_id, .. }
+            | FederationEvent::Alert { federation_id, .. } => *federation_id,
+        }
+    }
+
+    /// Name of the event type, used as SSE event name
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FederationEvent::Session { .. } => "session",
+            FederationEvent::Transaction { .. } => "transaction",
+            FederationEvent::PegIn { .. } => "peg_in",
+            FederationEvent::PegOut { .. } => "peg_out",
+            FederationEvent::GuardianStatus { .. } => "guardian_status",
+            FederationEvent::ObserverStalled { .. } => "observer_stalled",
+            FederationEvent::ConfigChanged { .. } => "config_changed",
+            FederationEvent::MetaChanged { .. } => "meta_changed",
+            FederationEvent::Rating { .. } => "rating",
+            FederationEvent::Alert { .. } => "alert",
+        }
+    }
+}
+
+/// All values [`FederationEvent::kind`] can return
+pub const FEDERATION_EVENT_KINDS: &[&str] = &[
+    "session",
+    "transaction",
+    "peg_in",
+    "peg_out",
+    "guardian_status",
+    "observer_stalled",
+    "config_changed",
+    "meta_changed",
+    "rating",
+    "alert",
+];
+
+/// Admin-registered HTTP endpoint that federation events are posted to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+    pub webhook_id: u32,
+    pub url: String,
+    /// Only events of this federation are delivered, all if `None`
+    pub federation_id: Option<FederationId>,
+    /// Event kinds to deliver, see [`FEDERATION_EVENT_KINDS`]
+    pub events: Vec<String>,
+    /// Peg-outs below this amount aren't delivered
+    pub min_withdrawal_amount: Option<Amount>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewWebhook {
+    pub url: String,
+    /// Key used to sign deliveries with HMAC-SHA256
+    pub secret: String,
+    #[serde(default)]
+    pub federation_id: Option<FederationId>,
+    pub events: Vec<String>,
+    #[serde(default)]
+    pub min_withdrawal_amount: Option<Amount>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub delivery_id: u64,
+    pub webhook_id: u32,
+    pub event: FederationEvent,
+    pub created_at: NaiveDateTime,
+    pub attempts: u32,
+    /// `None` once delivered or given up on
+    pub next_attempt_at: Option<NaiveDateTime>,
+    pub delivered_at: Option<NaiveDateTime>,
+    /// HTTP status of the last attempt
+    pub last_status: Option<u16>,
+    pub last_error: Option<String>,
+}
+
+/// Nostr relay used to fetch ratings and publish events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NostrRelay {
+    pub relay_url: String,
+    pub added_at: NaiveDateTime,
+    /// Whether the relay was connected during the last sync
+    pub connected: bool,
+    pub events_fetched: u64,
+    pub publish_attempts: u64,
+    pub publish_successes: u64,
+    /// Share of successful publish attempts, `None` if nothing was published
+    /// yet
+    pub publish_success_rate: Option<f64>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewNostrRelay {
+    pub relay_url: String,
+}
+
+/// Federation announced on Nostr that might not be observed yet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoveredFederation {
+    pub federation_id: FederationId,
+    pub invite_codes: Vec<String>,
+    /// Network as announced, e.g. `mainnet` or `signet`
+    pub network: Option<String>,
+    pub first_seen: NaiveDateTime,
+    pub last_seen: NaiveDateTime,
+    pub status: DiscoveryStatus,
+    /// Why adding the federation failed
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscoveryStatus {
+    /// Waiting for review
+    Pending,
+    Added,
+    Dismissed,
+    Failed,
+}
+
+/// Condition evaluated against the guardian health checks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// Every health check of a guardian failed for at least this long
+    GuardianUnreachable { for_secs: u64 },
+    /// A guardian's bitcoind is more than this many blocks behind the median
+    /// of all guardians
+    BlockHeightLag { max_lag_blocks: u32 },
+    /// Fewer guardians than needed for consensus answered the last health
+    /// check, so the federation can't process transactions
+    GuardiansBelowThreshold,
+    /// The 95th percentile of a guardian's API latency over the window exceeds
+    /// the limit
+    LatencyP95 {
+        max_latency_ms: u64,
+        window_secs: u64,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub rule_id: u32,
+    pub name: String,
+    /// Only evaluated for this federation, for all if `None`
+    pub federation_id: Option<FederationId>,
+    pub condition: AlertCondition,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewAlertRule {
+    pub name: String,
+    #[serde(default)]
+    pub federation_id: Option<FederationId>,
+    pub condition: AlertCondition,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertState {
+    Firing,
+    Resolved,
+}
+
+/// Current state of a rule for one federation or guardian
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alert {
+    pub rule_id: u32,
+    pub rule_name: String,
+    pub federation_id: FederationId,
+    /// `None` for federation-wide alerts
+    pub peer_id: Option<PeerId>,
+    pub state: AlertState,
+    /// Value that triggered the alert, e.g. lag in blocks or latency in ms
+    pub value: f64,
+    pub message: String,
+    /// Last time the alert started firing
+    pub fired_at: NaiveDateTime,
+    pub resolved_at: Option<NaiveDateTime>,
+    pub updated_at: NaiveDateTime,
+}
+
+/// Admin actions an API key can be allowed to perform
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiScope {
+    /// Adding federations, including discovered ones
+    AddFederation,
+    RemoveFederation,
+    Reindex,
+    Webhooks,
+    Alerts,
+    Relays,
+    /// Minting and revoking API keys and reading the audit log
+    ApiKeys,
+    /// Bypassing the config inspector cache
+    RefreshConfig,
+}
+
+impl ApiScope {
+    pub const ALL: [ApiScope; 8] = [
+        ApiScope::AddFederation,
+        ApiScope::RemoveFederation,
+        ApiScope::Reindex,
+        ApiScope::Webhooks,
+        ApiScope::Alerts,
+        ApiScope::Relays,
+        ApiScope::ApiKeys,
+        ApiScope::RefreshConfig,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ApiScope::AddFederation => "add_federation",
+            ApiScope::RemoveFederation => "remove_federation",
+            ApiScope::Reindex => "reindex",
+            ApiScope::Webhooks => "webhooks",
+            ApiScope::Alerts => "alerts",
+            ApiScope::Relays => "relays",
+            ApiScope::ApiKeys => "api_keys",
+            ApiScope::RefreshConfig => "refresh_config",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub key_id: u32,
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+    pub created_at: NaiveDateTime,
+    /// The key can't be used anymore after this time, never expires if `None`
+    pub expires_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewApiKey {
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+    #[serde(default)]
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+/// Newly minted API key, the secret is only returned once
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub key: ApiKey,
+    /// Bearer token to authenticate with
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditLogEntry {
+    pub entry_id: u64,
+    /// `None` if the admin token from the server config was used or
+    /// authentication failed
+    pub key_id: Option<u32>,
+    /// Name of the key, `root` for the admin token from the server config
+    pub principal: Option<String>,
+    pub method: String,
+    pub path: String,
+    pub scope: Option<ApiScope>,
+    /// HTTP status of the response
+    pub status: u16,
+    /// Failed authentication attempts are aggregated per route and hour, with
+    /// `path` being the route template
+    pub attempts: u32,
+    pub created_at: NaiveDateTime,
+}
+
+/// Client configs served by each guardian individually, compared to the one
+/// the federation agrees on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigConsistency {
+    pub federation_id: FederationId,
+    /// Hex encoded hash of the config returned by the federation's consensus
+    pub consensus_hash: String,
+    /// All guardians are reachable and serve the consensus config
+    pub consistent: bool,
+    pub guardians: BTreeMap<PeerId, GuardianConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuardianConfig {
+    pub name: String,
+    pub url: String,
+    pub status: GuardianConfigStatus,
+    /// Hex encoded hash of the config served by the guardian, `None` if it
+    /// was unreachable
+    pub config_hash: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuardianConfigStatus {
+    Consistent,
+    Divergent,
+    Unreachable,
+}
+
+/// Live status of a federation's guardians, queried on request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FederationLiveStatus {
+    pub federation_id: FederationId,
+    /// When the guardians were queried, results are cached for a short time
+    pub checked_at: NaiveDateTime,
+    pub online_guardians: usize,
+    /// Enough guardians are online for the federation to reach consensus
+    pub online: bool,
+    pub guardians: BTreeMap<PeerId, GuardianLiveStatus>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GuardianLiveStatus {
+    pub reachable: bool,
+    /// Latency of the block height request, `None` if it failed
+    pub latency_ms: Option<u64>,
+    pub session_count: Option<u64>,
+    pub block_height: Option<u32>,
+}
+
+/// Well-known meta fields of a federation, both from the config and the meta
+/// override file. Values are only set if they have the right type.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FederationMeta {
+    pub federation_name: Option<String>,
+    pub federation_icon_url: Option<String>,
+    pub welcome_message: Option<String>,
+    /// Unix timestamp after which the federation will shut down
+    pub federation_expiry_timestamp: Option<u64>,
+    /// Node public keys of the gateways recommended by the federation
+    pub vetted_gateways: Option<Vec<String>>,
+    pub meta_override_url: Option<String>,
+    /// Unix timestamp until which clients should show the countdown popup
+    pub popup_end_timestamp: Option<u64>,
+    pub popup_countdown_message: Option<String>,
+    /// Fedi legacy field, replaced by `meta_override_url`
+    pub meta_external_url: Option<String>,
+    /// Fedi legacy field, replaced by `welcome_message`
+    #[serde(rename = "fedi:pinned_message")]
+    pub fedi_pinned_message: Option<String>,
+    /// Fedi legacy field, replaced by `federation_icon_url`
+    #[serde(rename = "fedi:federation_icon_url")]
+    pub fedi_federation_icon_url: Option<String>,
+    #[serde(rename = "fedi:tos_url")]
+    pub fedi_tos_url: Option<String>,
+    #[serde(rename = "fedi:default_currency")]
+    pub fedi_default_currency: Option<String>,
+    /// Fedi legacy field, replaced by `popup_end_timestamp`
+    #[serde(rename = "fedi:popup_end_timestamp")]
+    pub fedi_popup_end_timestamp: Option<u64>,
+    /// Fedi legacy field, replaced by `popup_countdown_message`
+    #[serde(rename = "fedi:popup_countdown_message")]
+    pub fedi_popup_countdown_message: Option<String>,
+    #[serde(rename = "fedi:invite_codes_disabled")]
+    pub fedi_invite_codes_disabled: Option<bool>,
+    #[serde(rename = "fedi:new_members_disabled")]
+    pub fedi_new_members_disabled: Option<bool>,
+    #[serde(rename = "fedi:max_invoice_msats")]
+    pub fedi_max_invoice_msats: Option<u64>,
+    #[serde(rename = "fedi:max_balance_msats")]
+    pub fedi_max_balance_msats: Option<u64>,
+}
+
+impl FederationMeta {
+    /// Keys of all fields of [`FederationMeta`] as they appear in the meta
+    pub const KNOWN_FIELDS: [&'static str; 19] = [
+        "federation_name",
+        "federation_icon_url",
+        "welcome_message",
+        "federation_expiry_timestamp",
+        "vetted_gateways",
+        "meta_override_url",
+        "popup_end_timestamp",
+        "popup_countdown_message",
+        "meta_external_url",
+        "fedi:pinned_message",
+        "fedi:federation_icon_url",
+        "fedi:tos_url",
+        "fedi:default_currency",
+        "fedi:popup_end_timestamp",
+        "fedi:popup_countdown_message",
+        "fedi:invite_codes_disabled",
+        "fedi:new_members_disabled",
+        "fedi:max_invoice_msats",
+        "fedi:max_balance_msats",
+    ];
+}
+
+/// Problems found in a federation's meta fields, see
+/// [`FederationMeta`] for the fields that are understood
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaValidationReport {
+    pub federation_id: FederationId,
+    /// No issues were found
+    pub valid: bool,
+    /// The well-known fields that could be parsed
+    pub meta: FederationMeta,
+    pub issues: Vec<MetaIssue>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaIssue {
+    pub key: String,
+    pub source: MetaSource,
+    pub kind: MetaIssueKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetaSource {
+    Config,
+    /// The meta override file referenced by `meta_override_url` or
+    /// `meta_external_url`
+    Override,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetaIssueKind {
+    UnknownKey,
+    WrongType,
+    /// The value isn't a string and is ignored by clients
+    Dropped,
+    /// The meta override file couldn't be fetched or doesn't contain the
+    /// federation
+    OverrideUnavailable,
+}
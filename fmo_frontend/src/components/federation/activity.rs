@@ -15,13 +15,15 @@ use leptos::{
 use leptos_chartistry::*;
 
 use crate::components::alert::{Alert, AlertLevel};
-use crate::util::AsBitcoin;
+use crate::util::{use_session_events, AsBitcoin};
 
 #[component]
 pub fn ActivityChart(id: FederationId) -> impl IntoView {
+    // Refetch the history whenever the federation finishes a session
+    let sessions = use_session_events(&format!("/federations/{id}/events"));
     let history_resource = create_resource(
-        || (),
-        move |()| async move {
+        move || sessions.get(),
+        move |_| async move {
             fetch_federation_history(id)
                 .await
                 .map_err(|e| e.to_string())
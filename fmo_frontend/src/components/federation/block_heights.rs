@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use fedimint_core::config::FederationId;
+use fedimint_core::PeerId;
+use fmo_api_types::{BlockHeightActivity, BlockHeightStatus, FederationBlockHeights};
+use leptos::{component, create_resource, view, IntoView, SignalGet};
+use leptos_chartistry::*;
+
+use crate::components::alert::{Alert, AlertLevel};
+
+#[component]
+pub fn BlockHeights(
+    federation_id: FederationId,
+    guardian_names: BTreeMap<PeerId, String>,
+) -> impl IntoView {
+    let block_heights_resource = create_resource(
+        || (),
+        move |()| async move {
+            let block_heights = fetch_block_heights(federation_id).await?;
+            let history = fetch_block_height_history(federation_id).await?;
+            Result::<_, String>::Ok((block_heights, history))
+        },
+    );
+
+    view! {
+        {move || {
+            match block_heights_resource.get() {
+                Some(Ok((block_heights, history))) => {
+                    let rows = block_heights
+                        .guardians
+                        .iter()
+                        .map(|(peer_id, guardian)| {
+                            let name = guardian_names
+                                .get(peer_id)
+                                .cloned()
+                                .unwrap_or_else(|| peer_id.to_string());
+                            let (status, status_class) = match guardian.status {
+                                BlockHeightStatus::InSync => ("In sync", "text-green-600 dark:text-green-400"),
+                                BlockHeightStatus::Lagging => ("Lagging", "text-yellow-600 dark:text-yellow-400"),
+                                BlockHeightStatus::Stuck => ("Stuck", "text-red-600 dark:text-red-400"),
+                                BlockHeightStatus::Unknown => ("Unknown", "text-gray-500 dark:text-gray-400"),
+                            };
+                            view! {
+                                <tr class="bg-white border-b dark:bg-gray-800 dark:border-gray-700">
+                                    <th scope="row" class="px-6 py-4 font-medium text-gray-900 dark:text-white">
+                                        {name}
+                                    </th>
+                                    <td class="px-6 py-4">
+                                        {guardian.height.map(|height| height.to_string()).unwrap_or_else(|| "-".to_owned())}
+                                    </td>
+                                    <td class="px-6 py-4">
+                                        {guardian.lag_blocks.map(|lag| lag.to_string()).unwrap_or_else(|| "-".to_owned())}
+                                    </td>
+                                    <td class=format!("px-6 py-4 {status_class}")>{status}</td>
+                                </tr>
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    let peers = block_heights.guardians.keys().copied().collect::<Vec<_>>();
+                    let series_names = peers
+                        .iter()
+                        .map(|peer_id| {
+                            guardian_names
+                                .get(peer_id)
+                                .cloned()
+                                .unwrap_or_else(|| peer_id.to_string())
+                        })
+                        .collect::<Vec<_>>();
+                    let chart_data = history
+                        .into_iter()
+                        .map(|(date, activity)| {
+                            (
+                                NaiveDateTime::from(date).and_utc(),
+                                peers
+                                    .iter()
+                                    .map(|peer_id| {
+                                        activity
+                                            .lag_blocks
+                                            .get(peer_id)
+                                            .map(|&lag| lag as f64)
+                                            .unwrap_or(f64::NAN)
+                                    })
+                                    .collect::<Vec<_>>(),
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    view! {
+                        <div>
+                            <Alert
+                                message="Block heights are taken from the votes guardians submit to consensus whenever their bitcoind sees a new block. A guardian that stopped voting while the others continued is likely running a stuck bitcoind."
+                                level=AlertLevel::Info
+                                class="my-4"
+                            />
+                            <table class="w-full text-sm text-left rtl:text-right text-gray-500 dark:text-gray-400">
+                                <thead class="text-xs text-gray-700 uppercase bg-gray-50 dark:bg-gray-700 dark:text-gray-400">
+                                    <tr>
+                                        <th scope="col" class="px-6 py-3">"Guardian"</th>
+                                        <th scope="col" class="px-6 py-3">"Latest Vote"</th>
+                                        <th scope="col" class="px-6 py-3">"Lag (blocks)"</th>
+                                        <th scope="col" class="px-6 py-3">"Status"</th>
+                                    </tr>
+                                </thead>
+                                <tbody>{rows}</tbody>
+                            </table>
+                            <div class="w-full bg-white rounded-lg shadow dark:bg-gray-800 p-4 md:p-6 my-4">
+                                <BlockHeightLagChart names=series_names data=chart_data/>
+                            </div>
+                        </div>
+                    }
+                        .into_view()
+                }
+                Some(Err(e)) => view! { <p>"Error: " {e}</p> }.into_view(),
+                None => view! { <p>"Loading ..."</p> }.into_view(),
+            }
+        }}
+    }
+}
+
+#[component]
+fn BlockHeightLagChart(names: Vec<String>, data: Vec<(DateTime<Utc>, Vec<f64>)>) -> impl IntoView {
+    let series = names.into_iter().enumerate().fold(
+        Series::new(|data: &(DateTime<Utc>, Vec<f64>)| data.0),
+        |series, (idx, name)| {
+            series.line(
+                Line::new(move |data: &(DateTime<Utc>, Vec<f64>)| data.1[idx])
+                    .with_name(name)
+                    .with_interpolation(Interpolation::Linear),
+            )
+        },
+    );
+
+    view! {
+        <Chart
+            aspect_ratio=AspectRatio::from_env_width(300.0)
+            top=RotatedLabel::middle("Blocks behind consensus")
+            left=TickLabels::aligned_floats()
+            bottom=TickLabels::from_generator(Timestamps::from_period(Period::Day))
+            inner=[
+                AxisMarker::left_edge().into_inner(),
+                AxisMarker::bottom_edge().into_inner(),
+                XGridLine::default().into_inner(),
+                YGridLine::default().into_inner(),
+                XGuideLine::over_data().into_inner(),
+                YGuideLine::over_mouse().into_inner(),
+            ]
+            series=series
+            data=move || data.clone()
+        />
+    }
+}
+
+async fn fetch_block_heights(
+    federation_id: FederationId,
+) -> Result<FederationBlockHeights, String> {
+    let url = format!(
+        "{}/federations/{}/guardians/block_heights",
+        crate::BASE_URL,
+        federation_id
+    );
+    let res = reqwest::get(&url).await.map_err(|e| e.to_string())?;
+    let json = res.json().await.map_err(|e| e.to_string())?;
+    Ok(json)
+}
+
+async fn fetch_block_height_history(
+    federation_id: FederationId,
+) -> Result<BTreeMap<NaiveDate, BlockHeightActivity>, String> {
+    let url = format!(
+        "{}/federations/{}/guardians/block_heights/history",
+        crate::BASE_URL,
+        federation_id
+    );
+    let res = reqwest::get(&url).await.map_err(|e| e.to_string())?;
+    let json = res.json().await.map_err(|e| e.to_string())?;
+    Ok(json)
+}
@@ -64,7 +64,7 @@ pub fn General(config: JsonClientConfig) -> impl IntoView {
     }
 }
 
-fn get_network(cfg: &JsonClientConfig) -> String {
+pub(crate) fn get_network(cfg: &JsonClientConfig) -> String {
     // TODO: don't assume so much
     cfg.modules
         .iter()
@@ -1,7 +1,10 @@
 mod activity;
+mod block_heights;
 mod general;
 mod guardians;
 pub mod nostr_vote;
+mod reviews;
+mod solvency;
 pub mod stars_seletor;
 mod utxos;
 
@@ -14,9 +17,12 @@ use leptos_router::{use_params, Params, ParamsError, ParamsMap};
 use utxos::Utxos;
 
 use crate::components::federation::activity::ActivityChart;
+use crate::components::federation::block_heights::BlockHeights;
 use crate::components::federation::general::General;
 use crate::components::federation::guardians::{Guardian, Guardians};
 use crate::components::federation::nostr_vote::NostrVote;
+use crate::components::federation::reviews::Reviews;
+use crate::components::federation::solvency::Solvency;
 use crate::components::tabs::{Tab, Tabs};
 use crate::BASE_URL;
 
@@ -68,6 +74,12 @@ pub fn Federation() -> impl IntoView {
                 {move || {
                     match config_resource.get() {
                         Some(Ok(config)) => {
+                            let guardian_names = config
+                                .global
+                                .api_endpoints
+                                .iter()
+                                .map(|(&peer_id, guardian)| (peer_id, guardian.name.clone()))
+                                .collect::<BTreeMap<_, _>>();
                             view! {
                                 <div class="flex flex-wrap items-stretch gap-4 ">
                                     <div class="flex-1 min-w-[400px]">
@@ -95,6 +107,18 @@ pub fn Federation() -> impl IntoView {
                                     <Tab name="UTXOs">
                                         <Utxos federation_id=id().unwrap()/>
                                     </Tab>
+                                    <Tab name="Solvency">
+                                        <Solvency federation_id=id().unwrap()/>
+                                    </Tab>
+                                    <Tab name="Reviews">
+                                        <Reviews federation_id=id().unwrap()/>
+                                    </Tab>
+                                    <Tab name="Block Heights">
+                                        <BlockHeights
+                                            federation_id=id().unwrap()
+                                            guardian_names=guardian_names.clone()
+                                        />
+                                    </Tab>
                                     <Tab name="Config">
                                         <div class="w-full overflow-x-scroll my-4">
                                             <pre class="dark:text-white">
@@ -12,6 +12,7 @@ use nostr_sdk::{EventBuilder, Kind, SingleLetterTag, Tag, TagKind};
 use reqwest::StatusCode;
 
 use crate::components::alert::{Alert, AlertLevel};
+use crate::components::federation::general::get_network;
 use crate::components::federation::stars_seletor::StarsSelector;
 use crate::components::federations::rating::Rating;
 use crate::BASE_URL;
@@ -19,15 +20,26 @@ use crate::BASE_URL;
 #[component]
 pub fn NostrVote(config: JsonClientConfig) -> impl IntoView {
     let federation_id = config.global.calculate_federation_id();
+    // NIP-87 calls the main network "mainnet", everything else uses the same names
+    let network = match get_network(&config).as_str() {
+        "bitcoin" => "mainnet".to_owned(),
+        network => network.to_owned(),
+    };
 
     let (in_progress, set_in_progress) = create_signal(false);
     let sign_rating_action = create_action(move |(rating, comment): &(u8, String)| {
         let comment_inner = comment.clone();
         let rating_inner = *rating;
+        let network_inner = network.clone();
         async move {
-            let res = sign_and_publish_rating(federation_id, rating_inner, &comment_inner)
-                .await
-                .map_err(|e| e.to_string());
+            let res = sign_and_publish_rating(
+                federation_id,
+                &network_inner,
+                rating_inner,
+                &comment_inner,
+            )
+            .await
+            .map_err(|e| e.to_string());
             set_in_progress.set(false);
             res
         }
@@ -125,6 +137,7 @@ pub fn NostrVote(config: JsonClientConfig) -> impl IntoView {
 
 async fn sign_and_publish_rating(
     federation_id: FederationId,
+    network: &str,
     rating: u8,
     comment: &str,
 ) -> anyhow::Result<()> {
@@ -137,7 +150,7 @@ async fn sign_and_publish_rating(
         ),
         Tag::custom(
             TagKind::SingleLetter(SingleLetterTag::from_char('n').unwrap()),
-            ["mainnet"],
+            [network],
         ),
         Tag::custom(
             TagKind::SingleLetter(SingleLetterTag::from_char('k').unwrap()),
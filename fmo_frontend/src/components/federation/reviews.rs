@@ -0,0 +1,128 @@
+use fedimint_core::config::FederationId;
+use fmo_api_types::{FederationReview, FederationReviews};
+use leptos::{component, create_resource, create_signal, view, IntoView, SignalGet, SignalUpdate};
+use nostr_sdk::{PublicKey, ToBech32};
+
+const REVIEWS_PER_PAGE: u32 = 10;
+
+#[component]
+pub fn Reviews(federation_id: FederationId) -> impl IntoView {
+    let (page, set_page) = create_signal(0u32);
+    let reviews_resource = create_resource(
+        move || page.get(),
+        move |page| fetch_federation_reviews(federation_id, page),
+    );
+
+    view! {
+        {move || {
+            match reviews_resource.get() {
+                Some(Ok(reviews)) => {
+                    let pages = reviews.total.div_ceil(REVIEWS_PER_PAGE as u64).max(1);
+                    let has_next = ((page.get() + 1) as u64) < pages;
+                    let rows = reviews
+                        .reviews
+                        .into_iter()
+                        .map(|review| view! { <Review review=review /> })
+                        .collect::<Vec<_>>();
+                    view! {
+                        <div class="my-4">
+                            {if rows.is_empty() {
+                                view! {
+                                    <p class="text-gray-500 dark:text-gray-400">"No reviews yet"</p>
+                                }
+                                    .into_view()
+                            } else {
+                                rows.into_view()
+                            }}
+                            <div class="flex items-center justify-between mt-4 text-sm text-gray-700 dark:text-gray-400">
+                                <button
+                                    class="px-3 py-2 rounded-lg border border-gray-300 dark:border-gray-700 disabled:opacity-50"
+                                    disabled=move || page.get() == 0
+                                    on:click=move |_| set_page.update(|page| *page -= 1)
+                                >
+                                    "Previous"
+                                </button>
+                                <span>
+                                    "Page " {page.get() + 1} " of " {pages} " (" {reviews.total}
+                                    " reviews)"
+                                </span>
+                                <button
+                                    class="px-3 py-2 rounded-lg border border-gray-300 dark:border-gray-700 disabled:opacity-50"
+                                    disabled=!has_next
+                                    on:click=move |_| set_page.update(|page| *page += 1)
+                                >
+                                    "Next"
+                                </button>
+                            </div>
+                        </div>
+                    }
+                        .into_view()
+                }
+                Some(Err(e)) => view! { <p>"Error: " {e}</p> }.into_view(),
+                None => view! { <p>"Loading ..."</p> }.into_view(),
+            }
+        }}
+    }
+}
+
+#[component]
+fn Review(review: FederationReview) -> impl IntoView {
+    let npub = PublicKey::from_hex(&review.author)
+        .ok()
+        .and_then(|public_key| public_key.to_bech32().ok())
+        .unwrap_or_else(|| review.author.clone());
+    let profile = review.author_profile.unwrap_or_default();
+    let author_name = profile
+        .display_name
+        .filter(|name| !name.is_empty())
+        .or(profile.name.filter(|name| !name.is_empty()))
+        .unwrap_or_else(|| format!("{}…", &npub[..16.min(npub.len())]));
+
+    view! {
+        <div class="py-4 border-b border-gray-200 dark:border-gray-700">
+            <div class="flex items-center gap-3 mb-2">
+                {profile
+                    .picture
+                    .map(|picture| {
+                        view! { <img class="w-8 h-8 rounded-full" src=picture alt="" /> }
+                    })}
+                <div class="min-w-0">
+                    <a
+                        href=format!("https://njump.me/{npub}")
+                        class="font-medium text-gray-900 dark:text-white hover:underline truncate"
+                    >
+                        {author_name}
+                    </a>
+                    <p class="text-xs text-gray-500 dark:text-gray-400">
+                        {review.created_at.format("%Y-%m-%d %H:%M").to_string()}
+                        {profile.nip05.map(|nip05| format!(" · {nip05}"))}
+                    </p>
+                </div>
+                <span class="ms-auto text-yellow-300">
+                    {review
+                        .star_vote
+                        .map(|stars| "★".repeat(stars as usize) + &"☆".repeat(5 - stars as usize))}
+                </span>
+            </div>
+            <p class="text-gray-700 dark:text-gray-300 whitespace-pre-wrap break-words">
+                {review.comment}
+            </p>
+        </div>
+    }
+}
+
+async fn fetch_federation_reviews(
+    federation_id: FederationId,
+    page: u32,
+) -> Result<FederationReviews, String> {
+    let url = format!(
+        "{}/federations/{}/reviews?limit={}&offset={}",
+        crate::BASE_URL,
+        federation_id,
+        REVIEWS_PER_PAGE,
+        page * REVIEWS_PER_PAGE
+    );
+    let res = reqwest::get(&url).await.map_err(|e| e.to_string())?;
+    let json = res.json().await.map_err(|e| e.to_string())?;
+    Ok(json)
+}
@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use fedimint_core::config::FederationId;
+use fedimint_core::Amount;
+use fmo_api_types::FederationBalanceSheet;
+use leptos::{component, create_resource, view, IntoView, SignalGet};
+use leptos_chartistry::*;
+
+use crate::components::alert::{Alert, AlertLevel};
+use crate::util::AsBitcoin;
+
+#[component]
+pub fn Solvency(federation_id: FederationId) -> impl IntoView {
+    let balance_sheet_resource = create_resource(
+        || (),
+        move |()| async move {
+            let balance_sheet = fetch_balance_sheet(federation_id).await?;
+            let history = fetch_balance_sheet_history(federation_id).await?;
+            Result::<_, String>::Ok((balance_sheet, history))
+        },
+    );
+
+    view! {
+        {move || {
+            match balance_sheet_resource.get() {
+                Some(Ok((balance_sheet, history))) => {
+                    let surplus = if balance_sheet.surplus_msat >= 0 {
+                        Amount::from_msats(balance_sheet.surplus_msat as u64)
+                            .as_bitcoin(8)
+                            .to_string()
+                    } else {
+                        format!(
+                            "-{}",
+                            Amount::from_msats(balance_sheet.surplus_msat.unsigned_abs())
+                                .as_bitcoin(8),
+                        )
+                    };
+                    let chart_data = history
+                        .into_iter()
+                        .map(|(date, balance_sheet)| {
+                            (
+                                NaiveDateTime::from(date).and_utc(),
+                                msat_to_btc(balance_sheet.assets),
+                                msat_to_btc(
+                                    balance_sheet.ecash_liabilities
+                                        + balance_sheet.lightning_liabilities,
+                                ),
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    view! {
+                        <div>
+                            <Alert
+                                message="Assets are reconstructed from the public federation log and on-chain transactions. Fees collected by the federation are part of the surplus."
+                                level=AlertLevel::Info
+                                class="my-4"
+                            />
+                            <table class="w-full text-sm text-left rtl:text-right text-gray-500 dark:text-gray-400">
+                                <tbody>
+                                    <tr class="bg-white border-b dark:bg-gray-800 dark:border-gray-700">
+                                        <th scope="row" class="px-6 py-4 font-medium text-gray-900 dark:text-white">
+                                            "On-chain Assets"
+                                        </th>
+                                        <td class="px-6 py-4">{balance_sheet.assets.as_bitcoin(8).to_string()}</td>
+                                    </tr>
+                                    <tr class="bg-white border-b dark:bg-gray-800 dark:border-gray-700">
+                                        <th scope="row" class="px-6 py-4 font-medium text-gray-900 dark:text-white">
+                                            "Outstanding E-Cash"
+                                        </th>
+                                        <td class="px-6 py-4">{balance_sheet.ecash_liabilities.as_bitcoin(8).to_string()}</td>
+                                    </tr>
+                                    <tr class="bg-white border-b dark:bg-gray-800 dark:border-gray-700">
+                                        <th scope="row" class="px-6 py-4 font-medium text-gray-900 dark:text-white">
+                                            "Unclaimed Lightning Contracts"
+                                        </th>
+                                        <td class="px-6 py-4">{balance_sheet.lightning_liabilities.as_bitcoin(8).to_string()}</td>
+                                    </tr>
+                                    <tr class="bg-white dark:bg-gray-800">
+                                        <th scope="row" class="px-6 py-4 font-medium text-gray-900 dark:text-white">
+                                            "Surplus"
+                                        </th>
+                                        <td class="px-6 py-4">{surplus}</td>
+                                    </tr>
+                                </tbody>
+                            </table>
+                            <div class="w-full bg-white rounded-lg shadow dark:bg-gray-800 p-4 md:p-6 my-4">
+                                <BalanceSheetChart data=chart_data/>
+                            </div>
+                        </div>
+                    }
+                        .into_view()
+                }
+                Some(Err(e)) => view! { <p>"Error: " {e}</p> }.into_view(),
+                None => view! { <p>"Loading ..."</p> }.into_view(),
+            }
+        }}
+    }
+}
+
+#[component]
+fn BalanceSheetChart(data: Vec<(DateTime<Utc>, f64, f64)>) -> impl IntoView {
+    view! {
+        <Chart
+            aspect_ratio=AspectRatio::from_env_width(300.0)
+            top=RotatedLabel::middle("Assets vs. Liabilities")
+            left=TickLabels::aligned_floats()
+            bottom=TickLabels::from_generator(Timestamps::from_period(Period::Month))
+            inner=[
+                AxisMarker::left_edge().into_inner(),
+                AxisMarker::bottom_edge().into_inner(),
+                XGridLine::default().into_inner(),
+                YGridLine::default().into_inner(),
+                XGuideLine::over_data().into_inner(),
+                YGuideLine::over_mouse().into_inner(),
+            ]
+            series=Series::new(|data: &(DateTime<Utc>, f64, f64)| data.0)
+                .line(
+                    Line::new(|data: &(DateTime<Utc>, f64, f64)| data.1)
+                        .with_name("Assets")
+                        .with_interpolation(Interpolation::Linear),
+                )
+                .line(
+                    Line::new(|data: &(DateTime<Utc>, f64, f64)| data.2)
+                        .with_name("Liabilities")
+                        .with_interpolation(Interpolation::Linear),
+                )
+
+            data=move || data.clone()
+        />
+    }
+}
+
+fn msat_to_btc(amount: Amount) -> f64 {
+    amount.msats as f64 / 100_000_000_000.0
+}
+
+async fn fetch_balance_sheet(
+    federation_id: FederationId,
+) -> Result<FederationBalanceSheet, String> {
+    let url = format!("{}/federations/{}/solvency", crate::BASE_URL, federation_id);
+    let res = reqwest::get(&url).await.map_err(|e| e.to_string())?;
+    let json = res.json().await.map_err(|e| e.to_string())?;
+    Ok(json)
+}
+
+async fn fetch_balance_sheet_history(
+    federation_id: FederationId,
+) -> Result<BTreeMap<NaiveDate, FederationBalanceSheet>, String> {
+    let url = format!(
+        "{}/federations/{}/solvency/history",
+        crate::BASE_URL,
+        federation_id
+    );
+    let res = reqwest::get(&url).await.map_err(|e| e.to_string())?;
+    let json = res.json().await.map_err(|e| e.to_string())?;
+    Ok(json)
+}
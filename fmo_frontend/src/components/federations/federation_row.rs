@@ -45,6 +45,7 @@ pub fn FederationRow(
                 <Rating
                     count=rating.count
                     rating=rating.avg
+                    weighted_rating=rating.weighted_avg
                 />
             </td>
             <td class="px-6 py-4">
@@ -1,9 +1,11 @@
 use leptos::{component, view, IntoView};
 
 #[component]
-pub fn Rating(count: u64, rating: Option<f64>) -> impl IntoView {
+pub fn Rating(count: u64, rating: Option<f64>, weighted_rating: Option<f64>) -> impl IntoView {
+    let title = weighted_rating
+        .map(|weighted_rating| format!("Weighted by author trust and age: {weighted_rating:.1}"));
     view! {
-        <div class="flex justify-center">
+        <div class="flex justify-center" title=title>
             <div>
                 <div class="flex items-center">
                     <svg class="w-4 h-4 text-yellow-300 me-1" aria-hidden="true" xmlns="http://www.w3.org/2000/svg" fill="currentColor" viewBox="0 0 22 20">
@@ -8,12 +8,14 @@ use leptos::{component, create_resource, view, IntoView, SignalGet};
 use num_format::{Locale, ToFormattedString};
 use tracing::error;
 
-use crate::util::{AsBitcoin, FmtBitcoin};
+use crate::util::{use_session_events, AsBitcoin, FmtBitcoin};
 
 #[component]
 pub fn Totals() -> impl IntoView {
+    // Refetch the totals whenever any federation finishes a session
+    let sessions = use_session_events("/federations/events");
     let totals_res = create_resource(
-        || (),
+        move || sessions.get(),
         |_| async {
             retry(
                 "fetching federation totals",
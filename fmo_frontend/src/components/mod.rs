@@ -3,6 +3,7 @@ mod copyable;
 mod federation;
 mod federations;
 mod navbar;
+mod search;
 mod tabs;
 
 pub use copyable::Copyable;
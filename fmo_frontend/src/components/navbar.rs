@@ -1,5 +1,7 @@
 use leptos::{component, view, IntoView};
 
+use crate::components::search::SearchBar;
+
 pub struct NavItem {
     pub name: String,
     pub href: String,
@@ -45,7 +47,10 @@ pub fn NavBar(items: Vec<NavItem>) -> impl IntoView {
                         Fedimint Observer
                     </span>
                 </a>
-                <div class="flex md:order-2">
+                <div class="flex items-center md:order-2">
+                    <div class="mr-3">
+                        <SearchBar/>
+                    </div>
                     <a
                         href="https://github.com/elsirion/fedimint-observer/"
                         class="inline-flex items-center justify-center h-9 mr-3 px-3 text-xs font-medium text-gray-900 bg-white border border-gray-200 rounded-lg focus:outline-none hover:bg-gray-100 hover:text-blue-700 focus:z-10 focus:ring-2 focus:ring-gray-300 dark:focus:ring-gray-500 dark:bg-gray-800 dark:text-gray-400 dark:border-gray-600 dark:hover:text-white dark:hover:bg-gray-700"
@@ -0,0 +1,161 @@
+use fmo_api_types::SearchMatch;
+use leptos::{
+    component, create_action, create_effect, create_signal, event_target_value, view, IntoView,
+    SignalGet, SignalSet,
+};
+use leptos_router::use_navigate;
+
+use crate::BASE_URL;
+
+#[component]
+pub fn SearchBar() -> impl IntoView {
+    let (query, set_query) = create_signal(String::new());
+    let search_action = create_action(|query: &String| {
+        let query = query.clone();
+        async move { search(&query).await.map_err(|e| e.to_string()) }
+    });
+
+    // If there is exactly one match we can go there directly, otherwise the
+    // user has to pick one from the dropdown
+    let navigate = use_navigate();
+    create_effect(move |_| {
+        if let Some(Ok(matches)) = search_action.value().get() {
+            if let [single_match] = matches.as_slice() {
+                if let Some(href) = match_href(single_match) {
+                    set_query.set(String::new());
+                    search_action.value().set(None);
+                    navigate(&href, Default::default());
+                }
+            }
+        }
+    });
+
+    view! {
+        <form
+            class="relative w-full md:w-80"
+            on:submit=move |ev| {
+                ev.prevent_default();
+                let query = query.get();
+                if !query.trim().is_empty() {
+                    search_action.dispatch(query);
+                }
+            }
+        >
+            <input
+                type="search"
+                class="block w-full p-2 text-sm text-gray-900 border border-gray-300 rounded-lg bg-gray-50 focus:ring-blue-500 focus:border-blue-500 dark:bg-gray-700 dark:border-gray-600 dark:placeholder-gray-400 dark:text-white dark:focus:ring-blue-500 dark:focus:border-blue-500"
+                placeholder="Federation id, invite, txid, address, …"
+                on:input=move |ev| set_query.set(event_target_value(&ev))
+                prop:value=query
+            />
+            {move || {
+                match search_action.value().get() {
+                    Some(Ok(matches)) if matches.is_empty() => view! {
+                        <div class="absolute z-10 mt-1 w-full p-2 text-sm text-gray-500 bg-white rounded-lg shadow dark:bg-gray-700 dark:text-gray-400">
+                            "No results"
+                        </div>
+                    }
+                    .into_view(),
+                    Some(Ok(matches)) if matches.len() > 1 => {
+                        let items = matches
+                            .iter()
+                            .map(|search_match| {
+                                let item = match match_href(search_match) {
+                                    Some(href) => view! {
+                                        <a
+                                            href=href
+                                            class="block px-4 py-2 truncate hover:bg-gray-100 dark:hover:bg-gray-600 dark:hover:text-white"
+                                            on:click=move |_| search_action.value().set(None)
+                                        >
+                                            {match_label(search_match)}
+                                        </a>
+                                    }
+                                    .into_view(),
+                                    None => view! {
+                                        <span class="block px-4 py-2 truncate text-gray-500 dark:text-gray-400">
+                                            {match_label(search_match)}
+                                        </span>
+                                    }
+                                    .into_view(),
+                                };
+                                view! { <li>{item}</li> }
+                            })
+                            .collect::<Vec<_>>();
+                        view! {
+                            <ul class="absolute z-10 mt-1 w-full py-2 text-sm text-gray-700 bg-white rounded-lg shadow dark:bg-gray-700 dark:text-gray-200">
+                                {items}
+                            </ul>
+                        }
+                        .into_view()
+                    }
+                    Some(Err(e)) => view! {
+                        <div class="absolute z-10 mt-1 w-full p-2 text-sm text-red-800 bg-red-50 rounded-lg shadow dark:bg-gray-700 dark:text-red-400">
+                            {e}
+                        </div>
+                    }
+                    .into_view(),
+                    _ => view! {}.into_view(),
+                }
+            }}
+        </form>
+    }
+}
+
+/// Federation pages only exist for observed federations, so unobserved ones
+/// aren't linked
+fn match_href(search_match: &SearchMatch) -> Option<String> {
+    if let SearchMatch::Federation {
+        observed: false, ..
+    } = search_match
+    {
+        return None;
+    }
+    Some(format!("/federations/{}", search_match.federation_id()))
+}
+
+fn match_label(search_match: &SearchMatch) -> String {
+    match search_match {
+        SearchMatch::Federation {
+            federation_id,
+            name,
+            observed,
+        } => {
+            let name = name.clone().unwrap_or_else(|| federation_id.to_string());
+            if *observed {
+                format!("Federation {name}")
+            } else {
+                format!("Federation {name} (not observed)")
+            }
+        }
+        SearchMatch::Transaction {
+            txid,
+            session_index,
+            ..
+        } => format!("Transaction {txid} in session {session_index}"),
+        SearchMatch::Session {
+            federation_id,
+            session_index,
+        } => format!("Session {session_index} of {federation_id}"),
+        SearchMatch::LightningContract {
+            contract_id,
+            contract_type,
+            ..
+        } => format!("Lightning {contract_type} contract {contract_id}"),
+        SearchMatch::PegIn {
+            on_chain_txid,
+            on_chain_vout,
+            ..
+        } => format!("Peg-in {on_chain_txid}:{on_chain_vout}"),
+        SearchMatch::PegOut { on_chain_txid, .. } => format!("Peg-out {on_chain_txid}"),
+        SearchMatch::DepositAddress { address, .. } => format!("Deposit address {address}"),
+        SearchMatch::WithdrawalAddress { address, .. } => {
+            format!("Withdrawal address {address}")
+        }
+    }
+}
+
+async fn search(query: &str) -> anyhow::Result<Vec<SearchMatch>> {
+    let url = reqwest::Url::parse_with_params(&format!("{}/search", BASE_URL), &[("q", query)])?;
+    let response = reqwest::get(url).await?;
+    Ok(response.json().await?)
+}
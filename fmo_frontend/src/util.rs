@@ -1,6 +1,10 @@
 use std::fmt::Display;
 
 use fedimint_core::Amount;
+use leptos::{create_signal, on_cleanup, ReadSignal, SignalUpdate};
+use tracing::warn;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
 
 pub struct FmtBitcoin {
     amount: Amount,
@@ -30,3 +34,34 @@ impl AsBitcoin for Amount {
         }
     }
 }
+
+/// Counts the `session` events received from the server-sent event stream at
+/// `path`. Can be used as resource source to refetch data whenever a new
+/// session was observed. The connection is closed when the calling component
+/// is cleaned up.
+pub fn use_session_events(path: &str) -> ReadSignal<u64> {
+    let (sessions, set_sessions) = create_signal(0u64);
+
+    let url = format!("{}{}", crate::BASE_URL, path);
+    let event_source = match web_sys::EventSource::new(&url) {
+        Ok(event_source) => event_source,
+        Err(e) => {
+            warn!("Failed to subscribe to live events: {e:?}");
+            return sessions;
+        }
+    };
+
+    let on_session = Closure::<dyn FnMut(web_sys::MessageEvent)>::new(move |_| {
+        set_sessions.update(|sessions| *sessions += 1);
+    });
+    event_source
+        .add_event_listener_with_callback("session", on_session.as_ref().unchecked_ref())
+        .expect("Valid event listener");
+
+    on_cleanup(move || {
+        event_source.close();
+        drop(on_session);
+    });
+
+    sessions
+}
@@ -0,0 +1,12 @@
+INSERT INTO schema_version (version)
+VALUES (10);
+
+-- Progress of rebuilding the derived tables of a federation from its stored sessions, one row per federation
+CREATE TABLE IF NOT EXISTS reindex_jobs
+(
+    federation_id      BYTEA PRIMARY KEY REFERENCES federations (federation_id),
+    -- next session to be processed, everything before it was already reindexed
+    next_session_index INTEGER   NOT NULL,
+    started_at         TIMESTAMP NOT NULL,
+    finished_at        TIMESTAMP
+);
@@ -0,0 +1,43 @@
+INSERT INTO schema_version (version)
+VALUES (11);
+
+-- HTTP endpoints federation events are posted to, see `FederationEvent`
+CREATE TABLE IF NOT EXISTS webhooks
+(
+    webhook_id          SERIAL PRIMARY KEY,
+    url                 TEXT      NOT NULL,
+    -- HMAC-SHA256 key used to sign deliveries
+    secret              TEXT      NOT NULL,
+    -- NULL matches all federations
+    federation_id       BYTEA REFERENCES federations (federation_id),
+    -- event kinds to deliver
+    events              TEXT[]    NOT NULL,
+    -- only applies to peg-out events
+    min_withdrawal_msat BIGINT,
+    created_at          TIMESTAMP NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS webhook_deliveries
+(
+    delivery_id     BIGSERIAL PRIMARY KEY,
+    webhook_id      INTEGER   NOT NULL REFERENCES webhooks (webhook_id) ON DELETE CASCADE,
+    event           JSONB     NOT NULL,
+    created_at      TIMESTAMP NOT NULL,
+    attempts        INTEGER   NOT NULL DEFAULT 0,
+    -- NULL once delivered or given up on
+    next_attempt_at TIMESTAMP,
+    delivered_at    TIMESTAMP,
+    last_status     INTEGER,
+    last_error      TEXT
+);
+CREATE INDEX IF NOT EXISTS webhook_deliveries_pending ON webhook_deliveries (next_attempt_at) WHERE next_attempt_at IS NOT NULL;
+CREATE INDEX IF NOT EXISTS webhook_deliveries_webhook ON webhook_deliveries (webhook_id, delivery_id);
+
+-- Last client config and meta fields fetched from the guardians, used to detect changes
+CREATE TABLE IF NOT EXISTS federation_config_snapshots
+(
+    federation_id BYTEA PRIMARY KEY REFERENCES federations (federation_id),
+    config        JSONB     NOT NULL,
+    meta          JSONB     NOT NULL,
+    checked_at    TIMESTAMP NOT NULL
+);
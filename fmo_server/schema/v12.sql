@@ -0,0 +1,38 @@
+INSERT INTO schema_version (version)
+VALUES (12);
+
+CREATE TABLE IF NOT EXISTS alert_rules
+(
+    rule_id       SERIAL PRIMARY KEY,
+    name          TEXT      NOT NULL,
+    -- NULL applies the rule to all federations
+    federation_id BYTEA REFERENCES federations (federation_id),
+    -- serialized `AlertCondition`
+    condition     JSONB     NOT NULL,
+    created_at    TIMESTAMP NOT NULL
+);
+
+INSERT INTO alert_rules (name, condition, created_at)
+VALUES ('Guardian unreachable', '{"kind": "guardian_unreachable", "for_secs": 300}', now() AT TIME ZONE 'utc'),
+       ('Block height lag', '{"kind": "block_height_lag", "max_lag_blocks": 3}', now() AT TIME ZONE 'utc'),
+       ('Guardians below threshold', '{"kind": "guardians_below_threshold"}', now() AT TIME ZONE 'utc'),
+       ('High API latency', '{"kind": "latency_p95", "max_latency_ms": 2000, "window_secs": 900}',
+        now() AT TIME ZONE 'utc');
+
+-- Current state of every rule per federation and guardian
+CREATE TABLE IF NOT EXISTS alerts
+(
+    alert_id      BIGSERIAL PRIMARY KEY,
+    rule_id       INTEGER          NOT NULL REFERENCES alert_rules (rule_id) ON DELETE CASCADE,
+    federation_id BYTEA            NOT NULL REFERENCES federations (federation_id),
+    -- NULL for federation-wide alerts
+    peer_id       INTEGER,
+    state         TEXT             NOT NULL CHECK (state IN ('firing', 'resolved')),
+    value         DOUBLE PRECISION NOT NULL,
+    message       TEXT             NOT NULL,
+    fired_at      TIMESTAMP        NOT NULL,
+    resolved_at   TIMESTAMP,
+    updated_at    TIMESTAMP        NOT NULL
+);
+CREATE UNIQUE INDEX IF NOT EXISTS alerts_subject ON alerts (rule_id, federation_id, COALESCE(peer_id, -1));
+CREATE INDEX IF NOT EXISTS alerts_state ON alerts (state);
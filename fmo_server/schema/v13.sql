@@ -0,0 +1,39 @@
+INSERT INTO schema_version (version)
+VALUES (13);
+
+-- Ratings are parameterized replaceable events, so only the latest one per author and federation counts
+ALTER TABLE nostr_votes
+    ADD COLUMN IF NOT EXISTS author     BYTEA,
+    ADD COLUMN IF NOT EXISTS created_at TIMESTAMP;
+
+UPDATE nostr_votes
+SET author     = decode(event ->> 'pubkey', 'hex'),
+    created_at = to_timestamp((event ->> 'created_at')::BIGINT) AT TIME ZONE 'utc';
+
+DELETE
+FROM nostr_votes nv
+WHERE EXISTS (SELECT 1
+              FROM nostr_votes newer
+              WHERE newer.author = nv.author
+                AND newer.federation_id = nv.federation_id
+                AND (newer.created_at, newer.event_id) > (nv.created_at, nv.event_id));
+
+ALTER TABLE nostr_votes
+    ALTER COLUMN author SET NOT NULL,
+    ALTER COLUMN created_at SET NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS nostr_votes_author_federation ON nostr_votes (author, federation_id);
+
+-- NIP-09 deletions of ratings, kept so deleted ratings aren't re-inserted when fetched again
+CREATE TABLE IF NOT EXISTS nostr_vote_deletions
+(
+    deletion_event_id BYTEA     NOT NULL,
+    author            BYTEA     NOT NULL,
+    -- deleted event (`e` tag)
+    event_id          BYTEA,
+    -- federation of a deleted replaceable rating (`a` tag), deletes all versions up to `created_at`
+    federation_id     BYTEA,
+    created_at        TIMESTAMP NOT NULL,
+    CHECK ((event_id IS NULL) != (federation_id IS NULL))
+);
+CREATE UNIQUE INDEX IF NOT EXISTS nostr_vote_deletions_target ON nostr_vote_deletions (deletion_event_id, COALESCE(event_id, federation_id));
+CREATE INDEX IF NOT EXISTS nostr_vote_deletions_author ON nostr_vote_deletions (author);
@@ -0,0 +1,14 @@
+INSERT INTO schema_version (version)
+VALUES (14);
+
+-- Relay URLs are compared in their normalized form, which has a trailing slash if there is no path
+UPDATE nostr_relays SET relay_url = relay_url || '/' WHERE relay_url ~ '^wss?://[^/]+$';
+
+ALTER TABLE nostr_relays
+    ADD COLUMN added_at TIMESTAMP NOT NULL DEFAULT (NOW() AT TIME ZONE 'utc'),
+    ADD COLUMN connected BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN events_fetched BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN publish_attempts BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN publish_successes BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN last_error TEXT,
+    ADD COLUMN last_error_at TIMESTAMP;
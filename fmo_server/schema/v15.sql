@@ -0,0 +1,17 @@
+INSERT INTO schema_version (version)
+VALUES (15);
+
+-- Federations announced on Nostr (NIP-87 kind 38173 events), merged across all announcements of a federation
+CREATE TABLE IF NOT EXISTS discovered_federations
+(
+    federation_id BYTEA     NOT NULL PRIMARY KEY,
+    invite_codes  TEXT[]    NOT NULL,
+    network       TEXT,
+    -- latest announcement
+    event         JSONB     NOT NULL,
+    first_seen    TIMESTAMP NOT NULL,
+    last_seen     TIMESTAMP NOT NULL,
+    status        TEXT      NOT NULL CHECK (status IN ('pending', 'added', 'dismissed', 'failed')),
+    last_error    TEXT
+);
+CREATE INDEX IF NOT EXISTS discovered_federations_status ON discovered_federations (status);
@@ -0,0 +1,11 @@
+INSERT INTO schema_version (version)
+VALUES (16);
+
+-- Latest NIP-01 kind 0 profile metadata of rating authors, NULL if they don't have one
+CREATE TABLE IF NOT EXISTS nostr_profiles
+(
+    author     BYTEA     NOT NULL PRIMARY KEY,
+    metadata   JSONB,
+    created_at TIMESTAMP,
+    fetched_at TIMESTAMP NOT NULL
+);
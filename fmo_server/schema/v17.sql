@@ -0,0 +1,11 @@
+INSERT INTO schema_version (version)
+VALUES (17);
+
+-- Latest NIP-02 contact lists of the trusted pubkeys used for weighting ratings
+CREATE TABLE IF NOT EXISTS nostr_contacts
+(
+    author     BYTEA     NOT NULL PRIMARY KEY,
+    contacts   BYTEA[]   NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    fetched_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,31 @@
+INSERT INTO schema_version (version)
+VALUES (18);
+
+CREATE TABLE IF NOT EXISTS api_keys
+(
+    key_id       SERIAL PRIMARY KEY,
+    name         TEXT      NOT NULL,
+    -- SHA256 of the secret, the secret itself is only shown once when minting the key
+    key_hash     BYTEA     NOT NULL UNIQUE,
+    scopes       TEXT[]    NOT NULL,
+    created_at   TIMESTAMP NOT NULL,
+    expires_at   TIMESTAMP,
+    revoked_at   TIMESTAMP,
+    last_used_at TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS admin_audit_log
+(
+    entry_id   BIGSERIAL PRIMARY KEY,
+    -- NULL for the admin token from the server config or failed authentication
+    key_id     INTEGER REFERENCES api_keys (key_id),
+    principal  TEXT,
+    method     TEXT      NOT NULL,
+    path       TEXT      NOT NULL,
+    scope      TEXT,
+    status     INTEGER   NOT NULL,
+    -- failed authentication attempts are aggregated per route and hour
+    attempts   INTEGER   NOT NULL DEFAULT 1,
+    created_at TIMESTAMP NOT NULL
+);
+CREATE INDEX IF NOT EXISTS admin_audit_log_created_at ON admin_audit_log (created_at);
@@ -0,0 +1,21 @@
+INSERT INTO schema_version (version)
+VALUES (19);
+
+-- Configs fetched by the config inspector, so they survive restarts
+CREATE TABLE IF NOT EXISTS config_cache
+(
+    federation_id BYTEA PRIMARY KEY,
+    config        JSONB     NOT NULL,
+    fetched_at    TIMESTAMP NOT NULL,
+    -- needed to refresh the config in the background
+    invite_code   TEXT      NOT NULL
+);
+
+-- Every distinct config version the config inspector has seen
+CREATE TABLE IF NOT EXISTS config_cache_history
+(
+    federation_id BYTEA     NOT NULL,
+    config        JSONB     NOT NULL,
+    fetched_at    TIMESTAMP NOT NULL,
+    PRIMARY KEY (federation_id, fetched_at)
+);
@@ -0,0 +1,22 @@
+INSERT INTO schema_version (version)
+VALUES (6);
+
+-- The fee of a transaction is whatever the inputs are worth more than the outputs. Inputs/outputs of unknown modules
+-- have no amount and are thus ignored.
+CREATE MATERIALIZED VIEW transaction_fees AS
+WITH input_sums AS (SELECT federation_id, txid, SUM(amount_msat) AS amount_msat
+                    FROM transaction_inputs
+                    GROUP BY federation_id, txid),
+     output_sums AS (SELECT federation_id, txid, SUM(amount_msat) AS amount_msat
+                     FROM transaction_outputs
+                     GROUP BY federation_id, txid)
+SELECT t.federation_id,
+       t.txid,
+       t.session_index,
+       (COALESCE(i.amount_msat, 0) - COALESCE(o.amount_msat, 0))::BIGINT AS fee_msat
+FROM transactions t
+         LEFT JOIN input_sums i ON t.federation_id = i.federation_id AND t.txid = i.txid
+         LEFT JOIN output_sums o ON t.federation_id = o.federation_id AND t.txid = o.txid;
+
+CREATE UNIQUE INDEX transaction_fees_federation_id_txid_idx ON transaction_fees (federation_id, txid);
+CREATE INDEX transaction_fees_federation_id_session_index_idx ON transaction_fees (federation_id, session_index);
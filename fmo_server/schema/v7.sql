@@ -0,0 +1,59 @@
+INSERT INTO schema_version (version)
+VALUES (7);
+
+ALTER TABLE wallet_withdrawal_transactions
+    -- session in which the threshold number of peg-out signatures was reached
+    ADD COLUMN threshold_session_index   INTEGER,
+    -- on-chain data that is only available from an explorer, filled in asynchronously
+    ADD COLUMN weight                    INTEGER,
+    ADD COLUMN confirmation_block_height INTEGER,
+    -- transactions that were replaced or never confirm are polled with backoff and eventually given up on
+    ADD COLUMN details_attempts          INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN details_next_attempt_at   TIMESTAMP,
+    ADD COLUMN details_abandoned_at      TIMESTAMP;
+
+CREATE INDEX IF NOT EXISTS wallet_withdrawal_transactions_federation ON wallet_withdrawal_transactions (federation_id);
+
+-- All peg-out inputs spend federation UTXOs, so their amounts are known from either a peg-in or the change output of
+-- a previous peg-out. If any of them is unknown we can't calculate the fee.
+CREATE OR REPLACE VIEW wallet_withdrawal_stats AS
+WITH input_sums AS (SELECT wwti.on_chain_txid,
+                           SUM(COALESCE(wpi.amount_msat, wwto.amount_msat))                AS amount_msat,
+                           BOOL_AND(COALESCE(wpi.amount_msat, wwto.amount_msat) IS NOT NULL) AS complete
+                    FROM wallet_withdrawal_transaction_inputs wwti
+                             LEFT JOIN wallet_peg_ins wpi
+                                       ON wpi.on_chain_txid = wwti.previous_output_txid
+                                           AND wpi.on_chain_vout = wwti.previous_output_vout
+                             LEFT JOIN wallet_withdrawal_transaction_outputs wwto
+                                       ON wwto.on_chain_txid = wwti.previous_output_txid
+                                           AND wwto.on_chain_vout = wwti.previous_output_vout
+                    GROUP BY wwti.on_chain_txid),
+     output_sums AS (SELECT on_chain_txid, SUM(amount_msat) AS amount_msat
+                     FROM wallet_withdrawal_transaction_outputs
+                     GROUP BY on_chain_txid)
+SELECT wwt.federation_id,
+       wwt.on_chain_txid,
+       wwt.federation_txid,
+       request.session_index                                     AS request_session_index,
+       wwt.threshold_session_index,
+       wwt.confirmation_block_height,
+       wwt.weight,
+       CASE WHEN i.complete THEN ((i.amount_msat - o.amount_msat) / 1000)::BIGINT END AS fee_sat,
+       request_st.estimated_session_timestamp                    AS request_time,
+       threshold_st.estimated_session_timestamp                  AS threshold_time,
+       bt.timestamp                                              AS confirmation_time
+FROM wallet_withdrawal_transactions wwt
+         LEFT JOIN LATERAL (SELECT wwa.session_index
+                            FROM wallet_withdrawal_addresses wwa
+                            WHERE wwa.federation_id = wwt.federation_id
+                              AND wwa.txid = wwt.federation_txid
+                            LIMIT 1) request ON TRUE
+         LEFT JOIN input_sums i ON i.on_chain_txid = wwt.on_chain_txid
+         LEFT JOIN output_sums o ON o.on_chain_txid = wwt.on_chain_txid
+         LEFT JOIN session_times request_st
+                   ON request_st.federation_id = wwt.federation_id
+                       AND request_st.session_index = request.session_index
+         LEFT JOIN session_times threshold_st
+                   ON threshold_st.federation_id = wwt.federation_id
+                       AND threshold_st.session_index = wwt.threshold_session_index
+         LEFT JOIN block_times bt ON bt.block_height = wwt.confirmation_block_height;
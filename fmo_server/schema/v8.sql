@@ -0,0 +1,14 @@
+INSERT INTO schema_version (version)
+VALUES (8);
+
+CREATE TABLE IF NOT EXISTS feerate_votes
+(
+    federation_id BYTEA   NOT NULL REFERENCES federations (federation_id),
+    session_index INTEGER NOT NULL,
+    item_index    INTEGER NOT NULL,
+    proposer      INTEGER NOT NULL,
+    sats_per_kvb  BIGINT  NOT NULL,
+    PRIMARY KEY (federation_id, session_index, item_index),
+    FOREIGN KEY (federation_id, session_index) REFERENCES sessions (federation_id, session_index)
+);
+CREATE INDEX IF NOT EXISTS feerate_vote_federation_proposers ON feerate_votes (federation_id, proposer, session_index);
@@ -0,0 +1,48 @@
+INSERT INTO schema_version (version)
+VALUES (9);
+
+-- Number of accepted consensus items each guardian proposed per session, peers without items have no row
+CREATE TABLE IF NOT EXISTS session_peer_items
+(
+    federation_id BYTEA   NOT NULL REFERENCES federations (federation_id),
+    session_index INTEGER NOT NULL,
+    peer_id       INTEGER NOT NULL,
+    items         INTEGER NOT NULL,
+    PRIMARY KEY (federation_id, session_index, peer_id),
+    FOREIGN KEY (federation_id, session_index) REFERENCES sessions (federation_id, session_index)
+);
+CREATE INDEX IF NOT EXISTS session_peer_items_federation_peers ON session_peer_items (federation_id, peer_id, session_index);
+
+-- Stretches of consecutive sessions in which a guardian proposed nothing while others did. Sessions without any items
+-- aren't counted as inactivity since nobody had anything to propose. Consecutive missed sessions are grouped by the
+-- difference between their rank among all active sessions and their rank among the missed ones, which is constant
+-- within a stretch. Guardians that never proposed anything have no rows.
+CREATE MATERIALIZED VIEW guardian_inactive_ranges AS
+WITH active_sessions AS (SELECT federation_id,
+                                session_index,
+                                ROW_NUMBER() OVER (PARTITION BY federation_id ORDER BY session_index) AS active_rank
+                         FROM (SELECT DISTINCT federation_id, session_index
+                               FROM session_peer_items) s),
+     peers AS (SELECT DISTINCT federation_id, peer_id
+               FROM session_peer_items),
+     missed AS (SELECT p.federation_id,
+                       p.peer_id,
+                       a.session_index,
+                       a.active_rank -
+                       ROW_NUMBER() OVER (PARTITION BY p.federation_id, p.peer_id ORDER BY a.session_index) AS grp
+                FROM peers p
+                         JOIN active_sessions a ON a.federation_id = p.federation_id
+                         LEFT JOIN session_peer_items spi
+                                   ON spi.federation_id = a.federation_id
+                                       AND spi.session_index = a.session_index
+                                       AND spi.peer_id = p.peer_id
+                WHERE spi.peer_id IS NULL)
+SELECT federation_id,
+       peer_id,
+       MIN(session_index) AS start_session_index,
+       MAX(session_index) AS end_session_index,
+       COUNT(*)::BIGINT   AS sessions
+FROM missed
+GROUP BY federation_id, peer_id, grp;
+
+CREATE UNIQUE INDEX guardian_inactive_ranges_federation_id_peer_id_start_idx ON guardian_inactive_ranges (federation_id, peer_id, start_session_index);
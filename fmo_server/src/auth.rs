@@ -0,0 +1,588 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::{ensure, Context};
+use axum::extract::{FromRequestParts, MatchedPath, Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post, MethodRouter};
+use axum::{middleware, Extension, Json, Router};
+use axum_auth::AuthBearer;
+use bitcoin::hashes::{sha256, Hash};
+use chrono::NaiveDateTime;
+use fmo_api_types::{ApiKey, ApiScope, AuditLogEntry, CreatedApiKey, NewApiKey};
+use postgres_from_row::FromRow;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::federation::observer::FederationObserver;
+use crate::util::{execute, query, query_one, query_opt};
+use crate::AppState;
+
+/// Whoever authenticated a request, either the admin token from the server
+/// config or an API key. Available to handlers of routes using
+/// [`RequireScope::require_scope`] as an [`Extension`].
+#[derive(Debug, Clone)]
+pub struct Principal {
+    /// `None` for the admin token from the server config
+    key_id: Option<u32>,
+    name: String,
+    scopes: Vec<ApiScope>,
+}
+
+impl Principal {
+    /// Keys can only be minted and revoked by principals having all of their
+    /// scopes, so a limited key can't create a more powerful one or lock out
+    /// one
+    pub fn require_scopes(&self, scopes: &[ApiScope]) -> Result<(), AuthError> {
+        match scopes.iter().find(|scope| !self.scopes.contains(scope)) {
+            Some(&scope) => Err(AuthError::MissingScope {
+                principal: self.name.clone(),
+                scope,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Reasons to reject an admin request, which are reported with the matching
+/// status code instead of the usual 400
+#[derive(Debug)]
+pub enum AuthError {
+    MissingToken,
+    InvalidToken,
+    MissingScope { principal: String, scope: ApiScope },
+}
+
+impl std::fmt::Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthError::MissingToken => write!(f, "Missing bearer token"),
+            AuthError::InvalidToken => write!(f, "Invalid bearer token"),
+            AuthError::MissingScope { principal, scope } => {
+                write!(f, "{principal} lacks the {} scope", scope.name())
+            }
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        let message = format!("Error: {self}");
+        match self {
+            AuthError::MissingToken | AuthError::InvalidToken => (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Bearer")],
+                message,
+            )
+                .into_response(),
+            AuthError::MissingScope { .. } => (StatusCode::FORBIDDEN, message).into_response(),
+        }
+    }
+}
+
+/// What an admin request did, filled in by [`RequireScope::require_scope`] and
+/// written to the audit log by [`audit_admin_actions`] once the response status
+/// is known
+#[derive(Debug, Clone)]
+struct AuditRecord {
+    /// `None` if authentication failed
+    principal: Option<Principal>,
+    scope: ApiScope,
+    /// Route template, failed attempts are aggregated by it since the actual
+    /// path is chosen by whoever sends them
+    route: String,
+}
+
+/// Made available to admin routes by [`audit_admin_actions`]
+#[derive(Clone)]
+struct AdminContext {
+    federation_observer: FederationObserver,
+    audit: Arc<Mutex<Option<AuditRecord>>>,
+}
+
+/// Protects admin routes, requests have to carry a bearer token that is
+/// either the admin token from the server config or an API key with the
+/// route's scope
+pub trait RequireScope {
+    fn require_scope(self, scope: ApiScope) -> Self;
+}
+
+impl RequireScope for MethodRouter<AppState> {
+    fn require_scope(self, scope: ApiScope) -> Self {
+        self.route_layer(middleware::from_fn(
+            move |request: Request, next: Next| async move {
+                match authorize(scope, request).await {
+                    Ok(request) => next.run(request).await,
+                    Err(response) => response,
+                }
+            },
+        ))
+    }
+}
+
+async fn authorize(scope: ApiScope, request: Request) -> Result<Request, Response> {
+    let context = request
+        .extensions()
+        .get::<AdminContext>()
+        .cloned()
+        .expect("Admin routes are wrapped in audit_admin_actions");
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_owned())
+        .unwrap_or_default();
+
+    let (mut parts, body) = request.into_parts();
+    // Requests without any credentials aren't audited, they can't do anything
+    let AuthBearer(token) = AuthBearer::from_request_parts(&mut parts, &())
+        .await
+        .map_err(|_| AuthError::MissingToken.into_response())?;
+
+    let principal = match context.federation_observer.authenticate(&token).await {
+        Ok(principal) => principal,
+        Err(e) => {
+            warn!("Failed to authenticate admin request: {e:?}");
+            return Err(crate::error::AppError::from(e).into_response());
+        }
+    };
+
+    *context.audit.lock().expect("Lock poisoned") = Some(AuditRecord {
+        principal: principal.clone(),
+        scope,
+        route,
+    });
+
+    let principal = principal.ok_or_else(|| AuthError::InvalidToken.into_response())?;
+    principal
+        .require_scopes(&[scope])
+        .map_err(IntoResponse::into_response)?;
+
+    let mut request = Request::from_parts(parts, body);
+    request.extensions_mut().insert(principal);
+    Ok(request)
+}
+
+/// Middleware writing every request to a route protected by
+/// [`RequireScope::require_scope`] to the audit log
+pub async fn audit_admin_actions(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let audit = Arc::new(Mutex::new(None));
+    let method = request.method().to_string();
+    let path = request.uri().path().to_owned();
+    request.extensions_mut().insert(AdminContext {
+        federation_observer: state.federation_observer.clone(),
+        audit: audit.clone(),
+    });
+
+    let response = next.run(request).await;
+
+    let record = audit.lock().expect("Lock poisoned").take();
+    if let Some(record) = record {
+        if let Err(e) = state
+            .federation_observer
+            .write_audit_log(record, &method, &path, response.status().as_u16())
+            .await
+        {
+            warn!("Failed to write audit log: {e:?}");
+        }
+    }
+
+    response
+}
+
+pub fn get_auth_routes() -> Router<AppState> {
+    Router::new()
+        .route("/keys", get(list_api_keys).require_scope(ApiScope::ApiKeys))
+        .route(
+            "/keys",
+            post(create_api_key).require_scope(ApiScope::ApiKeys),
+        )
+        .route(
+            "/keys/:key_id",
+            delete(revoke_api_key).require_scope(ApiScope::ApiKeys),
+        )
+        .route(
+            "/audit",
+            get(list_audit_log).require_scope(ApiScope::ApiKeys),
+        )
+}
+
+#[derive(Debug, FromRow)]
+struct ApiKeyRow {
+    key_id: i32,
+    name: String,
+    scopes: Vec<String>,
+    created_at: NaiveDateTime,
+    expires_at: Option<NaiveDateTime>,
+    revoked_at: Option<NaiveDateTime>,
+    last_used_at: Option<NaiveDateTime>,
+}
+
+impl From<ApiKeyRow> for ApiKey {
+    fn from(row: ApiKeyRow) -> Self {
+        ApiKey {
+            key_id: row.key_id as u32,
+            name: row.name,
+            scopes: parse_scopes(&row.scopes),
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+            revoked_at: row.revoked_at,
+            last_used_at: row.last_used_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AuditLogParams {
+    #[serde(default = "default_audit_log_limit")]
+    limit: u32,
+}
+
+fn default_audit_log_limit() -> u32 {
+    100
+}
+
+async fn list_api_keys(State(state): State<AppState>) -> crate::error::Result<Json<Vec<ApiKey>>> {
+    Ok(state.federation_observer.list_api_keys().await?.into())
+}
+
+async fn create_api_key(
+    Extension(principal): Extension<Principal>,
+    State(state): State<AppState>,
+    Json(key): Json<NewApiKey>,
+) -> crate::error::Result<Json<CreatedApiKey>> {
+    principal.require_scopes(&key.scopes)?;
+    Ok(state.federation_observer.create_api_key(key).await?.into())
+}
+
+async fn revoke_api_key(
+    Extension(principal): Extension<Principal>,
+    Path(key_id): Path<u32>,
+    State(state): State<AppState>,
+) -> crate::error::Result<()> {
+    Ok(state
+        .federation_observer
+        .revoke_api_key(&principal, key_id)
+        .await?)
+}
+
+async fn list_audit_log(
+    Query(params): Query<AuditLogParams>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<Vec<AuditLogEntry>>> {
+    Ok(state
+        .federation_observer
+        .audit_log(params.limit)
+        .await?
+        .into())
+}
+
+impl FederationObserver {
+    /// Resolves a bearer token to the admin token from the server config or a
+    /// valid API key, `None` if it's neither
+    pub async fn authenticate(&self, token: &str) -> anyhow::Result<Option<Principal>> {
+        if self.is_admin_token(token) {
+            return Ok(Some(Principal {
+                key_id: None,
+                name: "root".to_owned(),
+                scopes: ApiScope::ALL.to_vec(),
+            }));
+        }
+
+        let key = query_opt::<ApiKeyRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            UPDATE api_keys
+            SET last_used_at = $2
+            WHERE key_hash = $1
+              AND revoked_at IS NULL
+              AND (expires_at IS NULL OR expires_at > $2)
+            RETURNING key_id, name, scopes, created_at, expires_at, revoked_at, last_used_at
+            ",
+            &[&hash_api_key(token), &chrono::Utc::now().naive_utc()],
+        )
+        .await?;
+
+        Ok(key.map(|key| Principal {
+            key_id: Some(key.key_id as u32),
+            scopes: parse_scopes(&key.scopes),
+            name: key.name,
+        }))
+    }
+
+    pub async fn list_api_keys(&self) -> anyhow::Result<Vec<ApiKey>> {
+        Ok(query::<ApiKeyRow>(
+            &self.connection().await?,
+            "SELECT key_id, name, scopes, created_at, expires_at, revoked_at, last_used_at FROM api_keys ORDER BY key_id",
+            &[],
+        )
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect())
+    }
+
+    pub async fn create_api_key(&self, key: NewApiKey) -> anyhow::Result<CreatedApiKey> {
+        ensure!(!key.name.is_empty(), "API key name must not be empty");
+        ensure!(!key.scopes.is_empty(), "No scopes given");
+        let now = chrono::Utc::now().naive_utc();
+        if let Some(expires_at) = key.expires_at {
+            ensure!(expires_at > now, "Expiry is in the past");
+        }
+
+        let secret = format!("fmo_{}", hex::encode(rand::random::<[u8; 32]>()));
+        let scopes = key
+            .scopes
+            .iter()
+            .map(|scope| scope.name().to_owned())
+            .collect::<Vec<_>>();
+
+        let key = query_one::<ApiKeyRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            INSERT INTO api_keys (name, key_hash, scopes, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING key_id, name, scopes, created_at, expires_at, revoked_at, last_used_at
+            ",
+            &[
+                &key.name,
+                &hash_api_key(&secret),
+                &scopes,
+                &now,
+                &key.expires_at,
+            ],
+        )
+        .await?;
+
+        info!("Created API key {} ({})", key.key_id, key.name);
+
+        Ok(CreatedApiKey {
+            key: key.into(),
+            secret,
+        })
+    }
+
+    pub async fn revoke_api_key(&self, principal: &Principal, key_id: u32) -> anyhow::Result<()> {
+        let mut conn = self.connection().await?;
+        let dbtx = conn.transaction().await?;
+
+        let scopes = query_opt::<ApiKeyRow>(
+            &dbtx,
+            // language=postgresql
+            "
+            SELECT key_id, name, scopes, created_at, expires_at, revoked_at, last_used_at
+            FROM api_keys
+            WHERE key_id = $1
+              AND revoked_at IS NULL
+            FOR UPDATE
+            ",
+            &[&(key_id as i32)],
+        )
+        .await?
+        .context("API key doesn't exist or is already revoked")?
+        .scopes;
+        principal.require_scopes(&parse_scopes(&scopes))?;
+
+        execute(
+            &dbtx,
+            "UPDATE api_keys SET revoked_at = $2 WHERE key_id = $1",
+            &[&(key_id as i32), &chrono::Utc::now().naive_utc()],
+        )
+        .await?;
+        dbtx.commit().await?;
+
+        info!("Revoked API key {key_id}");
+
+        Ok(())
+    }
+
+    /// Most recent audit log entries, newest first
+    pub async fn audit_log(&self, limit: u32) -> anyhow::Result<Vec<AuditLogEntry>> {
+        #[derive(Debug, FromRow)]
+        struct AuditLogRow {
+            entry_id: i64,
+            key_id: Option<i32>,
+            principal: Option<String>,
+            method: String,
+            path: String,
+            scope: Option<String>,
+            status: i32,
+            attempts: i32,
+            created_at: NaiveDateTime,
+        }
+
+        Ok(query::<AuditLogRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            SELECT entry_id, key_id, principal, method, path, scope, status, attempts, created_at
+            FROM admin_audit_log
+            ORDER BY entry_id DESC
+            LIMIT $1
+            ",
+            &[&(limit as i64)],
+        )
+        .await?
+        .into_iter()
+        .map(|row| AuditLogEntry {
+            entry_id: row.entry_id as u64,
+            key_id: row.key_id.map(|key_id| key_id as u32),
+            principal: row.principal,
+            method: row.method,
+            path: row.path,
+            scope: row
+                .scope
+                .and_then(|scope| parse_scopes(&[scope]).into_iter().next()),
+            status: row.status as u16,
+            attempts: row.attempts as u32,
+            created_at: row.created_at,
+        })
+        .collect())
+    }
+
+    async fn write_audit_log(
+        &self,
+        record: AuditRecord,
+        method: &str,
+        path: &str,
+        status: u16,
+    ) -> anyhow::Result<()> {
+        let now = chrono::Utc::now().naive_utc();
+
+        let Some(principal) = record.principal else {
+            // Failed attempts are counted per route and hour instead of being logged
+            // individually, otherwise anyone could grow the audit log without bound
+            execute(
+                &self.connection().await?,
+                // language=postgresql
+                "
+                WITH aggregated AS (
+                    UPDATE admin_audit_log
+                    SET attempts = attempts + 1
+                    WHERE entry_id = (SELECT entry_id
+                                      FROM admin_audit_log
+                                      WHERE principal IS NULL
+                                        AND method = $1
+                                        AND path = $2
+                                        AND status = $4
+                                        AND created_at >= date_trunc('hour', $5::TIMESTAMP)
+                                      ORDER BY entry_id DESC
+                                      LIMIT 1)
+                    RETURNING entry_id
+                )
+                INSERT INTO admin_audit_log (method, path, scope, status, created_at)
+                SELECT $1, $2, $3, $4, $5
+                WHERE NOT EXISTS (SELECT 1 FROM aggregated)
+                ",
+                &[
+                    &method,
+                    &record.route,
+                    &record.scope.name(),
+                    &(status as i32),
+                    &now,
+                ],
+            )
+            .await?;
+            return Ok(());
+        };
+
+        execute(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            INSERT INTO admin_audit_log (key_id, principal, method, path, scope, status, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ",
+            &[
+                &principal.key_id.map(|key_id| key_id as i32),
+                &principal.name,
+                &method,
+                &path,
+                &record.scope.name(),
+                &(status as i32),
+                &now,
+            ],
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+fn hash_api_key(secret: &str) -> Vec<u8> {
+    sha256::Hash::hash(secret.as_bytes())
+        .to_byte_array()
+        .to_vec()
+}
+
+/// Unknown scopes, e.g. from a newer version, are ignored
+fn parse_scopes(scopes: &[String]) -> Vec<ApiScope> {
+    scopes
+        .iter()
+        .filter_map(|scope| {
+            ApiScope::ALL
+                .into_iter()
+                .find(|known| known.name() == scope)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+    use fmo_api_types::ApiScope;
+
+    use super::{AuthError, Principal};
+    use crate::error::AppError;
+
+    #[test]
+    fn test_require_scopes() {
+        let principal = Principal {
+            key_id: Some(1),
+            name: "ci".to_owned(),
+            scopes: vec![ApiScope::Reindex, ApiScope::ApiKeys],
+        };
+
+        assert!(principal.require_scopes(&[]).is_ok());
+        assert!(principal
+            .require_scopes(&[ApiScope::ApiKeys, ApiScope::Reindex])
+            .is_ok());
+        assert!(matches!(
+            principal.require_scopes(&[ApiScope::Reindex, ApiScope::Webhooks]),
+            Err(AuthError::MissingScope {
+                scope: ApiScope::Webhooks,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_auth_error_status() {
+        let status = |error: AuthError| AppError::from(error).into_response().status();
+
+        assert_eq!(status(AuthError::MissingToken), StatusCode::UNAUTHORIZED);
+        assert_eq!(status(AuthError::InvalidToken), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            status(AuthError::MissingScope {
+                principal: "ci".to_owned(),
+                scope: ApiScope::Webhooks,
+            }),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            AppError::from(anyhow::anyhow!("Invalid invite code"))
+                .into_response()
+                .status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+
+use anyhow::{bail, Context};
+use clap::{Parser, Subcommand};
+use fedimint_core::api::InviteCode;
+use fedimint_core::config::FederationId;
+use tracing::{info, warn};
+
+use crate::federation::observer::FederationObserver;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Fedimint Observer API server")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// All commands except `serve` work on the database directly. Changes to the
+/// set of observed federations only take effect on a running server after a
+/// restart, use the admin API to apply them immediately.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the API server and observe all federations (default)
+    Serve,
+    /// Apply pending database migrations and exit
+    Migrate,
+    /// Manage observed federations
+    #[command(subcommand)]
+    Federation(FederationCommand),
+    /// Rebuild the tables derived from stored sessions without re-downloading
+    /// them from the guardians
+    Reindex {
+        /// Federation to reindex, all observed federations if omitted
+        federation_id: Option<FederationId>,
+        /// Only continue unfinished reindex jobs instead of starting over
+        #[arg(long)]
+        resume: bool,
+    },
+    /// Export the config and session log of a federation as newline-delimited
+    /// JSON
+    Export {
+        federation_id: FederationId,
+        /// File to write to, stdout if omitted
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Import a federation exported with `export` and rebuild its derived data
+    Import {
+        /// File to read from, stdin if omitted
+        input: Option<PathBuf>,
+    },
+    /// Check the stored data for inconsistencies, fails if any were found
+    Check,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum FederationCommand {
+    /// Download a federation's config and start observing it
+    Add { invite: InviteCode },
+    /// List observed federations
+    List,
+    /// Stop observing a federation and delete all data about it
+    Remove { federation_id: FederationId },
+}
+
+/// Runs any command but `serve`
+pub async fn run(command: Command) -> anyhow::Result<()> {
+    // The admin API isn't served, so no admin auth is needed
+    let observer = FederationObserver::connect(
+        &dotenv::var("FO_DATABASE").context("No FO_DATABASE provided")?,
+        "",
+    )
+    .await?;
+
+    match command {
+        Command::Serve => unreachable!("Handled by main"),
+        Command::Migrate => {
+            info!("Database schema is up to date");
+        }
+        Command::Federation(FederationCommand::Add { invite }) => {
+            match observer.download_federation(&invite).await? {
+                Some(federation) => info!("Added federation {}", federation.federation_id),
+                None => info!("Federation {} is already observed", invite.federation_id()),
+            }
+        }
+        Command::Federation(FederationCommand::List) => {
+            for federation in observer.list_federation_summaries().await? {
+                let sessions = observer.federation_session_count(federation.id).await?;
+                println!(
+                    "{}\t{}\t{sessions} sessions",
+                    federation.id,
+                    federation.name.as_deref().unwrap_or("-"),
+                );
+            }
+        }
+        Command::Federation(FederationCommand::Remove { federation_id }) => {
+            observer.remove_federation(federation_id).await?;
+        }
+        Command::Reindex {
+            federation_id,
+            resume,
+        } => {
+            let federation_ids = match federation_id {
+                Some(federation_id) => vec![federation_id],
+                None if resume => observer.unfinished_reindex_jobs().await?,
+                None => observer
+                    .list_federations()
+                    .await?
+                    .into_iter()
+                    .map(|federation| federation.federation_id)
+                    .collect(),
+            };
+
+            for federation_id in federation_ids {
+                if !(resume && observer.reindex_in_progress(federation_id).await?) {
+                    observer.start_reindex(federation_id).await?;
+                }
+                observer.run_reindex(federation_id).await?;
+            }
+        }
+        Command::Export {
+            federation_id,
+            output,
+        } => {
+            let sessions = match output {
+                Some(path) => {
+                    observer
+                        .export_federation(
+                            federation_id,
+                            BufWriter::new(File::create(path).context("Can't create output file")?),
+                        )
+                        .await?
+                }
+                None => {
+                    observer
+                        .export_federation(federation_id, std::io::stdout().lock())
+                        .await?
+                }
+            };
+            info!("Exported {sessions} sessions of {federation_id}");
+        }
+        Command::Import { input } => {
+            let federation_id = match input {
+                Some(path) => {
+                    observer
+                        .import_federation(BufReader::new(
+                            File::open(path).context("Can't open input file")?,
+                        ))
+                        .await?
+                }
+                None => observer.import_federation(std::io::stdin().lock()).await?,
+            };
+            observer.run_reindex(federation_id).await?;
+        }
+        Command::Check => {
+            let issues = observer.check_integrity().await?;
+            for issue in &issues {
+                warn!("{issue}");
+            }
+            if !issues.is_empty() {
+                bail!("Found {} issues", issues.len());
+            }
+            info!("No issues found");
+        }
+    }
+
+    Ok(())
+}
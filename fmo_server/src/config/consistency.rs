@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use axum::extract::Path;
+use axum::Json;
+use bitcoin::hashes::{sha256, Hash};
+use fedimint_core::api::{DynGlobalApi, FederationApiExt, InviteCode};
+use fedimint_core::config::{ClientConfig, PeerUrl};
+use fedimint_core::encoding::Encodable;
+use fedimint_core::endpoint_constants::CLIENT_CONFIG_ENDPOINT;
+use fedimint_core::module::ApiRequestErased;
+use fmo_api_types::{ConfigConsistency, GuardianConfig, GuardianConfigStatus};
+use futures::future::join_all;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Fetches the client config from every guardian individually instead of
+/// trusting the consensus result, so misconfigured guardians become visible
+pub async fn fetch_federation_config_consistency(
+    Path(invite): Path<InviteCode>,
+) -> crate::error::Result<Json<ConfigConsistency>> {
+    let consensus_config = ClientConfig::download_from_invite_code(&invite).await?;
+    let consensus_hash = config_hash(&consensus_config);
+    let api = DynGlobalApi::from_config(&consensus_config);
+
+    let guardians = join_all(consensus_config.global.api_endpoints.iter().map(
+        |(&peer_id, endpoint)| {
+            let api = api.clone();
+            let consensus_hash = &consensus_hash;
+            async move {
+                let config = api
+                    .request_single_peer(
+                        Some(REQUEST_TIMEOUT),
+                        CLIENT_CONFIG_ENDPOINT.to_owned(),
+                        ApiRequestErased::default(),
+                        peer_id,
+                    )
+                    .await
+                    .map_err(|e| e.to_string())
+                    .and_then(|json| {
+                        serde_json::from_value::<ClientConfig>(json).map_err(|e| e.to_string())
+                    });
+
+                (peer_id, guardian_config(endpoint, config, consensus_hash))
+            }
+        },
+    ))
+    .await
+    .into_iter()
+    .collect::<BTreeMap<_, _>>();
+
+    Ok(ConfigConsistency {
+        federation_id: invite.federation_id(),
+        consistent: guardians
+            .values()
+            .all(|guardian| guardian.status == GuardianConfigStatus::Consistent),
+        consensus_hash,
+        guardians,
+    }
+    .into())
+}
+
+/// Compares the config a guardian served to the consensus one
+fn guardian_config(
+    endpoint: &PeerUrl,
+    config: Result<ClientConfig, String>,
+    consensus_hash: &str,
+) -> GuardianConfig {
+    match config {
+        Ok(config) => {
+            let hash = config_hash(&config);
+            GuardianConfig {
+                name: endpoint.name.clone(),
+                url: endpoint.url.to_string(),
+                status: if hash == consensus_hash {
+                    GuardianConfigStatus::Consistent
+                } else {
+                    GuardianConfigStatus::Divergent
+                },
+                config_hash: Some(hash),
+                error: None,
+            }
+        }
+        Err(e) => GuardianConfig {
+            name: endpoint.name.clone(),
+            url: endpoint.url.to_string(),
+            status: GuardianConfigStatus::Unreachable,
+            config_hash: None,
+            error: Some(e),
+        },
+    }
+}
+
+fn config_hash(config: &ClientConfig) -> String {
+    sha256::Hash::hash(&config.consensus_encode_to_vec()).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use fedimint_core::config::{ClientConfig, GlobalClientConfig, PeerUrl};
+    use fedimint_core::module::CoreConsensusVersion;
+    use fedimint_core::PeerId;
+    use fmo_api_types::GuardianConfigStatus;
+
+    use super::{config_hash, guardian_config};
+
+    fn test_config(federation_name: &str) -> ClientConfig {
+        ClientConfig {
+            global: GlobalClientConfig {
+                api_endpoints: BTreeMap::from([(PeerId::from(0), test_endpoint())]),
+                consensus_version: CoreConsensusVersion { major: 0, minor: 0 },
+                meta: BTreeMap::from([("federation_name".to_owned(), federation_name.to_owned())]),
+            },
+            modules: BTreeMap::new(),
+        }
+    }
+
+    fn test_endpoint() -> PeerUrl {
+        PeerUrl {
+            url: "wss://guardian-0.example.com/".parse().unwrap(),
+            name: "Guardian 0".to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_config_hash() {
+        assert_eq!(
+            config_hash(&test_config("Test")),
+            config_hash(&test_config("Test"))
+        );
+        assert_ne!(
+            config_hash(&test_config("Test")),
+            config_hash(&test_config("Other"))
+        );
+    }
+
+    #[test]
+    fn test_guardian_config() {
+        let endpoint = test_endpoint();
+        let consensus_hash = config_hash(&test_config("Test"));
+
+        let consistent = guardian_config(&endpoint, Ok(test_config("Test")), &consensus_hash);
+        assert_eq!(consistent.status, GuardianConfigStatus::Consistent);
+        assert_eq!(consistent.config_hash, Some(consensus_hash.clone()));
+        assert_eq!(consistent.name, "Guardian 0");
+        assert_eq!(consistent.url, "wss://guardian-0.example.com/");
+
+        let divergent = guardian_config(&endpoint, Ok(test_config("Other")), &consensus_hash);
+        assert_eq!(divergent.status, GuardianConfigStatus::Divergent);
+        assert_eq!(
+            divergent.config_hash,
+            Some(config_hash(&test_config("Other")))
+        );
+        assert_eq!(divergent.error, None);
+
+        let unreachable = guardian_config(&endpoint, Err("timeout".to_owned()), &consensus_hash);
+        assert_eq!(unreachable.status, GuardianConfigStatus::Unreachable);
+        assert_eq!(unreachable.config_hash, None);
+        assert_eq!(unreachable.error.as_deref(), Some("timeout"));
+    }
+}
@@ -7,8 +7,9 @@ use axum::extract::{Path, State};
 use axum::Json;
 use fedimint_core::api::InviteCode;
 use fedimint_core::config::FederationId;
+use fmo_api_types::MetaValidationReport;
 
-use crate::meta::federation_meta;
+use crate::meta::{federation_meta, validate_meta};
 use crate::AppState;
 
 pub type MetaFields = BTreeMap<String, serde_json::Value>;
@@ -27,6 +28,20 @@ pub async fn fetch_federation_meta(
     federation_meta(&config, &state).await
 }
 
+pub async fn validate_federation_meta(
+    Path(invite): Path<InviteCode>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<MetaValidationReport>> {
+    let config = state
+        .federation_config_cache
+        .fetch_config_cached(&invite)
+        .await?;
+
+    Ok(validate_meta(&config, &state.meta_override_cache)
+        .await
+        .into())
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct MetaOverrideCache {
     client: reqwest::Client,
@@ -38,6 +53,18 @@ impl MetaOverrideCache {
         &self,
         url: &str,
         federation_id: FederationId,
+    ) -> anyhow::Result<MetaFields> {
+        Ok(parse_meta_lenient(
+            self.fetch_meta_raw_cached(url, federation_id).await?,
+        ))
+    }
+
+    /// Meta fields of the federation from the override file as they are, before
+    /// applying lenient parsing
+    pub async fn fetch_meta_raw_cached(
+        &self,
+        url: &str,
+        federation_id: FederationId,
     ) -> anyhow::Result<MetaFields> {
         let current_meta_cache_entry = self.override_files.read().await.get(url).cloned();
         let meta = match current_meta_cache_entry {
@@ -57,12 +84,11 @@ impl MetaOverrideCache {
             }
         };
 
-        let federation_meta = parse_meta_lenient(serde_json::from_value::<MetaFields>(
+        Ok(serde_json::from_value::<MetaFields>(
             meta.get(&federation_id.to_string())
                 .ok_or_else(|| anyhow!("No entry for federation {federation_id} in {url}"))?
                 .clone(),
-        )?);
-        Ok(federation_meta)
+        )?)
     }
 
     async fn fetch_meta_inner(&self, url: &str) -> anyhow::Result<serde_json::Value> {
@@ -1,22 +1,36 @@
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use axum::extract::{Path, State};
-use axum::routing::get;
+use axum::routing::{get, post};
 use axum::{Json, Router};
+use chrono::NaiveDateTime;
 use fedimint_core::api::InviteCode;
 use fedimint_core::config::{ClientConfig, FederationId, JsonClientConfig};
+use fedimint_core::encoding::Encodable;
+use fmo_api_types::ApiScope;
+use futures::future::{BoxFuture, Shared};
+use futures::FutureExt;
+use postgres_from_row::FromRow;
 use reqwest::Method;
 use tower_http::cors::{Any, CorsLayer};
 use tracing::warn;
 
+use crate::auth::RequireScope;
+use crate::config::consistency::fetch_federation_config_consistency;
 use crate::config::id::fetch_federation_id;
-use crate::config::meta::fetch_federation_meta;
+use crate::config::meta::{fetch_federation_meta, validate_federation_meta};
 use crate::config::modules::fetch_federation_module_kinds;
+use crate::config::status::fetch_federation_status;
 use crate::error::Result;
-use crate::util::config_to_json;
+use crate::federation::observer::FederationObserver;
+use crate::util::{config_to_json, execute, query, query_opt};
 use crate::AppState;
 
+/// Helper API that compares the configs served by each guardian
+pub mod consistency;
 /// Helper API that exposes the federation id
 pub mod id;
 /// Helper API that unifies config meta and override meta, applying lenient
@@ -25,12 +39,24 @@ pub mod meta;
 
 /// Helper API that exposes the federation modules
 pub mod modules;
+/// Helper API that queries the guardians' status live
+pub mod status;
 pub fn get_config_routes() -> Router<AppState> {
     let router = Router::new()
         .route("/:invite", get(fetch_federation_config))
+        .route(
+            "/:invite/refresh",
+            post(refresh_federation_config).require_scope(ApiScope::RefreshConfig),
+        )
         .route("/:invite/meta", get(fetch_federation_meta))
+        .route("/:invite/meta/validate", get(validate_federation_meta))
         .route("/:invite/id", get(fetch_federation_id))
-        .route("/:invite/module_kinds", get(fetch_federation_module_kinds));
+        .route("/:invite/module_kinds", get(fetch_federation_module_kinds))
+        .route(
+            "/:invite/consistency",
+            get(fetch_federation_config_consistency),
+        )
+        .route("/:invite/status", get(fetch_federation_status));
 
     let cors_enabled = dotenv::var("ALLOW_CONFIG_CORS").map_or(false, |v| v == "true");
 
@@ -56,36 +82,243 @@ pub async fn fetch_federation_config(
         .into())
 }
 
-#[derive(Default, Debug, Clone)]
+/// Bypasses the cache and fetches the config from the federation
+pub async fn refresh_federation_config(
+    Path(invite): Path<InviteCode>,
+    State(state): State<AppState>,
+) -> Result<Json<JsonClientConfig>> {
+    Ok(state
+        .federation_config_cache
+        .refresh_config(&invite)
+        .await?
+        .into())
+}
+
+/// Config cache backed by the database, so restarts don't cause every config
+/// to be downloaded again. Entries older than the TTL are still served while
+/// they are refreshed in the background.
+#[derive(Debug, Clone)]
 pub struct FederationConfigCache {
-    federations: Arc<tokio::sync::RwLock<HashMap<FederationId, JsonClientConfig>>>,
+    federation_observer: FederationObserver,
+    ttl: Duration,
+    /// Config fetches currently in progress, shared by all requests for the
+    /// same federation so a cold cache only causes one download each
+    in_flight: Arc<Mutex<BTreeMap<FederationId, ConfigFetch>>>,
+}
+
+type ConfigFetch =
+    Shared<BoxFuture<'static, std::result::Result<JsonClientConfig, Arc<anyhow::Error>>>>;
+
+#[derive(Debug, FromRow)]
+struct CachedConfigRow {
+    config: serde_json::Value,
+    fetched_at: NaiveDateTime,
 }
 
 impl FederationConfigCache {
+    /// How often cached configs are checked for staleness
+    const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+    pub fn new(federation_observer: FederationObserver, ttl: Duration) -> Self {
+        FederationConfigCache {
+            federation_observer,
+            ttl,
+            in_flight: Default::default(),
+        }
+    }
+
     pub async fn fetch_config_cached(
         &self,
         invite: &InviteCode,
     ) -> anyhow::Result<JsonClientConfig> {
+        let cached = query_opt::<CachedConfigRow>(
+            &self.federation_observer.connection().await?,
+            "SELECT config, fetched_at FROM config_cache WHERE federation_id = $1",
+            &[&invite.federation_id().consensus_encode_to_vec()],
+        )
+        .await?;
+
+        let Some(cached) = cached else {
+            return self.refresh_config(invite).await;
+        };
+
+        if is_stale(cached.fetched_at, chrono::Utc::now().naive_utc(), self.ttl) {
+            let slf = self.clone();
+            let invite = invite.clone();
+            tokio::spawn(async move {
+                if let Err(e) = slf.refresh_config(&invite).await {
+                    warn!(
+                        "Failed to refresh config of federation {}: {e:?}",
+                        invite.federation_id()
+                    );
+                }
+            });
+        }
+
+        Ok(serde_json::from_value(cached.config)?)
+    }
+
+    /// Fetches the config from the federation and updates the cache. Joins a
+    /// fetch of the same federation that is already in progress instead of
+    /// starting another one.
+    pub async fn refresh_config(&self, invite: &InviteCode) -> anyhow::Result<JsonClientConfig> {
         let federation_id = invite.federation_id();
+        let fetch = self
+            .in_flight
+            .lock()
+            .expect("Lock poisoned")
+            .entry(federation_id)
+            .or_insert_with(|| {
+                let slf = self.clone();
+                let invite = invite.clone();
+                async move {
+                    let result = slf.refresh_config_inner(&invite).await.map_err(Arc::new);
+                    slf.in_flight
+                        .lock()
+                        .expect("Lock poisoned")
+                        .remove(&invite.federation_id());
+                    result
+                }
+                .boxed()
+                .shared()
+            })
+            .clone();
+
+        fetch.await.map_err(|e| anyhow::anyhow!("{e:?}"))
+    }
 
-        if let Some(config) = self.federations.read().await.get(&federation_id).cloned() {
-            return Ok(config);
+    /// Refreshes cached configs once they are older than the TTL, so they stay
+    /// fresh even if nobody requests them
+    pub async fn refresh_stale_configs(self) {
+        let mut interval = tokio::time::interval(Self::REFRESH_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let stale_invites = match self.stale_invites().await {
+                Ok(stale_invites) => stale_invites,
+                Err(e) => {
+                    warn!("Failed to query stale configs: {e:?}");
+                    continue;
+                }
+            };
+
+            for invite in stale_invites {
+                if let Err(e) = self.refresh_config(&invite).await {
+                    warn!(
+                        "Failed to refresh config of federation {}: {e:?}",
+                        invite.federation_id()
+                    );
+                }
+            }
         }
+    }
+
+    async fn stale_invites(&self) -> anyhow::Result<Vec<InviteCode>> {
+        #[derive(Debug, FromRow)]
+        struct CachedInviteRow {
+            invite_code: String,
+            fetched_at: NaiveDateTime,
+        }
+
+        let now = chrono::Utc::now().naive_utc();
+        Ok(query::<CachedInviteRow>(
+            &self.federation_observer.connection().await?,
+            "SELECT invite_code, fetched_at FROM config_cache",
+            &[],
+        )
+        .await?
+        .into_iter()
+        .filter(|row| is_stale(row.fetched_at, now, self.ttl))
+        .filter_map(|row| InviteCode::from_str(&row.invite_code).ok())
+        .collect())
+    }
 
+    /// Updates the cache, recording a new version in the history if the config
+    /// changed
+    async fn refresh_config_inner(&self, invite: &InviteCode) -> anyhow::Result<JsonClientConfig> {
+        let federation_id = invite.federation_id();
         let config = fetch_config_inner(invite).await?;
-        let mut cache = self.federations.write().await;
-        if let Some(replaced) = cache.insert(federation_id, config.clone()) {
-            if replaced != config {
-                // TODO: use tracing
+        let config_json = serde_json::to_value(&config)?;
+        let now = chrono::Utc::now().naive_utc();
+
+        let mut connection = self.federation_observer.connection().await?;
+        let dbtx = connection.transaction().await?;
+
+        let previous = query_opt::<CachedConfigRow>(
+            &dbtx,
+            "SELECT config, fetched_at FROM config_cache WHERE federation_id = $1 FOR UPDATE",
+            &[&federation_id.consensus_encode_to_vec()],
+        )
+        .await?;
+
+        execute(
+            &dbtx,
+            // language=postgresql
+            "
+            INSERT INTO config_cache (federation_id, config, fetched_at, invite_code)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (federation_id) DO UPDATE SET config      = excluded.config,
+                                                      fetched_at  = excluded.fetched_at,
+                                                      invite_code = excluded.invite_code
+            ",
+            &[
+                &federation_id.consensus_encode_to_vec(),
+                &config_json,
+                &now,
+                &invite.to_string(),
+            ],
+        )
+        .await?;
+
+        if previous.as_ref().map(|previous| &previous.config) != Some(&config_json) {
+            if previous.is_some() {
                 warn!("Config for federation {federation_id} changed");
             }
+            execute(
+                &dbtx,
+                "INSERT INTO config_cache_history (federation_id, config, fetched_at) VALUES ($1, $2, $3)",
+                &[&federation_id.consensus_encode_to_vec(), &config_json, &now],
+            )
+            .await?;
         }
 
+        dbtx.commit().await?;
+
         Ok(config)
     }
 }
 
+/// Whether a config fetched at `fetched_at` should be refreshed
+fn is_stale(fetched_at: NaiveDateTime, now: NaiveDateTime, ttl: Duration) -> bool {
+    now.signed_duration_since(fetched_at)
+        .to_std()
+        .is_ok_and(|age| age > ttl)
+}
+
 async fn fetch_config_inner(invite: &InviteCode) -> anyhow::Result<JsonClientConfig> {
     let raw_config = ClientConfig::download_from_invite_code(invite).await?;
     config_to_json(raw_config)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::NaiveDateTime;
+
+    use super::is_stale;
+
+    #[test]
+    fn test_is_stale() {
+        let fetched_at = NaiveDateTime::default();
+        let ttl = Duration::from_secs(3600);
+        let after = |secs| fetched_at + chrono::Duration::seconds(secs);
+
+        assert!(!is_stale(fetched_at, after(0), ttl));
+        assert!(!is_stale(fetched_at, after(3600), ttl));
+        assert!(is_stale(fetched_at, after(3601), ttl));
+        // Entries from the future, e.g. after the clock went back, aren't stale
+        assert!(!is_stale(fetched_at, after(-10), ttl));
+        assert!(is_stale(fetched_at, after(1), Duration::ZERO));
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+use axum::extract::{Path, State};
+use axum::Json;
+use chrono::NaiveDateTime;
+use fedimint_core::api::{DynGlobalApi, InviteCode};
+use fedimint_core::config::{FederationId, JsonClientConfig};
+use fmo_api_types::{FederationLiveStatus, GuardianLiveStatus};
+
+use crate::federation::guardians::{probe_guardians, GuardianProbe};
+use crate::federation::observer::consensus_threshold;
+use crate::AppState;
+
+/// Time for which guardian statuses are served from cache
+const STATUS_CACHE_TTL: Duration = Duration::from_secs(30);
+
+pub async fn fetch_federation_status(
+    Path(invite): Path<InviteCode>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<FederationLiveStatus>> {
+    let status = state
+        .federation_status_cache
+        .fetch_status_cached(&invite, &state)
+        .await?;
+
+    Ok(status.into())
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct FederationStatusCache {
+    statuses: Arc<tokio::sync::RwLock<HashMap<FederationId, (FederationLiveStatus, SystemTime)>>>,
+}
+
+impl FederationStatusCache {
+    pub async fn fetch_status_cached(
+        &self,
+        invite: &InviteCode,
+        state: &AppState,
+    ) -> anyhow::Result<FederationLiveStatus> {
+        let federation_id = invite.federation_id();
+
+        let current_status_cache_entry = self.statuses.read().await.get(&federation_id).cloned();
+        if let Some((status, last_update)) = current_status_cache_entry {
+            if is_fresh(last_update, SystemTime::now()) {
+                return Ok(status);
+            }
+        }
+
+        let config = state
+            .federation_config_cache
+            .fetch_config_cached(invite)
+            .await?;
+        let status = fetch_status_inner(federation_id, &config).await?;
+
+        let mut cache = self.statuses.write().await;
+        // Expired entries would be re-fetched anyway, dropping them keeps the cache
+        // from growing with every federation ever queried
+        let now = SystemTime::now();
+        cache.retain(|_, (_, last_update)| is_fresh(*last_update, now));
+        cache.insert(federation_id, (status.clone(), SystemTime::now()));
+
+        Ok(status)
+    }
+}
+
+async fn fetch_status_inner(
+    federation_id: FederationId,
+    config: &JsonClientConfig,
+) -> anyhow::Result<FederationLiveStatus> {
+    let wallet_module = config
+        .modules
+        .iter()
+        .find_map(|(&module_instance_id, module)| {
+            (module.kind().as_str() == "wallet").then_some(module_instance_id)
+        })
+        .context("Wallet module not found")?;
+    let api = DynGlobalApi::from_endpoints(
+        config
+            .global
+            .api_endpoints
+            .iter()
+            .map(|(&peer_id, endpoint)| (peer_id, endpoint.url.clone()))
+            .collect::<Vec<_>>(),
+    );
+
+    let probes = probe_guardians(
+        &api,
+        config.global.api_endpoints.keys().copied(),
+        wallet_module,
+    )
+    .await;
+
+    Ok(live_status(
+        federation_id,
+        probes,
+        chrono::Utc::now().naive_utc(),
+    ))
+}
+
+/// Whether a status cached at `last_update` can still be served
+fn is_fresh(last_update: SystemTime, now: SystemTime) -> bool {
+    now.duration_since(last_update).unwrap_or_default() <= STATUS_CACHE_TTL
+}
+
+fn live_status(
+    federation_id: FederationId,
+    probes: Vec<GuardianProbe>,
+    checked_at: NaiveDateTime,
+) -> FederationLiveStatus {
+    let guardians = probes
+        .into_iter()
+        .map(|probe| {
+            let guardian = GuardianLiveStatus {
+                reachable: probe.status.is_some(),
+                // Only successful requests measure the guardian and not our timeout
+                latency_ms: probe
+                    .block_height
+                    .is_some()
+                    .then_some(probe.api_latency.as_millis() as u64),
+                session_count: probe
+                    .status
+                    .and_then(|status| status.federation)
+                    .map(|federation| federation.session_count),
+                block_height: probe.block_height,
+            };
+            (probe.peer_id, guardian)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let online_guardians = guardians
+        .values()
+        .filter(|guardian| guardian.reachable)
+        .count();
+
+    FederationLiveStatus {
+        federation_id,
+        checked_at,
+        online_guardians,
+        online: online_guardians >= consensus_threshold(guardians.len()),
+        guardians,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use chrono::NaiveDateTime;
+    use fedimint_core::api::{FederationStatus, ServerStatus, StatusResponse};
+    use fedimint_core::config::FederationId;
+    use fedimint_core::PeerId;
+
+    use super::{is_fresh, live_status, STATUS_CACHE_TTL};
+    use crate::federation::guardians::GuardianProbe;
+
+    fn probe(peer_id: u16, online: bool) -> GuardianProbe {
+        GuardianProbe {
+            peer_id: PeerId::from(peer_id),
+            status: online.then(|| StatusResponse {
+                server: ServerStatus::ConsensusRunning,
+                federation: Some(FederationStatus {
+                    session_count: 42,
+                    ..Default::default()
+                }),
+            }),
+            block_height: online.then_some(800_000),
+            api_latency: Duration::from_millis(120),
+        }
+    }
+
+    #[test]
+    fn test_is_fresh() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert!(is_fresh(now, now));
+        assert!(is_fresh(now - STATUS_CACHE_TTL, now));
+        assert!(!is_fresh(
+            now - STATUS_CACHE_TTL - Duration::from_secs(1),
+            now
+        ));
+        // Entries from the future, e.g. after the clock went back, are fresh
+        assert!(is_fresh(now + Duration::from_secs(60), now));
+    }
+
+    #[test]
+    fn test_live_status() {
+        let status = live_status(
+            FederationId::dummy(),
+            vec![
+                probe(0, true),
+                probe(1, true),
+                probe(2, true),
+                probe(3, false),
+            ],
+            NaiveDateTime::default(),
+        );
+        assert_eq!(status.online_guardians, 3);
+        assert!(status.online);
+
+        let online = &status.guardians[&PeerId::from(0)];
+        assert!(online.reachable);
+        assert_eq!(online.latency_ms, Some(120));
+        assert_eq!(online.session_count, Some(42));
+        assert_eq!(online.block_height, Some(800_000));
+
+        // The latency of failed requests would only measure the timeout
+        let offline = &status.guardians[&PeerId::from(3)];
+        assert!(!offline.reachable);
+        assert_eq!(offline.latency_ms, None);
+        assert_eq!(offline.session_count, None);
+
+        // 3 of 4 guardians are needed for consensus
+        let status = live_status(
+            FederationId::dummy(),
+            vec![
+                probe(0, true),
+                probe(1, true),
+                probe(2, false),
+                probe(3, false),
+            ],
+            NaiveDateTime::default(),
+        );
+        assert_eq!(status.online_guardians, 2);
+        assert!(!status.online);
+    }
+}
@@ -3,13 +3,23 @@
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 
+use crate::auth::AuthError;
+use crate::federation::nostr::RateLimited;
+
 pub(crate) type Result<T> = std::result::Result<T, AppError>;
 
 pub(crate) struct AppError(anyhow::Error);
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        (StatusCode::BAD_REQUEST, format!("Error: {}", self.0)).into_response()
+        let e = match self.0.downcast::<AuthError>() {
+            Ok(e) => return e.into_response(),
+            Err(e) => e,
+        };
+        match e.downcast::<RateLimited>() {
+            Ok(e) => e.into_response(),
+            Err(e) => (StatusCode::BAD_REQUEST, format!("Error: {e}")).into_response(),
+        }
     }
 }
 
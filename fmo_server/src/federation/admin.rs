@@ -0,0 +1,401 @@
+use std::io::{BufRead, Write};
+
+use anyhow::{ensure, Context};
+use axum::extract::{Path, State};
+use fedimint_core::config::{ClientConfig, FederationId};
+use fedimint_core::encoding::{Decodable, Encodable};
+use postgres_from_row::FromRow;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::federation::observer::FederationObserver;
+use crate::federation::reindex::clear_derived_tables;
+use crate::util::{query, query_value};
+use crate::AppState;
+
+/// Number of sessions read from or written to the database at once during
+/// export and import
+const EXPORT_BATCH_SIZE: i64 = 1000;
+
+/// First line of an export, followed by one [`ExportedSession`] per line
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportHeader {
+    federation_id: FederationId,
+    /// Hex encoded, consensus encoded client config
+    config: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportedSession {
+    session_index: u32,
+    /// Hex encoded, consensus encoded signed session outcome
+    session: String,
+}
+
+impl ExportHeader {
+    fn new(federation_id: FederationId, config: &ClientConfig) -> Self {
+        ExportHeader {
+            federation_id,
+            config: hex::encode(config.consensus_encode_to_vec()),
+        }
+    }
+
+    /// Decodes the config, making sure it belongs to the exported federation
+    fn decode_config(&self) -> anyhow::Result<ClientConfig> {
+        let config = ClientConfig::consensus_decode_vec(
+            hex::decode(&self.config).context("Invalid config encoding")?,
+            &Default::default(),
+        )
+        .context("Invalid config")?;
+        ensure!(
+            config.global.calculate_federation_id() == self.federation_id,
+            "Config doesn't belong to federation {}",
+            self.federation_id
+        );
+        Ok(config)
+    }
+}
+
+impl ExportedSession {
+    fn new(session_index: u32, session: &[u8]) -> Self {
+        ExportedSession {
+            session_index,
+            session: hex::encode(session),
+        }
+    }
+
+    fn decode_session(&self) -> anyhow::Result<Vec<u8>> {
+        hex::decode(&self.session).context("Invalid session encoding")
+    }
+}
+
+/// Writes one line of an export
+fn write_export_line(writer: &mut impl Write, line: &impl Serialize) -> anyhow::Result<()> {
+    serde_json::to_writer(&mut *writer, line)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+pub(super) async fn remove_observed_federation(
+    Path(federation_id): Path<FederationId>,
+    State(state): State<AppState>,
+) -> crate::error::Result<()> {
+    Ok(state
+        .federation_observer
+        .remove_federation(federation_id)
+        .await?)
+}
+
+impl FederationObserver {
+    /// Stops observing a federation and deletes all data about it
+    pub async fn remove_federation(&self, federation_id: FederationId) -> anyhow::Result<()> {
+        self.get_federation(federation_id)
+            .await?
+            .context("Federation doesn't exist")?;
+
+        self.stop_observer(federation_id);
+
+        let federation_id_bytes = federation_id.consensus_encode_to_vec();
+        let mut conn = self.connection().await?;
+        let dbtx = conn.transaction().await?;
+
+        clear_derived_tables(&dbtx, federation_id).await?;
+
+        // language=postgresql
+        const DELETE_FEDERATION: &str = "
+            DELETE FROM wallet_withdrawal_transaction_inputs
+            WHERE on_chain_txid IN (SELECT on_chain_txid FROM wallet_withdrawal_transactions WHERE federation_id = $1);
+            DELETE FROM wallet_withdrawal_transaction_outputs
+            WHERE on_chain_txid IN (SELECT on_chain_txid FROM wallet_withdrawal_transactions WHERE federation_id = $1);
+            DELETE FROM wallet_withdrawal_transactions WHERE federation_id = $1;
+            DELETE FROM guardian_health WHERE federation_id = $1;
+            DELETE FROM nostr_votes WHERE federation_id = $1;
+            DELETE FROM nostr_vote_deletions WHERE federation_id = $1;
+            DELETE FROM reindex_jobs WHERE federation_id = $1;
+            DELETE FROM federation_config_snapshots WHERE federation_id = $1;
+            DELETE FROM webhooks WHERE federation_id = $1;
+            DELETE FROM alerts WHERE federation_id = $1;
+            DELETE FROM alert_rules WHERE federation_id = $1;
+            DELETE FROM sessions WHERE federation_id = $1;
+            DELETE FROM federations WHERE federation_id = $1;
+        ";
+        for statement in DELETE_FEDERATION
+            .split(';')
+            .map(str::trim)
+            .filter(|statement| !statement.is_empty())
+        {
+            dbtx.execute(statement, &[&federation_id_bytes]).await?;
+        }
+
+        dbtx.commit().await?;
+        self.refresh_views().await?;
+
+        info!("Removed federation {federation_id}");
+
+        Ok(())
+    }
+
+    /// Writes the config and all stored sessions of a federation as
+    /// newline-delimited JSON. Derived data isn't exported since it can be
+    /// rebuilt by reindexing after an import.
+    pub async fn export_federation(
+        &self,
+        federation_id: FederationId,
+        mut writer: impl Write,
+    ) -> anyhow::Result<u64> {
+        #[derive(Debug, FromRow)]
+        struct SessionRow {
+            session_index: i32,
+            session: Vec<u8>,
+        }
+
+        let federation = self
+            .get_federation(federation_id)
+            .await?
+            .context("Federation doesn't exist")?;
+
+        write_export_line(
+            &mut writer,
+            &ExportHeader::new(federation_id, &federation.config),
+        )?;
+
+        let mut exported = 0;
+        let mut next_session_index = 0;
+        loop {
+            let sessions = query::<SessionRow>(
+                &self.connection().await?,
+                "
+                SELECT session_index, session
+                FROM sessions
+                WHERE federation_id = $1
+                  AND session_index >= $2
+                ORDER BY session_index
+                LIMIT $3
+                ",
+                &[
+                    &federation_id.consensus_encode_to_vec(),
+                    &next_session_index,
+                    &EXPORT_BATCH_SIZE,
+                ],
+            )
+            .await?;
+
+            let Some(last_session) = sessions.last() else {
+                break;
+            };
+            next_session_index = last_session.session_index + 1;
+
+            for session in sessions {
+                write_export_line(
+                    &mut writer,
+                    &ExportedSession::new(session.session_index as u32, &session.session),
+                )?;
+                exported += 1;
+            }
+        }
+
+        writer.flush()?;
+
+        Ok(exported)
+    }
+
+    /// Imports a federation exported by
+    /// [`FederationObserver::export_federation`] and schedules a reindex to
+    /// rebuild its derived data. Sessions that are already stored are
+    /// skipped.
+    pub async fn import_federation(&self, reader: impl BufRead) -> anyhow::Result<FederationId> {
+        let mut lines = reader.lines();
+
+        let header: ExportHeader = serde_json::from_str(&lines.next().context("Export is empty")??)
+            .context("Invalid export header")?;
+        let config = header.decode_config()?;
+
+        let federation_id_bytes = header.federation_id.consensus_encode_to_vec();
+        self.connection()
+            .await?
+            .execute(
+                "INSERT INTO federations VALUES ($1, $2) ON CONFLICT DO NOTHING",
+                &[&federation_id_bytes, &config.consensus_encode_to_vec()],
+            )
+            .await?;
+
+        let mut imported = 0;
+        let mut batch = Vec::with_capacity(EXPORT_BATCH_SIZE as usize);
+        loop {
+            let line = lines.next().transpose()?;
+            if let Some(line) = &line {
+                batch.push(
+                    serde_json::from_str::<ExportedSession>(line).context("Invalid session")?,
+                );
+            }
+
+            if batch.len() as i64 == EXPORT_BATCH_SIZE || (line.is_none() && !batch.is_empty()) {
+                let mut conn = self.connection().await?;
+                let dbtx = conn.transaction().await?;
+                for session in batch.drain(..) {
+                    dbtx.execute(
+                        "INSERT INTO sessions VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+                        &[
+                            &federation_id_bytes,
+                            &(session.session_index as i32),
+                            &session.decode_session()?,
+                        ],
+                    )
+                    .await?;
+                    imported += 1;
+                }
+                dbtx.commit().await?;
+                info!("Imported {imported} sessions");
+            }
+
+            if line.is_none() {
+                break;
+            }
+        }
+
+        self.start_reindex(header.federation_id).await?;
+
+        Ok(header.federation_id)
+    }
+
+    /// Looks for inconsistencies in the stored data that indicate bugs or
+    /// interrupted jobs, returns a human-readable description of each
+    pub async fn check_integrity(&self) -> anyhow::Result<Vec<String>> {
+        #[derive(Debug, FromRow)]
+        struct SessionStatsRow {
+            session_count: i64,
+            max_session_index: Option<i32>,
+        }
+
+        let mut issues = vec![];
+        for federation in self.list_federations().await? {
+            let federation_id = federation.federation_id;
+            let federation_id_bytes = federation_id.consensus_encode_to_vec();
+            let conn = self.connection().await?;
+
+            let session_stats = query::<SessionStatsRow>(
+                &conn,
+                "
+                SELECT COUNT(*)::bigint AS session_count, MAX(session_index) AS max_session_index
+                FROM sessions
+                WHERE federation_id = $1
+                ",
+                &[&federation_id_bytes],
+            )
+            .await?
+            .pop()
+            .expect("Aggregate always returns a row");
+            if let Some(max_session_index) = session_stats.max_session_index {
+                let missing = (max_session_index as i64 + 1) - session_stats.session_count;
+                if missing != 0 {
+                    issues.push(format!(
+                        "{federation_id}: {missing} sessions missing before session {max_session_index}"
+                    ));
+                }
+            }
+
+            if self.reindex_in_progress(federation_id).await? {
+                issues.push(format!(
+                    "{federation_id}: reindex unfinished, run `fmo_server reindex --resume`"
+                ));
+            }
+
+            let withdrawals_without_tx = query_value::<i64>(
+                &conn,
+                "
+                SELECT COUNT(*)::bigint
+                FROM wallet_withdrawal_transactions wwt
+                WHERE wwt.federation_id = $1
+                  AND wwt.threshold_session_index IS NOT NULL
+                  AND NOT EXISTS (SELECT 1
+                                  FROM wallet_withdrawal_transaction_outputs wwto
+                                  WHERE wwto.on_chain_txid = wwt.on_chain_txid)
+                ",
+                &[&federation_id_bytes],
+            )
+            .await?;
+            if withdrawals_without_tx != 0 {
+                issues.push(format!(
+                    "{federation_id}: {withdrawals_without_tx} signed peg-outs without on-chain transaction data"
+                ));
+            }
+
+            let balance_sheet = self.federation_balance_sheet(federation_id).await?;
+            if balance_sheet.surplus_msat < 0 {
+                issues.push(format!(
+                    "{federation_id}: liabilities exceed assets by {} msat",
+                    balance_sheet.surplus_msat.unsigned_abs()
+                ));
+            }
+        }
+
+        Ok(issues)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::io::BufRead;
+
+    use fedimint_core::config::{ClientConfig, GlobalClientConfig, PeerUrl};
+    use fedimint_core::module::CoreConsensusVersion;
+    use fedimint_core::PeerId;
+
+    use super::{write_export_line, ExportHeader, ExportedSession};
+
+    fn test_config(url: &str) -> ClientConfig {
+        ClientConfig {
+            global: GlobalClientConfig {
+                api_endpoints: BTreeMap::from([(
+                    PeerId::from(0),
+                    PeerUrl {
+                        url: url.parse().unwrap(),
+                        name: "Guardian 0".to_owned(),
+                    },
+                )]),
+                consensus_version: CoreConsensusVersion { major: 0, minor: 0 },
+                meta: BTreeMap::from([("federation_name".to_owned(), "Test".to_owned())]),
+            },
+            modules: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let config = test_config("wss://guardian-0.example.com/");
+        let federation_id = config.global.calculate_federation_id();
+        let sessions = [(0, vec![1, 2, 3]), (1, vec![]), (2, vec![0xff; 64])];
+
+        let mut export = Vec::new();
+        write_export_line(&mut export, &ExportHeader::new(federation_id, &config)).unwrap();
+        for (session_index, session) in &sessions {
+            write_export_line(&mut export, &ExportedSession::new(*session_index, session)).unwrap();
+        }
+
+        let mut lines = export.lines().map(Result::unwrap);
+        let header = serde_json::from_str::<ExportHeader>(&lines.next().unwrap()).unwrap();
+        assert_eq!(header.federation_id, federation_id);
+        assert_eq!(header.decode_config().unwrap(), config);
+
+        let imported = lines
+            .map(|line| {
+                let session = serde_json::from_str::<ExportedSession>(&line).unwrap();
+                (session.session_index, session.decode_session().unwrap())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(imported, sessions);
+    }
+
+    #[test]
+    fn test_export_header_rejects_foreign_config() {
+        let config = test_config("wss://guardian-0.example.com/");
+        let other_federation_id = test_config("wss://other.example.com/")
+            .global
+            .calculate_federation_id();
+
+        assert!(ExportHeader::new(other_federation_id, &config)
+            .decode_config()
+            .is_err());
+    }
+}
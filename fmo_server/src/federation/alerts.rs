@@ -0,0 +1,788 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use anyhow::{ensure, Context};
+use axum::extract::{Path, Query, State};
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
+use chrono::NaiveDateTime;
+use fedimint_core::config::{ClientConfig, FederationId};
+use fedimint_core::encoding::Encodable;
+use fedimint_core::PeerId;
+use fmo_api_types::{
+    Alert, AlertCondition, AlertRule, AlertState, ApiScope, FederationEvent, NewAlertRule,
+};
+use postgres_from_row::FromRow;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::auth::RequireScope;
+use crate::federation::observer::{signature_threshold, FederationObserver};
+use crate::util::{consensus_median, decode_db_value, execute, query, query_one, query_opt};
+use crate::AppState;
+
+/// Health checks older than this aren't considered current anymore, e.g. when
+/// the health monitor itself was down
+const MAX_CHECK_AGE: Duration = Duration::from_secs(5 * 60);
+
+pub fn get_alerts_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_alerts))
+        .route("/rules", get(list_alert_rules))
+        .route(
+            "/rules",
+            post(create_alert_rule).require_scope(ApiScope::Alerts),
+        )
+        .route(
+            "/rules/:rule_id",
+            delete(delete_alert_rule).require_scope(ApiScope::Alerts),
+        )
+}
+
+#[derive(Debug, Deserialize)]
+struct AlertParams {
+    state: Option<AlertState>,
+    federation_id: Option<FederationId>,
+}
+
+async fn list_alerts(
+    Query(params): Query<AlertParams>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<Vec<Alert>>> {
+    Ok(state
+        .federation_observer
+        .list_alerts(params.state, params.federation_id)
+        .await?
+        .into())
+}
+
+async fn list_alert_rules(
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<Vec<AlertRule>>> {
+    Ok(state
+        .federation_observer
+        .list_alert_rules(None)
+        .await?
+        .into())
+}
+
+async fn create_alert_rule(
+    State(state): State<AppState>,
+    Json(rule): Json<NewAlertRule>,
+) -> crate::error::Result<Json<AlertRule>> {
+    Ok(state
+        .federation_observer
+        .create_alert_rule(rule)
+        .await?
+        .into())
+}
+
+async fn delete_alert_rule(
+    Path(rule_id): Path<u32>,
+    State(state): State<AppState>,
+) -> crate::error::Result<()> {
+    Ok(state.federation_observer.delete_alert_rule(rule_id).await?)
+}
+
+#[derive(Debug, FromRow)]
+struct AlertRuleRow {
+    rule_id: i32,
+    name: String,
+    federation_id: Option<Vec<u8>>,
+    condition: serde_json::Value,
+    created_at: NaiveDateTime,
+}
+
+impl TryFrom<AlertRuleRow> for AlertRule {
+    type Error = anyhow::Error;
+
+    fn try_from(row: AlertRuleRow) -> Result<Self, Self::Error> {
+        Ok(AlertRule {
+            rule_id: row.rule_id as u32,
+            name: row.name,
+            federation_id: row.federation_id.map(decode_db_value),
+            condition: serde_json::from_value(row.condition)
+                .with_context(|| format!("Invalid condition of alert rule {}", row.rule_id))?,
+            created_at: row.created_at,
+        })
+    }
+}
+
+/// Outcome of evaluating a rule for a single guardian or the whole federation
+#[derive(Debug)]
+struct Evaluation {
+    peer_id: Option<PeerId>,
+    firing: bool,
+    value: f64,
+    message: String,
+}
+
+impl FederationObserver {
+    pub async fn list_alerts(
+        &self,
+        state: Option<AlertState>,
+        federation_id: Option<FederationId>,
+    ) -> anyhow::Result<Vec<Alert>> {
+        #[derive(Debug, FromRow)]
+        struct AlertRow {
+            rule_id: i32,
+            rule_name: String,
+            federation_id: Vec<u8>,
+            peer_id: Option<i32>,
+            state: String,
+            value: f64,
+            message: String,
+            fired_at: NaiveDateTime,
+            resolved_at: Option<NaiveDateTime>,
+            updated_at: NaiveDateTime,
+        }
+
+        let alerts = query::<AlertRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            SELECT a.rule_id,
+                   r.name AS rule_name,
+                   a.federation_id,
+                   a.peer_id,
+                   a.state,
+                   a.value,
+                   a.message,
+                   a.fired_at,
+                   a.resolved_at,
+                   a.updated_at
+            FROM alerts a
+                     JOIN alert_rules r ON a.rule_id = r.rule_id
+            WHERE ($1::TEXT IS NULL OR a.state = $1)
+              AND ($2::BYTEA IS NULL OR a.federation_id = $2)
+            ORDER BY a.updated_at DESC
+            ",
+            &[
+                &state.map(alert_state_str),
+                &federation_id.map(|federation_id| federation_id.consensus_encode_to_vec()),
+            ],
+        )
+        .await?;
+
+        Ok(alerts
+            .into_iter()
+            .map(|row| Alert {
+                rule_id: row.rule_id as u32,
+                rule_name: row.rule_name,
+                federation_id: decode_db_value(row.federation_id),
+                peer_id: row.peer_id.map(|peer_id| PeerId::from(peer_id as u16)),
+                state: if row.state == "firing" {
+                    AlertState::Firing
+                } else {
+                    AlertState::Resolved
+                },
+                value: row.value,
+                message: row.message,
+                fired_at: row.fired_at,
+                resolved_at: row.resolved_at,
+                updated_at: row.updated_at,
+            })
+            .collect())
+    }
+
+    /// Rules applying to the federation, or all rules if `None`
+    pub async fn list_alert_rules(
+        &self,
+        federation_id: Option<FederationId>,
+    ) -> anyhow::Result<Vec<AlertRule>> {
+        query::<AlertRuleRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            SELECT rule_id, name, federation_id, condition, created_at
+            FROM alert_rules
+            WHERE $1::BYTEA IS NULL
+               OR federation_id IS NULL
+               OR federation_id = $1
+            ORDER BY rule_id
+            ",
+            &[&federation_id.map(|federation_id| federation_id.consensus_encode_to_vec())],
+        )
+        .await?
+        .into_iter()
+        .map(TryInto::try_into)
+        .collect()
+    }
+
+    pub async fn create_alert_rule(&self, rule: NewAlertRule) -> anyhow::Result<AlertRule> {
+        ensure!(!rule.name.is_empty(), "Alert rule name must not be empty");
+        if let Some(federation_id) = rule.federation_id {
+            self.get_federation(federation_id)
+                .await?
+                .context("Federation doesn't exist")?;
+        }
+
+        let rule = query_one::<AlertRuleRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            INSERT INTO alert_rules (name, federation_id, condition, created_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING rule_id, name, federation_id, condition, created_at
+            ",
+            &[
+                &rule.name,
+                &rule
+                    .federation_id
+                    .map(|federation_id| federation_id.consensus_encode_to_vec()),
+                &serde_json::to_value(&rule.condition).expect("Can be serialized"),
+                &chrono::Utc::now().naive_utc(),
+            ],
+        )
+        .await?;
+
+        info!("Created alert rule {} ({})", rule.rule_id, rule.name);
+
+        rule.try_into()
+    }
+
+    pub async fn delete_alert_rule(&self, rule_id: u32) -> anyhow::Result<()> {
+        let deleted = execute(
+            &self.connection().await?,
+            "DELETE FROM alert_rules WHERE rule_id = $1",
+            &[&(rule_id as i32)],
+        )
+        .await?;
+        ensure!(deleted == 1, "Alert rule doesn't exist");
+
+        Ok(())
+    }
+
+    /// Evaluates all rules applying to the federation against the latest health
+    /// checks, publishing an event whenever an alert starts firing or resolves
+    pub(super) async fn evaluate_alert_rules(
+        &self,
+        federation_id: FederationId,
+        config: &ClientConfig,
+    ) -> anyhow::Result<()> {
+        let now = chrono::Utc::now().naive_utc();
+
+        for rule in self.list_alert_rules(Some(federation_id)).await? {
+            let evaluations = match &rule.condition {
+                AlertCondition::GuardianUnreachable { for_secs } => {
+                    self.evaluate_guardian_unreachable(federation_id, *for_secs, now)
+                        .await?
+                }
+                AlertCondition::BlockHeightLag { max_lag_blocks } => {
+                    self.evaluate_block_height_lag(federation_id, *max_lag_blocks, now)
+                        .await?
+                }
+                AlertCondition::GuardiansBelowThreshold => {
+                    self.evaluate_guardians_below_threshold(federation_id, config, now)
+                        .await?
+                }
+                AlertCondition::LatencyP95 {
+                    max_latency_ms,
+                    window_secs,
+                } => {
+                    self.evaluate_latency_p95(federation_id, *max_latency_ms, *window_secs, now)
+                        .await?
+                }
+            };
+
+            for evaluation in evaluations {
+                self.update_alert(&rule, federation_id, evaluation, now)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn evaluate_guardian_unreachable(
+        &self,
+        federation_id: FederationId,
+        for_secs: u64,
+        now: NaiveDateTime,
+    ) -> anyhow::Result<Vec<Evaluation>> {
+        // Looking back twice as far lets us tell "down for long enough" apart from "no
+        // data yet", e.g. right after starting the observer
+        let checks = query::<TimedHealthCheckRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            SELECT guardian_id, time, status IS NOT NULL AS online
+            FROM guardian_health
+            WHERE federation_id = $1
+              AND time >= $2
+            ORDER BY guardian_id, time
+            ",
+            &[
+                &federation_id.consensus_encode_to_vec(),
+                &(now - chrono::Duration::seconds(2 * for_secs as i64)),
+            ],
+        )
+        .await?;
+
+        Ok(guardian_unreachable_evaluations(checks, for_secs, now))
+    }
+
+    async fn evaluate_block_height_lag(
+        &self,
+        federation_id: FederationId,
+        max_lag_blocks: u32,
+        now: NaiveDateTime,
+    ) -> anyhow::Result<Vec<Evaluation>> {
+        let block_heights = self
+            .latest_health_checks(federation_id, now)
+            .await?
+            .into_iter()
+            .filter_map(|check| Some((check.guardian_id, check.block_height? as u32)))
+            .collect::<BTreeMap<_, _>>();
+
+        Ok(block_height_lag_evaluations(block_heights, max_lag_blocks))
+    }
+
+    async fn evaluate_guardians_below_threshold(
+        &self,
+        federation_id: FederationId,
+        config: &ClientConfig,
+        now: NaiveDateTime,
+    ) -> anyhow::Result<Vec<Evaluation>> {
+        let num_guardians = config.global.api_endpoints.len();
+        let threshold = signature_threshold(config);
+        let online = self
+            .latest_health_checks(federation_id, now)
+            .await?
+            .into_iter()
+            .filter(|check| check.online)
+            .count();
+
+        Ok(vec![guardians_below_threshold_evaluation(
+            online,
+            num_guardians,
+            threshold,
+        )])
+    }
+
+    async fn evaluate_latency_p95(
+        &self,
+        federation_id: FederationId,
+        max_latency_ms: u64,
+        window_secs: u64,
+        now: NaiveDateTime,
+    ) -> anyhow::Result<Vec<Evaluation>> {
+        let latencies = query::<LatencyRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            SELECT guardian_id,
+                   percentile_cont(0.95) WITHIN GROUP (ORDER BY latency_ms)::DOUBLE PRECISION AS p95_latency_ms
+            FROM guardian_health
+            WHERE federation_id = $1
+              AND time >= $2
+              -- failed requests only measure our timeout
+              AND block_height IS NOT NULL
+            GROUP BY guardian_id
+            ",
+            &[
+                &federation_id.consensus_encode_to_vec(),
+                &(now - chrono::Duration::seconds(window_secs as i64)),
+            ],
+        )
+        .await?;
+
+        Ok(latency_p95_evaluations(latencies, max_latency_ms))
+    }
+
+    async fn latest_health_checks(
+        &self,
+        federation_id: FederationId,
+        now: NaiveDateTime,
+    ) -> anyhow::Result<Vec<HealthCheckRow>> {
+        query::<HealthCheckRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            SELECT DISTINCT ON (guardian_id) guardian_id, status IS NOT NULL AS online, block_height
+            FROM guardian_health
+            WHERE federation_id = $1
+              AND time >= $2
+            ORDER BY guardian_id, time DESC
+            ",
+            &[
+                &federation_id.consensus_encode_to_vec(),
+                &(now - chrono::Duration::from_std(MAX_CHECK_AGE).expect("Fits")),
+            ],
+        )
+        .await
+    }
+
+    async fn update_alert(
+        &self,
+        rule: &AlertRule,
+        federation_id: FederationId,
+        evaluation: Evaluation,
+        now: NaiveDateTime,
+    ) -> anyhow::Result<()> {
+        #[derive(Debug, FromRow)]
+        struct AlertStateRow {
+            state: String,
+        }
+
+        let conn = self.connection().await?;
+        let federation_id_param = federation_id.consensus_encode_to_vec();
+        let peer_id_param = evaluation.peer_id.map(|peer_id| peer_id.to_usize() as i32);
+
+        let was_firing = query_opt::<AlertStateRow>(
+            &conn,
+            // language=postgresql
+            "
+            SELECT state
+            FROM alerts
+            WHERE rule_id = $1
+              AND federation_id = $2
+              AND COALESCE(peer_id, -1) = COALESCE($3, -1)
+            ",
+            &[&(rule.rule_id as i32), &federation_id_param, &peer_id_param],
+        )
+        .await?
+        .is_some_and(|alert| alert.state == "firing");
+
+        let new_state = match alert_transition(was_firing, evaluation.firing) {
+            AlertTransition::None => return Ok(()),
+            AlertTransition::Update => {
+                execute(
+                    &conn,
+                    // language=postgresql
+                    "
+                    UPDATE alerts
+                    SET value      = $4,
+                        message    = $5,
+                        updated_at = $6
+                    WHERE rule_id = $1
+                      AND federation_id = $2
+                      AND COALESCE(peer_id, -1) = COALESCE($3, -1)
+                    ",
+                    &[
+                        &(rule.rule_id as i32),
+                        &federation_id_param,
+                        &peer_id_param,
+                        &evaluation.value,
+                        &evaluation.message,
+                        &now,
+                    ],
+                )
+                .await?;
+                return Ok(());
+            }
+            AlertTransition::Change(new_state) => new_state,
+        };
+
+        execute(
+            &conn,
+            // language=postgresql
+            "
+            INSERT INTO alerts (rule_id, federation_id, peer_id, state, value, message, fired_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $7)
+            ON CONFLICT (rule_id, federation_id, COALESCE(peer_id, -1)) DO UPDATE
+                SET state       = excluded.state,
+                    value       = excluded.value,
+                    message     = excluded.message,
+                    fired_at    = CASE WHEN excluded.state = 'firing' THEN excluded.fired_at ELSE alerts.fired_at END,
+                    resolved_at = CASE WHEN excluded.state = 'resolved' THEN excluded.updated_at END,
+                    updated_at  = excluded.updated_at
+            ",
+            &[
+                &(rule.rule_id as i32),
+                &federation_id_param,
+                &peer_id_param,
+                &alert_state_str(new_state),
+                &evaluation.value,
+                &evaluation.message,
+                &now,
+            ],
+        )
+        .await?;
+
+        info!(
+            "Alert {} of {federation_id} {}: {}",
+            rule.name,
+            alert_state_str(new_state),
+            evaluation.message
+        );
+        self.publish_event(FederationEvent::Alert {
+            federation_id,
+            rule_id: rule.rule_id,
+            rule_name: rule.name.clone(),
+            peer_id: evaluation.peer_id,
+            state: new_state,
+            message: evaluation.message,
+        })
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct HealthCheckRow {
+    guardian_id: i32,
+    online: bool,
+    block_height: Option<i32>,
+}
+
+#[derive(Debug, FromRow)]
+struct TimedHealthCheckRow {
+    guardian_id: i32,
+    time: NaiveDateTime,
+    online: bool,
+}
+
+#[derive(Debug, FromRow)]
+struct LatencyRow {
+    guardian_id: i32,
+    p95_latency_ms: f64,
+}
+
+/// What has to happen to the stored alert after an evaluation
+#[derive(Debug, PartialEq, Eq)]
+enum AlertTransition {
+    /// Neither firing before nor now
+    None,
+    /// Still firing, only the value and message change
+    Update,
+    /// Started firing or resolved
+    Change(AlertState),
+}
+
+fn alert_transition(was_firing: bool, firing: bool) -> AlertTransition {
+    match (was_firing, firing) {
+        (false, false) => AlertTransition::None,
+        (true, true) => AlertTransition::Update,
+        (false, true) => AlertTransition::Change(AlertState::Firing),
+        (true, false) => AlertTransition::Change(AlertState::Resolved),
+    }
+}
+
+/// Fires for guardians whose checks failed continuously for at least
+/// `for_secs`, `checks` have to be ordered by time
+fn guardian_unreachable_evaluations(
+    checks: Vec<TimedHealthCheckRow>,
+    for_secs: u64,
+    now: NaiveDateTime,
+) -> Vec<Evaluation> {
+    // Time of the first failed check after the last successful one per guardian,
+    // `None` if the last check succeeded
+    let mut down_since = BTreeMap::<i32, Option<NaiveDateTime>>::new();
+    for check in checks {
+        let guardian_down_since = down_since.entry(check.guardian_id).or_default();
+        if check.online {
+            *guardian_down_since = None;
+        } else if guardian_down_since.is_none() {
+            *guardian_down_since = Some(check.time);
+        }
+    }
+
+    down_since
+        .into_iter()
+        .map(|(guardian_id, down_since)| {
+            let down_secs = down_since.map_or(0, |down_since| {
+                (now - down_since).num_seconds().max(0) as u64
+            });
+            let firing = down_since.is_some() && down_secs >= for_secs;
+            Evaluation {
+                peer_id: Some(PeerId::from(guardian_id as u16)),
+                firing,
+                value: down_secs as f64,
+                message: if firing {
+                    format!("Guardian {guardian_id} unreachable for {down_secs}s")
+                } else {
+                    format!("Guardian {guardian_id} reachable")
+                },
+            }
+        })
+        .collect()
+}
+
+/// Fires for guardians more than `max_lag_blocks` behind the median of all
+/// reported block heights
+fn block_height_lag_evaluations(
+    block_heights: BTreeMap<i32, u32>,
+    max_lag_blocks: u32,
+) -> Vec<Evaluation> {
+    let Some(consensus_height) = consensus_median(block_heights.values().copied()) else {
+        return vec![];
+    };
+
+    block_heights
+        .into_iter()
+        .map(|(guardian_id, height)| {
+            let lag_blocks = consensus_height.saturating_sub(height);
+            Evaluation {
+                peer_id: Some(PeerId::from(guardian_id as u16)),
+                firing: lag_blocks > max_lag_blocks,
+                value: lag_blocks as f64,
+                message: format!(
+                    "Guardian {guardian_id} is at block {height}, {lag_blocks} blocks behind the consensus height {consensus_height}"
+                ),
+            }
+        })
+        .collect()
+}
+
+fn guardians_below_threshold_evaluation(
+    online: usize,
+    num_guardians: usize,
+    threshold: usize,
+) -> Evaluation {
+    Evaluation {
+        peer_id: None,
+        firing: online < threshold,
+        value: online as f64,
+        message: format!(
+            "{online} of {num_guardians} guardians online, {threshold} needed for consensus"
+        ),
+    }
+}
+
+fn latency_p95_evaluations(latencies: Vec<LatencyRow>, max_latency_ms: u64) -> Vec<Evaluation> {
+    latencies
+        .into_iter()
+        .map(|row| Evaluation {
+            peer_id: Some(PeerId::from(row.guardian_id as u16)),
+            firing: row.p95_latency_ms > max_latency_ms as f64,
+            value: row.p95_latency_ms,
+            message: format!(
+                "95th percentile API latency of guardian {} is {:.0}ms",
+                row.guardian_id, row.p95_latency_ms
+            ),
+        })
+        .collect()
+}
+
+fn alert_state_str(state: AlertState) -> &'static str {
+    match state {
+        AlertState::Firing => "firing",
+        AlertState::Resolved => "resolved",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use chrono::NaiveDateTime;
+    use fedimint_core::PeerId;
+    use fmo_api_types::AlertState;
+
+    use super::{
+        alert_transition, block_height_lag_evaluations, guardian_unreachable_evaluations,
+        guardians_below_threshold_evaluation, latency_p95_evaluations, AlertTransition, LatencyRow,
+        TimedHealthCheckRow,
+    };
+
+    fn check(guardian_id: i32, secs: i64, online: bool) -> TimedHealthCheckRow {
+        TimedHealthCheckRow {
+            guardian_id,
+            time: NaiveDateTime::default() + chrono::Duration::seconds(secs),
+            online,
+        }
+    }
+
+    #[test]
+    fn test_alert_transition() {
+        assert_eq!(alert_transition(false, false), AlertTransition::None);
+        assert_eq!(alert_transition(true, true), AlertTransition::Update);
+        assert_eq!(
+            alert_transition(false, true),
+            AlertTransition::Change(AlertState::Firing)
+        );
+        assert_eq!(
+            alert_transition(true, false),
+            AlertTransition::Change(AlertState::Resolved)
+        );
+    }
+
+    #[test]
+    fn test_guardian_unreachable() {
+        let now = NaiveDateTime::default() + chrono::Duration::seconds(600);
+        let checks = vec![
+            // Down for 400s
+            check(0, 100, true),
+            check(0, 200, false),
+            check(0, 400, false),
+            // Down for 200s only
+            check(1, 300, false),
+            check(1, 400, true),
+            check(1, 400, false),
+            // Recovered
+            check(2, 100, false),
+            check(2, 500, true),
+        ];
+
+        let evaluations = guardian_unreachable_evaluations(checks, 300, now);
+        let firing = evaluations
+            .iter()
+            .map(|evaluation| (evaluation.peer_id, evaluation.firing, evaluation.value))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            firing,
+            vec![
+                (Some(PeerId::from(0)), true, 400.0),
+                (Some(PeerId::from(1)), false, 200.0),
+                (Some(PeerId::from(2)), false, 0.0),
+            ]
+        );
+        assert_eq!(evaluations[0].message, "Guardian 0 unreachable for 400s");
+        assert_eq!(evaluations[2].message, "Guardian 2 reachable");
+    }
+
+    #[test]
+    fn test_block_height_lag() {
+        assert!(block_height_lag_evaluations(BTreeMap::new(), 3).is_empty());
+
+        let evaluations =
+            block_height_lag_evaluations(BTreeMap::from([(0, 100), (1, 100), (2, 97), (3, 96)]), 3);
+        let lags = evaluations
+            .iter()
+            .map(|evaluation| (evaluation.value, evaluation.firing))
+            .collect::<Vec<_>>();
+        // The consensus height is the median of 96, 97, 100 and 100
+        assert_eq!(
+            lags,
+            vec![(0.0, false), (0.0, false), (3.0, false), (4.0, true)]
+        );
+    }
+
+    #[test]
+    fn test_guardians_below_threshold() {
+        let evaluation = guardians_below_threshold_evaluation(2, 4, 3);
+        assert!(evaluation.firing);
+        assert_eq!(evaluation.peer_id, None);
+        assert_eq!(
+            evaluation.message,
+            "2 of 4 guardians online, 3 needed for consensus"
+        );
+
+        assert!(!guardians_below_threshold_evaluation(3, 4, 3).firing);
+    }
+
+    #[test]
+    fn test_latency_p95() {
+        let evaluations = latency_p95_evaluations(
+            vec![
+                LatencyRow {
+                    guardian_id: 0,
+                    p95_latency_ms: 1000.0,
+                },
+                LatencyRow {
+                    guardian_id: 1,
+                    p95_latency_ms: 1000.4,
+                },
+            ],
+            1000,
+        );
+        assert!(!evaluations[0].firing);
+        assert!(evaluations[1].firing);
+        assert_eq!(
+            evaluations[1].message,
+            "95th percentile API latency of guardian 1 is 1000ms"
+        );
+    }
+}
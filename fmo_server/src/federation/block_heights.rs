@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use axum::extract::{Path, State};
+use axum::Json;
+use chrono::NaiveDate;
+use fedimint_core::config::FederationId;
+use fedimint_core::encoding::Encodable;
+use fedimint_core::PeerId;
+use fmo_api_types::{
+    BlockHeightActivity, BlockHeightStatus, FederationBlockHeights, GuardianBlockHeight,
+};
+use postgres_from_row::FromRow;
+
+use crate::federation::observer::FederationObserver;
+use crate::util::{consensus_median, query};
+use crate::AppState;
+
+/// A guardian is only considered lagging if it's more than one block behind
+/// since a block may have been found between its vote and the others' votes
+const LAGGING_BLOCKS: u32 = 2;
+/// Roughly an hour worth of blocks
+const STUCK_BLOCKS: u32 = 6;
+
+pub(super) async fn get_guardian_block_heights(
+    Path(federation_id): Path<FederationId>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<FederationBlockHeights>> {
+    Ok(state
+        .federation_observer
+        .guardian_block_heights(federation_id)
+        .await?
+        .into())
+}
+
+pub(super) async fn get_guardian_block_height_history(
+    Path(federation_id): Path<FederationId>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<BTreeMap<NaiveDate, BlockHeightActivity>>> {
+    Ok(state
+        .federation_observer
+        .guardian_block_height_history(federation_id)
+        .await?
+        .into())
+}
+
+impl FederationObserver {
+    pub async fn guardian_block_heights(
+        &self,
+        federation_id: FederationId,
+    ) -> anyhow::Result<FederationBlockHeights> {
+        #[derive(Debug, FromRow)]
+        struct LatestVoteRow {
+            proposer: i32,
+            session_index: i32,
+            height_vote: i32,
+        }
+
+        let config = self
+            .get_federation(federation_id)
+            .await?
+            .context("Federation doesn't exist")?
+            .config;
+
+        let latest_votes = query::<LatestVoteRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            SELECT DISTINCT ON (proposer) proposer, session_index, height_vote
+            FROM block_height_votes
+            WHERE federation_id = $1
+            ORDER BY proposer, session_index DESC, item_index DESC
+            ",
+            &[&federation_id.consensus_encode_to_vec()],
+        )
+        .await?
+        .into_iter()
+        .map(|vote| (PeerId::from(vote.proposer as u16), vote))
+        .collect::<BTreeMap<_, _>>();
+
+        let consensus_height =
+            consensus_median(latest_votes.values().map(|vote| vote.height_vote as u32));
+
+        let guardians = config
+            .global
+            .api_endpoints
+            .keys()
+            .map(|peer_id| {
+                let vote = latest_votes.get(peer_id);
+                let height = vote.map(|vote| vote.height_vote as u32);
+                let lag_blocks = height
+                    .zip(consensus_height)
+                    .map(|(height, consensus_height)| consensus_height.saturating_sub(height));
+
+                (
+                    *peer_id,
+                    GuardianBlockHeight {
+                        height,
+                        session_index: vote.map(|vote| vote.session_index as u64),
+                        lag_blocks,
+                        status: block_height_status(lag_blocks),
+                    },
+                )
+            })
+            .collect();
+
+        Ok(FederationBlockHeights {
+            consensus_height,
+            guardians,
+        })
+    }
+
+    /// Daily lag of each guardian behind the consensus height. Guardians only
+    /// vote when their bitcoind sees a new block, so the latest vote is
+    /// carried forward across days in which a guardian didn't vote.
+    pub async fn guardian_block_height_history(
+        &self,
+        federation_id: FederationId,
+    ) -> anyhow::Result<BTreeMap<NaiveDate, BlockHeightActivity>> {
+        #[derive(Debug, FromRow)]
+        struct DailyVoteRow {
+            date: NaiveDate,
+            proposer: i32,
+            height_vote: i32,
+        }
+
+        self.get_federation(federation_id)
+            .await?
+            .context("Federation doesn't exist")?;
+
+        let daily_votes = query::<DailyVoteRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            SELECT DATE(st.estimated_session_timestamp) AS date,
+                   bhv.proposer,
+                   MAX(bhv.height_vote)                 AS height_vote
+            FROM block_height_votes bhv
+                     JOIN session_times st
+                          ON bhv.federation_id = st.federation_id AND bhv.session_index = st.session_index
+            WHERE bhv.federation_id = $1
+              AND st.estimated_session_timestamp IS NOT NULL
+            GROUP BY date, bhv.proposer
+            ORDER BY date
+            ",
+            &[&federation_id.consensus_encode_to_vec()],
+        )
+        .await?;
+
+        let mut latest_heights = BTreeMap::<PeerId, u32>::new();
+        let mut history = BTreeMap::new();
+        let mut votes = daily_votes.into_iter().peekable();
+        while let Some(vote) = votes.next() {
+            latest_heights.insert(PeerId::from(vote.proposer as u16), vote.height_vote as u32);
+
+            // Only evaluate once all votes of the day were applied
+            if votes.peek().is_some_and(|next| next.date == vote.date) {
+                continue;
+            }
+
+            let consensus_height = consensus_median(latest_heights.values().copied())
+                .expect("At least one vote was applied");
+            history.insert(
+                vote.date,
+                BlockHeightActivity {
+                    consensus_height,
+                    lag_blocks: latest_heights
+                        .iter()
+                        .map(|(&peer_id, &height)| {
+                            (peer_id, consensus_height.saturating_sub(height))
+                        })
+                        .collect(),
+                },
+            );
+        }
+
+        Ok(history)
+    }
+}
+
+fn block_height_status(lag_blocks: Option<u32>) -> BlockHeightStatus {
+    match lag_blocks {
+        Some(lag) if lag < LAGGING_BLOCKS => BlockHeightStatus::InSync,
+        Some(lag) if lag < STUCK_BLOCKS => BlockHeightStatus::Lagging,
+        Some(_) => BlockHeightStatus::Stuck,
+        None => BlockHeightStatus::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fmo_api_types::BlockHeightStatus;
+
+    use super::{block_height_status, LAGGING_BLOCKS, STUCK_BLOCKS};
+
+    #[test]
+    fn test_block_height_status() {
+        assert_eq!(block_height_status(Some(0)), BlockHeightStatus::InSync);
+        assert_eq!(
+            block_height_status(Some(LAGGING_BLOCKS - 1)),
+            BlockHeightStatus::InSync
+        );
+        assert_eq!(
+            block_height_status(Some(LAGGING_BLOCKS)),
+            BlockHeightStatus::Lagging
+        );
+        assert_eq!(
+            block_height_status(Some(STUCK_BLOCKS)),
+            BlockHeightStatus::Stuck
+        );
+        assert_eq!(block_height_status(None), BlockHeightStatus::Unknown);
+    }
+}
@@ -0,0 +1,433 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::{bail, ensure, Context};
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use chrono::NaiveDateTime;
+use fedimint_core::api::InviteCode;
+use fedimint_core::config::{ClientConfig, FederationId};
+use fedimint_core::encoding::Encodable;
+use fmo_api_types::{DiscoveredFederation, DiscoveryStatus};
+use nostr_sdk::{Event, Filter, Kind, RelayPool, SingleLetterTag, Timestamp};
+use postgres_from_row::FromRow;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::federation::nostr::nostr_network;
+use crate::federation::observer::FederationObserver;
+use crate::util::{config_to_json, decode_db_value, execute, query, query_opt};
+use crate::AppState;
+
+/// Fedimint federation announcement (NIP-87)
+const ANNOUNCEMENT_KIND: Kind = Kind::Custom(38173);
+
+#[derive(Debug, FromRow)]
+struct DiscoveredFederationRow {
+    federation_id: Vec<u8>,
+    invite_codes: Vec<String>,
+    network: Option<String>,
+    first_seen: NaiveDateTime,
+    last_seen: NaiveDateTime,
+    status: String,
+    last_error: Option<String>,
+}
+
+impl From<DiscoveredFederationRow> for DiscoveredFederation {
+    fn from(row: DiscoveredFederationRow) -> Self {
+        DiscoveredFederation {
+            federation_id: decode_db_value(row.federation_id),
+            invite_codes: row.invite_codes,
+            network: row.network,
+            first_seen: row.first_seen,
+            last_seen: row.last_seen,
+            status: match row.status.as_str() {
+                "pending" => DiscoveryStatus::Pending,
+                "added" => DiscoveryStatus::Added,
+                "dismissed" => DiscoveryStatus::Dismissed,
+                _ => DiscoveryStatus::Failed,
+            },
+            last_error: row.last_error,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct DiscoveredFederationsParams {
+    status: Option<DiscoveryStatus>,
+}
+
+pub(super) async fn list_discovered_federations(
+    Query(params): Query<DiscoveredFederationsParams>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<Vec<DiscoveredFederation>>> {
+    Ok(state
+        .federation_observer
+        .list_discovered_federations(params.status)
+        .await?
+        .into())
+}
+
+pub(super) async fn add_discovered_federation(
+    Path(federation_id): Path<FederationId>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<FederationId>> {
+    Ok(state
+        .federation_observer
+        .add_discovered_federation(federation_id, false)
+        .await?
+        .into())
+}
+
+pub(super) async fn dismiss_discovered_federation(
+    Path(federation_id): Path<FederationId>,
+    State(state): State<AppState>,
+) -> crate::error::Result<()> {
+    Ok(state
+        .federation_observer
+        .dismiss_discovered_federation(federation_id)
+        .await?)
+}
+
+impl FederationObserver {
+    pub async fn list_discovered_federations(
+        &self,
+        status: Option<DiscoveryStatus>,
+    ) -> anyhow::Result<Vec<DiscoveredFederation>> {
+        Ok(query::<DiscoveredFederationRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            SELECT federation_id, invite_codes, network, first_seen, last_seen, status, last_error
+            FROM discovered_federations
+            WHERE ($1::TEXT IS NULL OR status = $1)
+            ORDER BY last_seen DESC
+            ",
+            &[&status.map(discovery_status_str)],
+        )
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect())
+    }
+
+    /// Tries the announced invite codes until one works. If `mainnet_only` is
+    /// set federations on other networks are rejected after downloading their
+    /// config, since the announced network can't be trusted.
+    pub async fn add_discovered_federation(
+        &self,
+        federation_id: FederationId,
+        mainnet_only: bool,
+    ) -> anyhow::Result<FederationId> {
+        let discovered = query_opt::<DiscoveredFederationRow>(
+            &self.connection().await?,
+            "SELECT federation_id, invite_codes, network, first_seen, last_seen, status, last_error FROM discovered_federations WHERE federation_id = $1",
+            &[&federation_id.consensus_encode_to_vec()],
+        )
+        .await?
+        .context("Federation wasn't discovered")?;
+
+        let mut last_error = None;
+        for invite in &discovered.invite_codes {
+            let result = async {
+                let invite = InviteCode::from_str(invite)?;
+                if mainnet_only {
+                    let config = ClientConfig::download_from_invite_code(&invite).await?;
+                    let network = nostr_network(&config_to_json(config)?);
+                    ensure!(
+                        network.as_deref() == Some("mainnet"),
+                        "Federation isn't on mainnet but {network:?}"
+                    );
+                }
+                self.add_federation(&invite).await
+            }
+            .await;
+
+            match result {
+                Ok(federation_id) => {
+                    self.set_discovery_status(federation_id, DiscoveryStatus::Added, None)
+                        .await?;
+                    info!("Added discovered federation {federation_id}");
+                    return Ok(federation_id);
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        let error = last_error.unwrap_or_else(|| anyhow::anyhow!("No invite codes known"));
+        self.set_discovery_status(
+            federation_id,
+            DiscoveryStatus::Failed,
+            Some(&format!("{error:?}")),
+        )
+        .await?;
+
+        Err(error.context("Failed to add discovered federation"))
+    }
+
+    pub async fn dismiss_discovered_federation(
+        &self,
+        federation_id: FederationId,
+    ) -> anyhow::Result<()> {
+        let updated = self
+            .set_discovery_status(federation_id, DiscoveryStatus::Dismissed, None)
+            .await?;
+        ensure!(updated, "Federation wasn't discovered");
+
+        Ok(())
+    }
+
+    async fn set_discovery_status(
+        &self,
+        federation_id: FederationId,
+        status: DiscoveryStatus,
+        error: Option<&str>,
+    ) -> anyhow::Result<bool> {
+        let updated = execute(
+            &self.connection().await?,
+            "UPDATE discovered_federations SET status = $2, last_error = $3 WHERE federation_id = $1",
+            &[
+                &federation_id.consensus_encode_to_vec(),
+                &discovery_status_str(status),
+                &error,
+            ],
+        )
+        .await?;
+
+        Ok(updated == 1)
+    }
+
+    /// Collects federation announcements published after `since` and, if
+    /// enabled, starts observing newly discovered mainnet federations
+    pub(super) async fn sync_federation_announcements(
+        &self,
+        client: &RelayPool,
+        since: Option<Timestamp>,
+    ) -> anyhow::Result<()> {
+        let events = self
+            .fetch_all_events(
+                client,
+                Filter {
+                    ids: None,
+                    authors: None,
+                    kinds: Some(vec![ANNOUNCEMENT_KIND].into_iter().collect()),
+                    search: None,
+                    since: None,
+                    until: None,
+                    limit: None,
+                    generic_tags: HashMap::new(),
+                },
+                since,
+            )
+            .await?;
+
+        let announcements = events
+            .into_iter()
+            .filter_map(|event| match Announcement::try_from(event) {
+                Ok(announcement) => Some(announcement),
+                Err(e) => {
+                    warn!("Ignoring invalid federation announcement: {e:?}");
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let connection = self.connection().await?;
+        let now = chrono::Utc::now().naive_utc();
+        for announcement in announcements {
+            execute(
+                &connection,
+                // language=postgresql
+                "
+                INSERT INTO discovered_federations (federation_id, invite_codes, network, event, first_seen, last_seen, status)
+                VALUES ($1, $2, $3, $4, $5, $5,
+                        CASE WHEN EXISTS (SELECT 1 FROM federations WHERE federation_id = $1) THEN 'added' ELSE 'pending' END)
+                ON CONFLICT (federation_id) DO UPDATE
+                    SET invite_codes = ARRAY(SELECT DISTINCT unnest(discovered_federations.invite_codes || excluded.invite_codes)),
+                        network      = COALESCE(excluded.network, discovered_federations.network),
+                        event        = excluded.event,
+                        last_seen    = excluded.last_seen
+                ",
+                &[
+                    &announcement.federation_id.consensus_encode_to_vec(),
+                    &announcement.invite_codes,
+                    &announcement.network,
+                    &serde_json::to_value(announcement.event).expect("can be serialized"),
+                    &now,
+                ],
+            )
+            .await?;
+        }
+
+        if !self.auto_add_discovered_federations {
+            return Ok(());
+        }
+
+        for discovered in self
+            .list_discovered_federations(Some(DiscoveryStatus::Pending))
+            .await?
+            .into_iter()
+            .filter(|discovered| discovered.network.as_deref() == Some("mainnet"))
+        {
+            if let Err(e) = self
+                .add_discovered_federation(discovered.federation_id, true)
+                .await
+            {
+                warn!(
+                    "Failed to auto-add discovered federation {}: {e:?}",
+                    discovered.federation_id
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct Announcement {
+    federation_id: FederationId,
+    invite_codes: Vec<String>,
+    network: Option<String>,
+    event: Event,
+}
+
+impl TryFrom<Event> for Announcement {
+    type Error = anyhow::Error;
+
+    fn try_from(event: Event) -> Result<Self, Self::Error> {
+        ensure!(
+            event.kind == ANNOUNCEMENT_KIND,
+            "Not a federation announcement"
+        );
+
+        let tag_values = |tag: char| {
+            let tag = SingleLetterTag::from_char(tag).expect("Tag is valid");
+            event
+                .tags()
+                .iter()
+                .filter(move |event_tag| event_tag.single_letter_tag() == Some(tag))
+                .filter_map(|event_tag| event_tag.as_vec().get(1).cloned())
+                .collect::<Vec<_>>()
+        };
+
+        let federation_id = tag_values('d')
+            .first()
+            .context("No federation id tag found")?
+            .parse::<FederationId>()?;
+
+        // Only keep invite codes that actually belong to the announced federation
+        let invite_codes = tag_values('u')
+            .into_iter()
+            .filter(|invite| {
+                InviteCode::from_str(invite)
+                    .is_ok_and(|invite| invite.federation_id() == federation_id)
+            })
+            .collect::<Vec<_>>();
+        if invite_codes.is_empty() {
+            bail!("No valid invite code for {federation_id} found");
+        }
+
+        Ok(Announcement {
+            federation_id,
+            invite_codes,
+            network: tag_values('n').first().cloned(),
+            event,
+        })
+    }
+}
+
+fn discovery_status_str(status: DiscoveryStatus) -> &'static str {
+    match status {
+        DiscoveryStatus::Pending => "pending",
+        DiscoveryStatus::Added => "added",
+        DiscoveryStatus::Dismissed => "dismissed",
+        DiscoveryStatus::Failed => "failed",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fedimint_core::api::InviteCode;
+    use fedimint_core::config::FederationId;
+    use fedimint_core::PeerId;
+    use nostr_sdk::{Event, EventBuilder, Keys, Kind, Tag};
+
+    use super::{Announcement, ANNOUNCEMENT_KIND};
+
+    fn invite_code(federation_id: FederationId) -> String {
+        InviteCode::new(
+            "wss://guardian-0.example.com/".parse().unwrap(),
+            PeerId::from(0),
+            federation_id,
+        )
+        .to_string()
+    }
+
+    fn event(kind: Kind, tags: &[&[&str]]) -> Event {
+        EventBuilder::new(kind, "", tags.iter().map(|tag| Tag::parse(tag).unwrap()))
+            .to_event(&Keys::generate())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_announcement() {
+        let federation_id = FederationId::dummy();
+        let other_federation_id = "4".repeat(64).parse::<FederationId>().unwrap();
+        let invite = invite_code(federation_id);
+
+        let announcement = Announcement::try_from(event(
+            ANNOUNCEMENT_KIND,
+            &[
+                &["d", &federation_id.to_string()],
+                &["u", &invite],
+                // Invite codes of other federations and garbage are dropped
+                &["u", &invite_code(other_federation_id)],
+                &["u", "not an invite code"],
+                &["n", "mainnet"],
+            ],
+        ))
+        .unwrap();
+        assert_eq!(announcement.federation_id, federation_id);
+        assert_eq!(announcement.invite_codes, vec![invite.clone()]);
+        assert_eq!(announcement.network.as_deref(), Some("mainnet"));
+
+        // The network is optional
+        let announcement = Announcement::try_from(event(
+            ANNOUNCEMENT_KIND,
+            &[&["d", &federation_id.to_string()], &["u", &invite]],
+        ))
+        .unwrap();
+        assert_eq!(announcement.network, None);
+    }
+
+    #[test]
+    fn test_parse_invalid_announcement() {
+        let federation_id = FederationId::dummy();
+        let invite = invite_code(federation_id);
+
+        // Wrong kind
+        assert!(Announcement::try_from(event(
+            Kind::TextNote,
+            &[&["d", &federation_id.to_string()], &["u", &invite]],
+        ))
+        .is_err());
+        // No federation id
+        assert!(Announcement::try_from(event(ANNOUNCEMENT_KIND, &[&["u", &invite]])).is_err());
+        // Invalid federation id
+        assert!(Announcement::try_from(event(
+            ANNOUNCEMENT_KIND,
+            &[&["d", "not a federation id"], &["u", &invite]],
+        ))
+        .is_err());
+        // No invite code of the announced federation
+        assert!(Announcement::try_from(event(
+            ANNOUNCEMENT_KIND,
+            &[
+                &["d", &federation_id.to_string()],
+                &["u", &invite_code("4".repeat(64).parse().unwrap())],
+            ],
+        ))
+        .is_err());
+    }
+}
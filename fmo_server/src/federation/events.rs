@@ -0,0 +1,185 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::extract::{Path, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use deadpool_postgres::GenericClient;
+use fedimint_core::config::FederationId;
+use fedimint_core::encoding::Encodable;
+use fedimint_core::Amount;
+use fmo_api_types::FederationEvent;
+use futures::Stream;
+use postgres_from_row::FromRow;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::debug;
+
+use crate::federation::observer::FederationObserver;
+use crate::util::{decode_db_value, query};
+use crate::AppState;
+
+/// Server-sent event stream of all observed federations
+pub(super) async fn global_events(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    event_stream(state.federation_observer, None)
+}
+
+/// Server-sent event stream of a single federation
+pub(super) async fn federation_events(
+    Path(federation_id): Path<FederationId>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>> {
+    state
+        .federation_observer
+        .get_federation(federation_id)
+        .await?
+        .context("Federation doesn't exist")?;
+
+    Ok(event_stream(state.federation_observer, Some(federation_id)))
+}
+
+fn event_stream(
+    observer: FederationObserver,
+    federation_id: Option<FederationId>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let mut events = observer.subscribe_events();
+
+    let stream = async_stream::stream! {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(skipped)) => {
+                    // Clients refetch aggregated data on new events anyway, so missing some
+                    // isn't a problem
+                    debug!("Event subscriber lagged behind, skipped {skipped} events");
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+
+            if federation_id.is_none() || federation_id == Some(event.federation_id()) {
+                yield Event::default().event(event.kind()).json_data(&event);
+            }
+        }
+    };
+
+    // Keeps connections open through proxies that time out idle requests
+    Sse::new(stream).keep_alive(KeepAlive::new().interval(Duration::from_secs(15)))
+}
+
+impl FederationObserver {
+    /// Events for everything ingested with the session, has to be called with
+    /// the transaction the session was processed in
+    pub(super) async fn session_events(
+        dbtx: &impl GenericClient,
+        federation_id: FederationId,
+        session_index: u64,
+    ) -> anyhow::Result<Vec<FederationEvent>> {
+        #[derive(Debug, FromRow)]
+        struct TransactionRow {
+            txid: Vec<u8>,
+            amount_msat: i64,
+        }
+
+        #[derive(Debug, FromRow)]
+        struct PegInRow {
+            on_chain_txid: Vec<u8>,
+            on_chain_vout: i32,
+            txid: Vec<u8>,
+            amount_msat: i64,
+        }
+
+        #[derive(Debug, FromRow)]
+        struct PegOutRow {
+            txid: Vec<u8>,
+            address: String,
+            amount_msat: i64,
+        }
+
+        let federation_id_param = federation_id.consensus_encode_to_vec();
+        let session_index_param = session_index as i32;
+
+        let transactions = query::<TransactionRow>(
+            dbtx,
+            // language=postgresql
+            "
+            SELECT t.txid, COALESCE(SUM(ti.amount_msat), 0)::BIGINT AS amount_msat
+            FROM transactions t
+                     LEFT JOIN transaction_inputs ti ON t.federation_id = ti.federation_id AND t.txid = ti.txid
+            WHERE t.federation_id = $1
+              AND t.session_index = $2
+            GROUP BY t.txid, t.item_index
+            ORDER BY t.item_index
+            ",
+            &[&federation_id_param, &session_index_param],
+        )
+        .await?;
+
+        let peg_ins = query::<PegInRow>(
+            dbtx,
+            // language=postgresql
+            "
+            SELECT wpi.on_chain_txid, wpi.on_chain_vout, wpi.txid, wpi.amount_msat
+            FROM wallet_peg_ins wpi
+                     JOIN transactions t ON wpi.federation_id = t.federation_id AND wpi.txid = t.txid
+            WHERE wpi.federation_id = $1
+              AND t.session_index = $2
+            ",
+            &[&federation_id_param, &session_index_param],
+        )
+        .await?;
+
+        let peg_outs = query::<PegOutRow>(
+            dbtx,
+            // language=postgresql
+            "
+            SELECT wwa.txid, wwa.address, tout.amount_msat
+            FROM wallet_withdrawal_addresses wwa
+                     JOIN transaction_outputs tout ON wwa.federation_id = tout.federation_id
+                AND wwa.txid = tout.txid
+                AND wwa.out_index = tout.out_index
+            WHERE wwa.federation_id = $1
+              AND wwa.session_index = $2
+            ",
+            &[&federation_id_param, &session_index_param],
+        )
+        .await?;
+
+        let mut events = vec![FederationEvent::Session {
+            federation_id,
+            session_index,
+            transactions: transactions.len() as u64,
+        }];
+
+        for transaction in transactions {
+            events.push(FederationEvent::Transaction {
+                federation_id,
+                txid: decode_db_value(transaction.txid),
+                session_index,
+                amount: Amount::from_msats(transaction.amount_msat as u64),
+            });
+        }
+
+        for peg_in in peg_ins {
+            events.push(FederationEvent::PegIn {
+                federation_id,
+                // On-chain txids are stored in display byte order, see `process_transaction`
+                on_chain_txid: hex::encode(peg_in.on_chain_txid),
+                on_chain_vout: peg_in.on_chain_vout as u32,
+                txid: decode_db_value(peg_in.txid),
+                amount: Amount::from_msats(peg_in.amount_msat as u64),
+            });
+        }
+
+        for peg_out in peg_outs {
+            events.push(FederationEvent::PegOut {
+                federation_id,
+                txid: decode_db_value(peg_out.txid),
+                address: peg_out.address,
+                amount: Amount::from_msats(peg_out.amount_msat as u64),
+            });
+        }
+
+        Ok(events)
+    }
+}
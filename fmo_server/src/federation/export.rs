@@ -0,0 +1,421 @@
+use std::fmt::{Display, Formatter};
+
+use anyhow::{bail, Context};
+use axum::body::{Body, Bytes};
+use axum::extract::{Path, Query, State};
+use axum::http::header::{ACCEPT, CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Response};
+use chrono::{NaiveDate, NaiveDateTime};
+use fedimint_core::config::FederationId;
+use fedimint_core::encoding::Encodable;
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+use tokio_postgres::types::Type;
+use tokio_postgres::Row;
+
+use crate::federation::observer::FederationObserver;
+use crate::AppState;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum ExportDataset {
+    Transactions,
+    TransactionInputs,
+    TransactionOutputs,
+    Sessions,
+    PegIns,
+    PegOuts,
+    Utxos,
+    Histogram,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum ExportFormat {
+    Csv,
+    Ndjson,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ExportParams {
+    /// Takes precedence over the `Accept` header
+    format: Option<ExportFormat>,
+}
+
+pub(super) async fn export_dataset(
+    Path((federation_id, dataset)): Path<(FederationId, ExportDataset)>,
+    Query(params): Query<ExportParams>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> crate::error::Result<Response> {
+    let format = ExportFormat::negotiate(params.format, &headers);
+
+    let (columns, rows) = state
+        .federation_observer
+        .export_rows(federation_id, dataset)
+        .await?;
+
+    Ok((
+        [
+            (CONTENT_TYPE, format.content_type().to_owned()),
+            (
+                CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"{federation_id}-{dataset}.{}\"",
+                    format.file_extension()
+                ),
+            ),
+        ],
+        Body::from_stream(encode_rows(columns, rows, format)),
+    )
+        .into_response())
+}
+
+impl FederationObserver {
+    /// Streams the rows of an export dataset directly from Postgres, so even
+    /// large federations don't have to be loaded into memory
+    pub(super) async fn export_rows(
+        &self,
+        federation_id: FederationId,
+        dataset: ExportDataset,
+    ) -> anyhow::Result<(
+        Vec<String>,
+        impl Stream<Item = Result<Row, tokio_postgres::Error>> + Send + 'static,
+    )> {
+        self.get_federation(federation_id)
+            .await?
+            .context("Federation doesn't exist")?;
+
+        let conn = self.connection().await?;
+        let statement = conn.prepare(dataset.query()).await?;
+        let columns = statement
+            .columns()
+            .iter()
+            .map(|column| column.name().to_owned())
+            .collect();
+        let rows = conn
+            .query_raw(&statement, [federation_id.consensus_encode_to_vec()])
+            .await?;
+
+        // The connection has to stay checked out of the pool till all rows were read
+        Ok((
+            columns,
+            rows.map(move |row| {
+                let _conn = &conn;
+                row
+            }),
+        ))
+    }
+}
+
+impl ExportDataset {
+    /// Queries take the federation id as their only parameter. Binary ids are
+    /// hex encoded, on-chain txids in their usual display order.
+    fn query(self) -> &'static str {
+        match self {
+            // language=postgresql
+            ExportDataset::Transactions => "
+                SELECT encode(t.txid, 'hex')             AS txid,
+                       t.session_index,
+                       t.item_index,
+                       st.estimated_session_timestamp AS timestamp
+                FROM transactions t
+                         LEFT JOIN session_times st
+                                   ON t.federation_id = st.federation_id AND t.session_index = st.session_index
+                WHERE t.federation_id = $1
+                ORDER BY t.session_index, t.item_index
+            ",
+            // language=postgresql
+            ExportDataset::TransactionInputs => "
+                SELECT encode(ti.txid, 'hex')           AS txid,
+                       ti.in_index,
+                       ti.kind,
+                       ti.amount_msat,
+                       encode(ti.ln_contract_id, 'hex') AS ln_contract_id
+                FROM transaction_inputs ti
+                         JOIN transactions t ON ti.federation_id = t.federation_id AND ti.txid = t.txid
+                WHERE ti.federation_id = $1
+                ORDER BY t.session_index, t.item_index, ti.in_index
+            ",
+            // language=postgresql
+            ExportDataset::TransactionOutputs => "
+                SELECT encode(tout.txid, 'hex')           AS txid,
+                       tout.out_index,
+                       tout.kind,
+                       tout.amount_msat,
+                       tout.ln_contract_interaction_kind,
+                       encode(tout.ln_contract_id, 'hex') AS ln_contract_id
+                FROM transaction_outputs tout
+                         JOIN transactions t ON tout.federation_id = t.federation_id AND tout.txid = t.txid
+                WHERE tout.federation_id = $1
+                ORDER BY t.session_index, t.item_index, tout.out_index
+            ",
+            // language=postgresql
+            ExportDataset::Sessions => "
+                SELECT s.session_index,
+                       st.estimated_session_timestamp                          AS timestamp,
+                       (SELECT COUNT(*)
+                        FROM transactions t
+                        WHERE t.federation_id = s.federation_id
+                          AND t.session_index = s.session_index)::bigint        AS transactions,
+                       (SELECT COALESCE(SUM(spi.items), 0)
+                        FROM session_peer_items spi
+                        WHERE spi.federation_id = s.federation_id
+                          AND spi.session_index = s.session_index)::bigint      AS items
+                FROM sessions s
+                         LEFT JOIN session_times st
+                                   ON s.federation_id = st.federation_id AND s.session_index = st.session_index
+                WHERE s.federation_id = $1
+                ORDER BY s.session_index
+            ",
+            // language=postgresql
+            ExportDataset::PegIns => "
+                SELECT encode(wpi.on_chain_txid, 'hex') AS on_chain_txid,
+                       wpi.on_chain_vout,
+                       wpi.address,
+                       wpi.amount_msat,
+                       encode(wpi.txid, 'hex')          AS txid,
+                       wpi.in_index,
+                       t.session_index
+                FROM wallet_peg_ins wpi
+                         JOIN transactions t ON wpi.federation_id = t.federation_id AND wpi.txid = t.txid
+                WHERE wpi.federation_id = $1
+                ORDER BY t.session_index, t.item_index, wpi.in_index
+            ",
+            // language=postgresql
+            ExportDataset::PegOuts => "
+                SELECT encode(wws.on_chain_txid, 'hex')   AS on_chain_txid,
+                       encode(wws.federation_txid, 'hex') AS txid,
+                       wws.request_session_index,
+                       wws.threshold_session_index,
+                       wws.confirmation_block_height,
+                       wws.weight,
+                       wws.fee_sat,
+                       wws.request_time,
+                       wws.threshold_time,
+                       wws.confirmation_time
+                FROM wallet_withdrawal_stats wws
+                WHERE wws.federation_id = $1
+                ORDER BY wws.threshold_session_index NULLS LAST
+            ",
+            // language=postgresql
+            ExportDataset::Utxos => "
+                SELECT encode(on_chain_txid, 'hex') AS on_chain_txid,
+                       on_chain_vout,
+                       address,
+                       amount_msat
+                FROM utxos
+                WHERE federation_id = $1
+                ORDER BY on_chain_txid, on_chain_vout
+            ",
+            // language=postgresql
+            ExportDataset::Histogram => "
+                WITH transaction_amounts AS (SELECT t.txid,
+                                                    DATE(st.estimated_session_timestamp) AS date,
+                                                    COALESCE((SELECT SUM(ti.amount_msat)
+                                                              FROM transaction_inputs ti
+                                                              WHERE ti.federation_id = t.federation_id
+                                                                AND ti.txid = t.txid), 0) AS amount_msat,
+                                                    COALESCE(tf.fee_msat, 0)                  AS fee_msat
+                                             FROM transactions t
+                                                      JOIN session_times st
+                                                           ON t.federation_id = st.federation_id AND
+                                                              t.session_index = st.session_index
+                                                      LEFT JOIN transaction_fees tf
+                                                                ON t.federation_id = tf.federation_id AND t.txid = tf.txid
+                                             WHERE t.federation_id = $1
+                                               AND st.estimated_session_timestamp IS NOT NULL)
+                SELECT date,
+                       COUNT(*)::bigint         AS transactions,
+                       SUM(amount_msat)::bigint AS volume_msat,
+                       SUM(fee_msat)::bigint    AS fees_msat
+                FROM transaction_amounts
+                GROUP BY date
+                ORDER BY date
+            ",
+        }
+    }
+}
+
+impl Display for ExportDataset {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ExportDataset::Transactions => "transactions",
+            ExportDataset::TransactionInputs => "transaction_inputs",
+            ExportDataset::TransactionOutputs => "transaction_outputs",
+            ExportDataset::Sessions => "sessions",
+            ExportDataset::PegIns => "peg_ins",
+            ExportDataset::PegOuts => "peg_outs",
+            ExportDataset::Utxos => "utxos",
+            ExportDataset::Histogram => "histogram",
+        };
+        f.write_str(name)
+    }
+}
+
+impl ExportFormat {
+    /// Picks the requested format, falling back to the `Accept` header and
+    /// then CSV
+    fn negotiate(requested: Option<ExportFormat>, headers: &HeaderMap) -> ExportFormat {
+        requested
+            .or_else(|| {
+                let accept = headers.get(ACCEPT)?.to_str().ok()?;
+                if accept.contains("text/csv") {
+                    Some(ExportFormat::Csv)
+                } else if accept.contains("application/x-ndjson") {
+                    Some(ExportFormat::Ndjson)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(ExportFormat::Csv)
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    fn file_extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+fn encode_rows(
+    columns: Vec<String>,
+    rows: impl Stream<Item = Result<Row, tokio_postgres::Error>> + Send + 'static,
+    format: ExportFormat,
+) -> impl Stream<Item = anyhow::Result<Bytes>> + Send + 'static {
+    async_stream::try_stream! {
+        if let ExportFormat::Csv = format {
+            let mut writer = csv::Writer::from_writer(vec![]);
+            writer.write_record(&columns)?;
+            yield Bytes::from(writer.into_inner()?);
+        }
+
+        let mut rows = Box::pin(rows);
+        while let Some(row) = rows.next().await {
+            yield Bytes::from(encode_row(&columns, row_values(&row?)?, format)?);
+        }
+    }
+}
+
+/// Encodes a single row as a CSV record or NDJSON object, including the
+/// trailing newline
+fn encode_row(
+    columns: &[String],
+    values: Vec<serde_json::Value>,
+    format: ExportFormat,
+) -> anyhow::Result<Vec<u8>> {
+    Ok(match format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(vec![]);
+            writer.write_record(values.iter().map(|value| match value {
+                serde_json::Value::Null => String::new(),
+                serde_json::Value::String(string) => string.clone(),
+                other => other.to_string(),
+            }))?;
+            writer.into_inner()?
+        }
+        ExportFormat::Ndjson => {
+            let object = columns
+                .iter()
+                .cloned()
+                .zip(values)
+                .collect::<serde_json::Map<_, _>>();
+            let mut line = serde_json::to_vec(&object)?;
+            line.push(b'\n');
+            line
+        }
+    })
+}
+
+/// Converts the column types used by export queries to JSON
+fn row_values(row: &Row) -> anyhow::Result<Vec<serde_json::Value>> {
+    row.columns()
+        .iter()
+        .enumerate()
+        .map(|(idx, column)| {
+            let column_type = column.type_();
+            Ok(if *column_type == Type::INT4 {
+                json!(row.try_get::<_, Option<i32>>(idx)?)
+            } else if *column_type == Type::INT8 {
+                json!(row.try_get::<_, Option<i64>>(idx)?)
+            } else if *column_type == Type::BOOL {
+                json!(row.try_get::<_, Option<bool>>(idx)?)
+            } else if *column_type == Type::TEXT {
+                json!(row.try_get::<_, Option<String>>(idx)?)
+            } else if *column_type == Type::TIMESTAMP {
+                json!(row.try_get::<_, Option<NaiveDateTime>>(idx)?)
+            } else if *column_type == Type::DATE {
+                json!(row.try_get::<_, Option<NaiveDate>>(idx)?)
+            } else {
+                bail!("Unsupported column type {column_type} of {}", column.name())
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::header::ACCEPT;
+    use axum::http::HeaderMap;
+    use serde_json::json;
+
+    use super::{encode_row, ExportFormat};
+
+    #[test]
+    fn test_negotiate_format() {
+        let mut headers = HeaderMap::new();
+        assert!(matches!(
+            ExportFormat::negotiate(None, &headers),
+            ExportFormat::Csv
+        ));
+
+        headers.insert(ACCEPT, "application/x-ndjson".parse().unwrap());
+        assert!(matches!(
+            ExportFormat::negotiate(None, &headers),
+            ExportFormat::Ndjson
+        ));
+        // The query parameter takes precedence over the header
+        assert!(matches!(
+            ExportFormat::negotiate(Some(ExportFormat::Csv), &headers),
+            ExportFormat::Csv
+        ));
+
+        headers.insert(ACCEPT, "text/html, */*".parse().unwrap());
+        assert!(matches!(
+            ExportFormat::negotiate(None, &headers),
+            ExportFormat::Csv
+        ));
+    }
+
+    #[test]
+    fn test_encode_row() {
+        let columns = vec![
+            "txid".to_owned(),
+            "amount_msat".to_owned(),
+            "kind".to_owned(),
+        ];
+        let values = || vec![json!("ab,cd"), json!(1000), json!(null)];
+
+        assert_eq!(
+            String::from_utf8(encode_row(&columns, values(), ExportFormat::Csv).unwrap()).unwrap(),
+            "\"ab,cd\",1000,\n"
+        );
+
+        let line = encode_row(&columns, values(), ExportFormat::Ndjson).unwrap();
+        assert_eq!(line.last(), Some(&b'\n'));
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&line).unwrap(),
+            json!({"txid": "ab,cd", "amount_msat": 1000, "kind": null})
+        );
+    }
+}
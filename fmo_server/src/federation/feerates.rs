@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use axum::extract::{Path, State};
+use axum::Json;
+use chrono::NaiveDate;
+use fedimint_core::config::FederationId;
+use fedimint_core::encoding::Encodable;
+use fedimint_core::PeerId;
+use fmo_api_types::{FederationFeerates, FeerateActivity, GuardianFeerateVote};
+use postgres_from_row::FromRow;
+
+use crate::federation::observer::FederationObserver;
+use crate::util::{consensus_median, query};
+use crate::AppState;
+
+pub(super) async fn get_federation_feerates(
+    Path(federation_id): Path<FederationId>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<FederationFeerates>> {
+    Ok(state
+        .federation_observer
+        .federation_feerates(federation_id)
+        .await?
+        .into())
+}
+
+pub(super) async fn get_federation_feerate_history(
+    Path(federation_id): Path<FederationId>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<BTreeMap<NaiveDate, FeerateActivity>>> {
+    Ok(state
+        .federation_observer
+        .federation_feerate_history(federation_id)
+        .await?
+        .into())
+}
+
+impl FederationObserver {
+    pub async fn federation_feerates(
+        &self,
+        federation_id: FederationId,
+    ) -> anyhow::Result<FederationFeerates> {
+        #[derive(Debug, FromRow)]
+        struct LatestVoteRow {
+            proposer: i32,
+            session_index: i32,
+            sats_per_kvb: i64,
+        }
+
+        self.get_federation(federation_id)
+            .await?
+            .context("Federation doesn't exist")?;
+
+        let latest_votes = query::<LatestVoteRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            SELECT DISTINCT ON (proposer) proposer, session_index, sats_per_kvb
+            FROM feerate_votes
+            WHERE federation_id = $1
+            ORDER BY proposer, session_index DESC, item_index DESC
+            ",
+            &[&federation_id.consensus_encode_to_vec()],
+        )
+        .await?;
+
+        let consensus_sats_per_kvb =
+            consensus_median(latest_votes.iter().map(|vote| vote.sats_per_kvb as u64));
+
+        let guardians = latest_votes
+            .into_iter()
+            .map(|vote| {
+                let sats_per_kvb = vote.sats_per_kvb as u64;
+                let deviation = consensus_sats_per_kvb
+                    .filter(|&consensus| consensus != 0)
+                    .map(|consensus| sats_per_kvb as f64 / consensus as f64 - 1.0);
+                (
+                    PeerId::from(vote.proposer as u16),
+                    GuardianFeerateVote {
+                        session_index: vote.session_index as u64,
+                        sats_per_kvb,
+                        deviation,
+                    },
+                )
+            })
+            .collect();
+
+        Ok(FederationFeerates {
+            consensus_sats_per_kvb,
+            guardians,
+        })
+    }
+
+    pub async fn federation_feerate_history(
+        &self,
+        federation_id: FederationId,
+    ) -> anyhow::Result<BTreeMap<NaiveDate, FeerateActivity>> {
+        #[derive(Debug, FromRow)]
+        struct DailyVoteRow {
+            date: NaiveDate,
+            proposer: i32,
+            sats_per_kvb: i64,
+        }
+
+        self.get_federation(federation_id)
+            .await?
+            .context("Federation doesn't exist")?;
+
+        let daily_votes = query::<DailyVoteRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            SELECT DISTINCT ON (date, fv.proposer) DATE(st.estimated_session_timestamp) AS date,
+                                                   fv.proposer,
+                                                   fv.sats_per_kvb
+            FROM feerate_votes fv
+                     JOIN session_times st
+                          ON fv.federation_id = st.federation_id AND fv.session_index = st.session_index
+            WHERE fv.federation_id = $1
+              AND st.estimated_session_timestamp IS NOT NULL
+            ORDER BY date, fv.proposer, fv.session_index DESC, fv.item_index DESC
+            ",
+            &[&federation_id.consensus_encode_to_vec()],
+        )
+        .await?;
+
+        let mut history = BTreeMap::<NaiveDate, FeerateActivity>::new();
+        for vote in daily_votes {
+            history
+                .entry(vote.date)
+                .or_insert_with(|| FeerateActivity {
+                    consensus_sats_per_kvb: None,
+                    guardians: BTreeMap::new(),
+                })
+                .guardians
+                .insert(PeerId::from(vote.proposer as u16), vote.sats_per_kvb as u64);
+        }
+
+        for activity in history.values_mut() {
+            activity.consensus_sats_per_kvb =
+                consensus_median(activity.guardians.values().copied());
+        }
+
+        Ok(history)
+    }
+}
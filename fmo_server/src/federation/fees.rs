@@ -0,0 +1,251 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use axum::extract::{Path, State};
+use axum::Json;
+use chrono::NaiveDate;
+use fedimint_core::config::{ClientConfig, FederationId};
+use fedimint_core::encoding::Encodable;
+use fedimint_core::Amount;
+use fmo_api_types::{FederationFees, FeeActivity, ModuleFeeParams};
+use postgres_from_row::FromRow;
+
+use crate::federation::observer::FederationObserver;
+use crate::util::{config_to_json, query, query_one};
+use crate::AppState;
+
+pub(super) async fn get_federation_fees(
+    Path(federation_id): Path<FederationId>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<FederationFees>> {
+    Ok(state
+        .federation_observer
+        .federation_fees(federation_id)
+        .await?
+        .into())
+}
+
+pub(super) async fn get_federation_fee_histogram(
+    Path(federation_id): Path<FederationId>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<BTreeMap<NaiveDate, FeeActivity>>> {
+    Ok(state
+        .federation_observer
+        .federation_fee_histogram(federation_id)
+        .await?
+        .into())
+}
+
+#[derive(Debug, FromRow)]
+struct ModuleItemCountRow {
+    date: Option<NaiveDate>,
+    kind: String,
+    inputs: i64,
+    outputs: i64,
+}
+
+impl FederationObserver {
+    pub async fn federation_fees(
+        &self,
+        federation_id: FederationId,
+    ) -> anyhow::Result<FederationFees> {
+        #[derive(Debug, FromRow)]
+        struct FeeTotalsRow {
+            collected: i64,
+            transactions: i64,
+        }
+
+        let config = self
+            .get_federation(federation_id)
+            .await?
+            .context("Federation doesn't exist")?
+            .config;
+        let fee_params = module_fee_params(&config)?;
+
+        let totals = query_one::<FeeTotalsRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            SELECT COALESCE(SUM(fee_msat), 0)::bigint AS collected,
+                   COUNT(*)::bigint                   AS transactions
+            FROM transaction_fees
+            WHERE federation_id = $1
+            ",
+            &[&federation_id.consensus_encode_to_vec()],
+        )
+        .await?;
+
+        let expected = self
+            .module_item_counts(federation_id, false)
+            .await?
+            .iter()
+            .map(|row| expected_module_fee(&fee_params, row))
+            .sum::<Amount>();
+
+        Ok(FederationFees {
+            collected: Amount::from_msats(totals.collected.max(0) as u64),
+            transactions: totals.transactions as u64,
+            expected,
+            fee_params,
+        })
+    }
+
+    pub async fn federation_fee_histogram(
+        &self,
+        federation_id: FederationId,
+    ) -> anyhow::Result<BTreeMap<NaiveDate, FeeActivity>> {
+        #[derive(Debug, FromRow)]
+        struct DailyFeeRow {
+            date: NaiveDate,
+            collected: i64,
+        }
+
+        let config = self
+            .get_federation(federation_id)
+            .await?
+            .context("Federation doesn't exist")?
+            .config;
+        let fee_params = module_fee_params(&config)?;
+
+        let daily_fees = query::<DailyFeeRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            SELECT DATE(st.estimated_session_timestamp)  AS date,
+                   COALESCE(SUM(tf.fee_msat), 0)::bigint AS collected
+            FROM transaction_fees tf
+                     JOIN session_times st
+                          ON tf.federation_id = st.federation_id AND tf.session_index = st.session_index
+            WHERE tf.federation_id = $1
+              AND st.estimated_session_timestamp IS NOT NULL
+            GROUP BY date
+            ORDER BY date
+            ",
+            &[&federation_id.consensus_encode_to_vec()],
+        )
+        .await?;
+
+        let mut histogram = daily_fees
+            .into_iter()
+            .map(|row| {
+                (
+                    row.date,
+                    FeeActivity {
+                        collected: Amount::from_msats(row.collected.max(0) as u64),
+                        by_module: BTreeMap::new(),
+                    },
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        for row in self.module_item_counts(federation_id, true).await? {
+            let Some(date) = row.date else {
+                continue;
+            };
+
+            let module_fee = expected_module_fee(&fee_params, &row);
+            let day = histogram.entry(date).or_insert_with(|| FeeActivity {
+                collected: Amount::ZERO,
+                by_module: BTreeMap::new(),
+            });
+            *day.by_module
+                .entry(row.kind.clone())
+                .or_insert(Amount::ZERO) += module_fee;
+        }
+
+        Ok(histogram)
+    }
+
+    /// Number of inputs and outputs per module kind, optionally split by day
+    async fn module_item_counts(
+        &self,
+        federation_id: FederationId,
+        daily: bool,
+    ) -> anyhow::Result<Vec<ModuleItemCountRow>> {
+        // language=postgresql
+        const DAILY_QUERY: &str = "
+            WITH items AS (SELECT federation_id, txid, kind, 1 AS inputs, 0 AS outputs
+                           FROM transaction_inputs
+                           WHERE federation_id = $1
+                           UNION ALL
+                           SELECT federation_id, txid, kind, 0 AS inputs, 1 AS outputs
+                           FROM transaction_outputs
+                           WHERE federation_id = $1)
+            SELECT DATE(st.estimated_session_timestamp) AS date,
+                   i.kind,
+                   SUM(i.inputs)::bigint                AS inputs,
+                   SUM(i.outputs)::bigint               AS outputs
+            FROM items i
+                     JOIN transactions t ON i.federation_id = t.federation_id AND i.txid = t.txid
+                     JOIN session_times st ON t.federation_id = st.federation_id AND t.session_index = st.session_index
+            GROUP BY date, i.kind
+            ORDER BY date
+        ";
+
+        // language=postgresql
+        const TOTAL_QUERY: &str = "
+            SELECT NULL::date AS date,
+                   kind,
+                   SUM(inputs)::bigint  AS inputs,
+                   SUM(outputs)::bigint AS outputs
+            FROM (SELECT kind, 1 AS inputs, 0 AS outputs
+                  FROM transaction_inputs
+                  WHERE federation_id = $1
+                  UNION ALL
+                  SELECT kind, 0 AS inputs, 1 AS outputs
+                  FROM transaction_outputs
+                  WHERE federation_id = $1) items
+            GROUP BY kind
+        ";
+
+        query::<ModuleItemCountRow>(
+            &self.connection().await?,
+            if daily { DAILY_QUERY } else { TOTAL_QUERY },
+            &[&federation_id.consensus_encode_to_vec()],
+        )
+        .await
+    }
+}
+
+fn expected_module_fee(
+    fee_params: &BTreeMap<String, ModuleFeeParams>,
+    row: &ModuleItemCountRow,
+) -> Amount {
+    fee_params
+        .get(&row.kind)
+        .map(|params| params.input * (row.inputs as u64) + params.output * (row.outputs as u64))
+        .unwrap_or(Amount::ZERO)
+}
+
+/// Extracts the per input/output fees of all modules we know about from the
+/// client config
+fn module_fee_params(config: &ClientConfig) -> anyhow::Result<BTreeMap<String, ModuleFeeParams>> {
+    let json_config = config_to_json(config.clone())?;
+
+    Ok(json_config
+        .modules
+        .values()
+        .filter_map(|module| {
+            let kind = module.kind().as_str();
+            let (input_field, output_field) = match kind {
+                "mint" => ("note_spend_abs", "note_issuance_abs"),
+                "ln" => ("contract_input", "contract_output"),
+                "wallet" => ("peg_in_abs", "peg_out_abs"),
+                _ => return None,
+            };
+
+            let fee_consensus = module.value().get("fee_consensus")?;
+            let parse_fee = |field: &str| {
+                serde_json::from_value::<Amount>(fee_consensus.get(field)?.clone()).ok()
+            };
+
+            Some((
+                kind.to_owned(),
+                ModuleFeeParams {
+                    input: parse_fee(input_field)?,
+                    output: parse_fee(output_field)?,
+                },
+            ))
+        })
+        .collect())
+}
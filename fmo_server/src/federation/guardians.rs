@@ -1,22 +1,91 @@
+use std::collections::BTreeMap;
 use std::time::{Duration, Instant};
 
 use anyhow::Context;
 use fedimint_core::api::{DynGlobalApi, FederationApiExt, StatusResponse};
 use fedimint_core::config::{ClientConfig, FederationId};
+use fedimint_core::core::ModuleInstanceId;
 use fedimint_core::encoding::Encodable;
 use fedimint_core::endpoint_constants::{BLOCK_COUNT_LOCAL_ENDPOINT, STATUS_ENDPOINT};
 use fedimint_core::module::ApiRequestErased;
+use fedimint_core::PeerId;
+use fmo_api_types::FederationEvent;
 use futures::future::join_all;
+use tracing::warn;
 
 use crate::federation::observer::FederationObserver;
 
+/// Result of querying a single guardian's status and block height
+pub(crate) struct GuardianProbe {
+    pub peer_id: PeerId,
+    pub status: Option<StatusResponse>,
+    pub block_height: Option<u32>,
+    /// Time the block height request took
+    pub api_latency: Duration,
+}
+
+/// Queries the status and wallet block count of all `peers` concurrently
+pub(crate) async fn probe_guardians(
+    api: &DynGlobalApi,
+    peers: impl IntoIterator<Item = PeerId>,
+    wallet_module: ModuleInstanceId,
+) -> Vec<GuardianProbe> {
+    const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+    join_all(peers.into_iter().map(|peer_id| {
+        let api = api.clone();
+        async move {
+            // We don't time the first request, there might be a reconnect happening in
+            // the background
+            let status = api
+                .request_single_peer(
+                    Some(REQUEST_TIMEOUT),
+                    STATUS_ENDPOINT.to_owned(),
+                    ApiRequestErased::default(),
+                    peer_id,
+                )
+                .await
+                .ok()
+                .and_then(|json| serde_json::from_value::<StatusResponse>(json).ok());
+
+            // Second request is used to determine ping
+            // TODO: how much time does bitcoind take to answer if at all (caching?)?
+            let start_time = Instant::now();
+            let block_height = api
+                .with_module(wallet_module)
+                .request_single_peer(
+                    Some(REQUEST_TIMEOUT),
+                    BLOCK_COUNT_LOCAL_ENDPOINT.to_owned(),
+                    ApiRequestErased::default(),
+                    peer_id,
+                )
+                .await
+                .ok()
+                .and_then(|json| serde_json::from_value::<Option<u32>>(json).ok().flatten())
+                .map(|block_count| {
+                    // Fedimint uses 1-based block heights, while bitcoind uses 0-based
+                    // heights
+                    block_count - 1
+                });
+            let api_latency = start_time.elapsed();
+
+            GuardianProbe {
+                peer_id,
+                status,
+                block_height,
+                api_latency,
+            }
+        }
+    }))
+    .await
+}
+
 impl FederationObserver {
     pub async fn monitor_health(
         &self,
         federation_id: FederationId,
         config: ClientConfig,
     ) -> anyhow::Result<()> {
-        const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
         const REQUEST_INTERVAL: Duration = Duration::from_secs(60);
 
         let mut interval = tokio::time::interval(REQUEST_INTERVAL);
@@ -29,58 +98,31 @@ impl FederationObserver {
             })
             .context("Wallet module not found")?;
 
+        // Used to only publish status changes, not every check
+        let mut last_online = BTreeMap::new();
+
         loop {
             interval.tick().await;
 
-            let peer_status_responses =
-                join_all(config.global.api_endpoints.keys().map(|&peer_id| {
-                    let api = api.clone();
-                    async move {
-                        // We don't time the first request, there might be a reconnect happening in
-                        // the background
-                        let status = api
-                            .request_single_peer(
-                                Some(REQUEST_TIMEOUT),
-                                STATUS_ENDPOINT.to_owned(),
-                                ApiRequestErased::default(),
-                                peer_id,
-                            )
-                            .await
-                            .ok()
-                            .and_then(|json| serde_json::from_value::<StatusResponse>(json).ok());
-
-                        // Second request is used to determine ping
-                        // TODO: how much time does bitcoind take to answer if at all (caching?)?
-                        let start_time = Instant::now();
-                        let block_height = api
-                            .with_module(wallet_module)
-                            .request_single_peer(
-                                Some(REQUEST_TIMEOUT),
-                                BLOCK_COUNT_LOCAL_ENDPOINT.to_owned(),
-                                ApiRequestErased::default(),
-                                peer_id,
-                            )
-                            .await
-                            .ok()
-                            .and_then(|json| {
-                                serde_json::from_value::<Option<u32>>(json).ok().flatten()
-                            })
-                            .map(|block_count| {
-                                // Fedimint uses 1-based block heights, while bitcoind uses 0-based
-                                // heights
-                                block_count - 1
-                            });
-                        let api_latency = start_time.elapsed();
-
-                        (peer_id, status, block_height, api_latency)
-                    }
-                }))
-                .await;
+            let peer_status_responses = probe_guardians(
+                &api,
+                config.global.api_endpoints.keys().copied(),
+                wallet_module,
+            )
+            .await;
 
             let mut conn = self.connection().await?;
             let dbtx = conn.transaction().await?;
             let timestamp = chrono::Utc::now().naive_utc();
-            for (peer_id, status, block_height, api_latency) in peer_status_responses {
+            let mut guardian_statuses = Vec::new();
+            for GuardianProbe {
+                peer_id,
+                status,
+                block_height,
+                api_latency,
+            } in peer_status_responses
+            {
+                guardian_statuses.push((peer_id, status.is_some(), block_height));
                 dbtx.execute(
                     "INSERT INTO guardian_health VALUES ($1, $2, $3, $4, $5, $6)",
                     &[
@@ -94,7 +136,29 @@ impl FederationObserver {
                 )
                 .await?;
             }
+
+            let status_changes = guardian_statuses
+                .into_iter()
+                .filter(|&(peer_id, online, _)| last_online.insert(peer_id, online) != Some(online))
+                .map(
+                    |(peer_id, online, block_height)| FederationEvent::GuardianStatus {
+                        federation_id,
+                        peer_id,
+                        online,
+                        block_height,
+                    },
+                )
+                .collect::<Vec<_>>();
+            Self::enqueue_webhook_deliveries(&dbtx, &status_changes).await?;
             dbtx.commit().await?;
+
+            for event in status_changes {
+                self.broadcast_event(event);
+            }
+
+            if let Err(e) = self.evaluate_alert_rules(federation_id, &config).await {
+                warn!("Error while evaluating alert rules of {federation_id}: {e:?}");
+            }
         }
     }
 }
@@ -1,11 +1,22 @@
+use std::collections::BTreeSet;
+use std::time::Duration;
+
 use anyhow::Context;
 use axum::extract::{Path, State};
 use axum::Json;
-use fedimint_core::config::FederationId;
+use fedimint_core::config::{ClientConfig, FederationId};
+use fedimint_core::encoding::Encodable;
+use fedimint_core::PeerId;
+use fmo_api_types::FederationEvent;
+use postgres_from_row::FromRow;
+use tokio::time::interval;
+use tracing::{info, warn};
 
-use crate::config::meta::MetaFields;
-use crate::meta::federation_meta;
-use crate::util::config_to_json;
+use crate::config::meta::{MetaFields, MetaOverrideCache};
+use crate::federation::db::Federation;
+use crate::federation::observer::FederationObserver;
+use crate::meta::{federation_meta, federation_meta_fields};
+use crate::util::{config_to_json, execute, query_opt};
 
 // FIXME: cache meta in DB
 pub(super) async fn get_federation_meta(
@@ -21,3 +32,123 @@ pub(super) async fn get_federation_meta(
 
     federation_meta(&config_to_json(config)?, &state).await
 }
+
+impl FederationObserver {
+    /// Periodically re-fetches the config and meta fields of all observed
+    /// federations to publish events when they change
+    pub(super) async fn watch_federation_configs(self) {
+        const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+        let meta_override_cache = MetaOverrideCache::default();
+        let mut interval = interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let federations = match self.list_federations().await {
+                Ok(federations) => federations,
+                Err(e) => {
+                    warn!("Error while listing federations: {e:?}");
+                    continue;
+                }
+            };
+
+            for federation in federations {
+                let federation_id = federation.federation_id;
+                if let Err(e) = self
+                    .check_federation_config(federation, &meta_override_cache)
+                    .await
+                {
+                    warn!("Error while checking config of {federation_id}: {e:?}");
+                }
+            }
+        }
+    }
+
+    async fn check_federation_config(
+        &self,
+        federation: Federation,
+        meta_override_cache: &MetaOverrideCache,
+    ) -> anyhow::Result<()> {
+        #[derive(Debug, FromRow)]
+        struct ConfigSnapshot {
+            config: serde_json::Value,
+            meta: serde_json::Value,
+        }
+
+        let federation_id = federation.federation_id;
+        let invite = federation
+            .config
+            .invite_code(&PeerId::from(0))
+            .expect("There should always be a peer 0");
+
+        let config = config_to_json(ClientConfig::download_from_invite_code(&invite).await?)?;
+        let meta = federation_meta_fields(&config, meta_override_cache).await?;
+
+        let config = serde_json::to_value(&config).expect("Can be serialized");
+        let meta = serde_json::to_value(&meta).expect("Can be serialized");
+
+        let previous = query_opt::<ConfigSnapshot>(
+            &self.connection().await?,
+            "SELECT config, meta FROM federation_config_snapshots WHERE federation_id = $1",
+            &[&federation_id.consensus_encode_to_vec()],
+        )
+        .await?;
+
+        execute(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            INSERT INTO federation_config_snapshots (federation_id, config, meta, checked_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (federation_id) DO UPDATE SET config     = excluded.config,
+                                                      meta       = excluded.meta,
+                                                      checked_at = excluded.checked_at
+            ",
+            &[
+                &federation_id.consensus_encode_to_vec(),
+                &config,
+                &meta,
+                &chrono::Utc::now().naive_utc(),
+            ],
+        )
+        .await?;
+
+        // The first snapshot is only the baseline
+        let Some(previous) = previous else {
+            return Ok(());
+        };
+
+        if previous.config != config {
+            info!("Config of {federation_id} changed");
+            self.publish_event(FederationEvent::ConfigChanged { federation_id })
+                .await?;
+        }
+
+        let changed_keys = changed_meta_keys(
+            &serde_json::from_value(previous.meta).unwrap_or_default(),
+            &serde_json::from_value(meta).expect("Was serialized from MetaFields"),
+        );
+        if !changed_keys.is_empty() {
+            info!("Meta fields {changed_keys:?} of {federation_id} changed");
+            self.publish_event(FederationEvent::MetaChanged {
+                federation_id,
+                changed_keys,
+            })
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Keys that were added, removed or changed
+fn changed_meta_keys(previous: &MetaFields, current: &MetaFields) -> Vec<String> {
+    previous
+        .keys()
+        .chain(current.keys())
+        .filter(|key| previous.get(*key) != current.get(*key))
+        .cloned()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
@@ -1,43 +1,171 @@
+mod admin;
+pub mod alerts;
+mod block_heights;
 pub mod db;
-mod guardians;
+mod discovery;
+mod events;
+mod export;
+mod feerates;
+mod fees;
+pub mod guardians;
 mod meta;
-mod nostr;
+pub mod nostr;
+mod nostr_attestations;
+mod nostr_contacts;
+mod nostr_relays;
 pub mod observer;
+mod participation;
+pub mod reindex;
+mod reviews;
+pub mod search;
 mod session;
+mod solvency;
 mod transaction;
+mod webhooks;
+mod withdrawals;
+
+use std::net::SocketAddr;
 
 use anyhow::Context;
-use axum::extract::{Path, State};
-use axum::routing::{get, put};
+use axum::extract::{ConnectInfo, Path, State};
+use axum::http::HeaderMap;
+use axum::routing::{delete, get, post, put};
 use axum::{Json, Router};
-use axum_auth::AuthBearer;
 use fedimint_core::api::InviteCode;
 use fedimint_core::config::{ClientConfig, FederationId, JsonClientConfig};
 use fedimint_core::core::ModuleInstanceId;
 use fedimint_core::module::registry::ModuleDecoderRegistry;
-use fmo_api_types::{FederationSummary, FedimintTotals};
+use fmo_api_types::{ApiScope, FederationSummary, FedimintTotals};
 use serde_json::json;
 
+use crate::auth::RequireScope;
+use crate::federation::admin::remove_observed_federation;
+use crate::federation::block_heights::{
+    get_guardian_block_height_history, get_guardian_block_heights,
+};
+use crate::federation::discovery::{
+    add_discovered_federation, dismiss_discovered_federation, list_discovered_federations,
+};
+use crate::federation::events::{federation_events, global_events};
+use crate::federation::export::export_dataset;
+use crate::federation::feerates::{get_federation_feerate_history, get_federation_feerates};
+use crate::federation::fees::{get_federation_fee_histogram, get_federation_fees};
 use crate::federation::meta::get_federation_meta;
+use crate::federation::nostr_attestations::get_nostr_public_key;
+use crate::federation::nostr_relays::{add_nostr_relay, list_nostr_relays, remove_nostr_relay};
+use crate::federation::participation::get_guardian_participation;
+use crate::federation::reindex::{list_reindex_jobs, reindex_all_federations, reindex_federation};
+use crate::federation::reviews::list_reviews;
 use crate::federation::session::{count_sessions, list_sessions};
+use crate::federation::solvency::{get_federation_solvency, get_federation_solvency_history};
 use crate::federation::transaction::{
     count_transactions, list_transactions, transaction, transaction_histogram,
 };
+use crate::federation::webhooks::{
+    create_webhook, delete_webhook, list_webhook_deliveries, list_webhooks,
+};
+use crate::federation::withdrawals::{list_withdrawals, withdrawal_statistics};
 use crate::util::{config_to_json, get_decoders};
 use crate::{federation, AppState};
 
 pub fn get_federations_routes() -> Router<AppState> {
     Router::new()
         .route("/", get(list_observed_federations))
-        .route("/", put(add_observed_federation))
+        .route(
+            "/",
+            put(add_observed_federation).require_scope(ApiScope::AddFederation),
+        )
         .route("/totals", get(get_federation_totals))
+        .route("/events", get(global_events))
         .route("/nostr/rating", put(publish_rating_event))
+        .route("/nostr/pubkey", get(get_nostr_public_key))
+        .route(
+            "/nostr/relays",
+            get(list_nostr_relays).require_scope(ApiScope::Relays),
+        )
+        .route(
+            "/nostr/relays",
+            post(add_nostr_relay).require_scope(ApiScope::Relays),
+        )
+        .route(
+            "/nostr/relays",
+            delete(remove_nostr_relay).require_scope(ApiScope::Relays),
+        )
+        .route(
+            "/discovered",
+            get(list_discovered_federations).require_scope(ApiScope::AddFederation),
+        )
+        .route(
+            "/discovered/:federation_id/add",
+            post(add_discovered_federation).require_scope(ApiScope::AddFederation),
+        )
+        .route(
+            "/discovered/:federation_id/dismiss",
+            post(dismiss_discovered_federation).require_scope(ApiScope::AddFederation),
+        )
+        .route(
+            "/reindex",
+            get(list_reindex_jobs).require_scope(ApiScope::Reindex),
+        )
+        .route(
+            "/reindex",
+            post(reindex_all_federations).require_scope(ApiScope::Reindex),
+        )
+        .route(
+            "/webhooks",
+            get(list_webhooks).require_scope(ApiScope::Webhooks),
+        )
+        .route(
+            "/webhooks",
+            post(create_webhook).require_scope(ApiScope::Webhooks),
+        )
+        .route(
+            "/webhooks/:webhook_id",
+            delete(delete_webhook).require_scope(ApiScope::Webhooks),
+        )
+        .route(
+            "/webhooks/:webhook_id/deliveries",
+            get(list_webhook_deliveries).require_scope(ApiScope::Webhooks),
+        )
         .route("/:federation_id", get(get_federation_overview))
+        .route(
+            "/:federation_id",
+            delete(remove_observed_federation).require_scope(ApiScope::RemoveFederation),
+        )
         .route(
             "/:federation_id/config",
             get(federation::get_federation_config),
         )
+        .route(
+            "/:federation_id/guardians/block_heights",
+            get(get_guardian_block_heights),
+        )
+        .route(
+            "/:federation_id/guardians/block_heights/history",
+            get(get_guardian_block_height_history),
+        )
+        .route(
+            "/:federation_id/guardians/participation",
+            get(get_guardian_participation),
+        )
+        .route("/:federation_id/events", get(federation_events))
         .route("/:federation_id/meta", get(get_federation_meta))
+        .route(
+            "/:federation_id/reindex",
+            post(reindex_federation).require_scope(ApiScope::Reindex),
+        )
+        .route("/:federation_id/reviews", get(list_reviews))
+        .route("/:federation_id/export/:dataset", get(export_dataset))
+        .route("/:federation_id/feerates", get(get_federation_feerates))
+        .route(
+            "/:federation_id/feerates/history",
+            get(get_federation_feerate_history),
+        )
+        .route("/:federation_id/fees", get(get_federation_fees))
+        .route(
+            "/:federation_id/fees/histogram",
+            get(get_federation_fee_histogram),
+        )
         .route("/:federation_id/transactions", get(list_transactions))
         .route(
             "/:federation_id/transactions/:transaction_id",
@@ -54,6 +182,16 @@ pub fn get_federations_routes() -> Router<AppState> {
         .route("/:federation_id/utxos", get(get_federation_utxos))
         .route("/:federation_id/sessions", get(list_sessions))
         .route("/:federation_id/sessions/count", get(count_sessions))
+        .route("/:federation_id/solvency", get(get_federation_solvency))
+        .route(
+            "/:federation_id/solvency/history",
+            get(get_federation_solvency_history),
+        )
+        .route("/:federation_id/withdrawals", get(list_withdrawals))
+        .route(
+            "/:federation_id/withdrawals/stats",
+            get(withdrawal_statistics),
+        )
 }
 
 pub async fn list_observed_federations(
@@ -67,12 +205,9 @@ pub async fn list_observed_federations(
 }
 
 pub async fn add_observed_federation(
-    AuthBearer(auth): AuthBearer,
     State(state): State<AppState>,
     Json(body): Json<serde_json::Value>,
 ) -> crate::error::Result<Json<FederationId>> {
-    state.federation_observer.check_auth(&auth)?;
-
     let invite: InviteCode = serde_json::from_value(
         body.get("invite")
             .context("Request did not contain invite field")?
@@ -140,9 +275,20 @@ async fn get_federation_totals(
 
 async fn publish_rating_event(
     State(state): State<AppState>,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(event): Json<nostr_sdk::Event>,
 ) -> crate::error::Result<()> {
-    Ok(state.federation_observer.submit_rating(event).await?.into())
+    let client_ip = nostr::client_ip(
+        client_addr.ip(),
+        &headers,
+        &state.federation_observer.trusted_proxies,
+    );
+    state
+        .federation_observer
+        .submit_rating(event, client_ip)
+        .await?;
+    Ok(())
 }
 
 fn decoders_from_config(config: &ClientConfig) -> ModuleDecoderRegistry {
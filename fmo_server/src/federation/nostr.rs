@@ -1,15 +1,18 @@
-use std::collections::HashMap;
-use std::time::Duration;
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
 
 use anyhow::{ensure, Context};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, NaiveDateTime};
 use deadpool_postgres::GenericClient;
-use fedimint_core::config::FederationId;
+use fedimint_core::config::{FederationId, JsonClientConfig};
 use fedimint_core::encoding::Encodable;
 use fedimint_core::task::sleep;
-use fmo_api_types::FederationRating;
+use fmo_api_types::{FederationEvent, FederationRating, RatingWeights};
 use nostr_sdk::{
-    Event, Filter, FilterOptions, Kind, RelayOptions, RelayPool, RelayPoolOptions,
-    RelaySendOptions, SingleLetterTag,
+    Event, Filter, FilterOptions, Kind, PublicKey, Relay, RelayPool, SingleLetterTag, Timestamp,
 };
 use postgres_from_row::FromRow;
 use regex::Regex;
@@ -17,16 +20,30 @@ use tokio::time::interval;
 use tracing::{debug, info, warn};
 
 use crate::federation::observer::FederationObserver;
-use crate::util::{query, query_one};
+use crate::util::{config_to_json, query, query_one};
 
-#[derive(Debug, Clone, FromRow)]
-struct NostrRelay {
-    relay_url: String,
-}
+/// Relays limit the number of authors per filter
+pub(super) const AUTHORS_PER_REQUEST: usize = 100;
+
+/// Weight of ratings by authors the trusted pubkeys don't follow
+const UNTRUSTED_RATING_WEIGHT: f64 = 0.1;
+/// Age after which a rating only counts half
+const RATING_HALF_LIFE_DAYS: u32 = 180;
+
+/// Ratings that can be submitted per IP address and hour
+const RATINGS_PER_IP: u32 = 10;
+/// Ratings that can be submitted per author and hour
+const RATINGS_PER_PUBKEY: u32 = 3;
+/// Submitted ratings are expected to be freshly signed
+const MAX_RATING_AGE: Duration = Duration::from_secs(60 * 60);
+const MAX_RATING_CLOCK_DRIFT: Duration = Duration::from_secs(10 * 60);
 
 impl FederationObserver {
     /// Syncs Nostr events:
     ///   * Fedimint federation votes
+    ///   * Fedimint federation announcements
+    ///   * Profiles of rating authors
+    ///   * Contact lists of the trusted pubkeys
     pub async fn sync_nostr_events(self) {
         const SLEEP_SECS: u64 = 60;
         loop {
@@ -39,43 +56,32 @@ impl FederationObserver {
         }
     }
 
-    async fn nostr_relay_client(&self) -> anyhow::Result<RelayPool> {
-        let relays = query::<NostrRelay>(
-            &self.connection().await?,
-            "SELECT relay_url FROM nostr_relays",
-            &[],
-        )
-        .await?
-        .into_iter()
-        .map(|relay| relay.relay_url)
-        .collect::<Vec<_>>();
-        let client = RelayPool::new(RelayPoolOptions::default());
-        for relay_url in &relays {
-            client.add_relay(relay_url, RelayOptions::default()).await?;
-        }
-        client.connect(Some(Duration::from_secs(5))).await;
-
-        info!(?relays, "Started Nostr client");
-
-        Ok(client)
-    }
-
     async fn sync_nostr_events_inner(&self) -> anyhow::Result<()> {
+        // Events can take a while to propagate between relays, so we re-fetch a bit of
+        // the already synced time range
+        const SYNC_OVERLAP_SECS: u64 = 24 * 60 * 60;
+
         let mut interval = interval(Duration::from_secs(60));
 
         let client = self.nostr_relay_client().await?;
 
+        // Everything is fetched on the first run, later ones only fetch new events
+        let mut since = None;
+
         loop {
             interval.tick().await;
+            let sync_start = Timestamp::now();
+
+            self.update_relay_pool(&client).await?;
+            self.record_relay_status(&client).await?;
 
             let federations = self.list_federations().await?;
             let federation_tag = SingleLetterTag::from_char('d').expect("Tag is valid");
 
-            // TODO: fetch multiple pages till synced up, ok enough for now since new events
-            // will always be at the top and old ones will be ignored by us
-            let events = client
-                .get_events_of(
-                    vec![Filter {
+            let events = self
+                .fetch_all_events(
+                    &client,
+                    Filter {
                         ids: None,
                         authors: None,
                         kinds: Some(vec![Kind::Custom(38000)].into_iter().collect()),
@@ -90,9 +96,8 @@ impl FederationObserver {
                                 .map(|federation| federation.federation_id.to_string())
                                 .collect(),
                         )]),
-                    }],
-                    Duration::from_secs(30),
-                    FilterOptions::default(),
+                    },
+                    since,
                 )
                 .await?;
 
@@ -106,11 +111,72 @@ impl FederationObserver {
                 Some((parsed, event))
             });
 
+            let mut new_ratings = vec![];
             for (parsed_event, event) in parsed_events {
-                insert_parsed_event(&dbtx, parsed_event, event).await?;
+                if insert_parsed_event(&dbtx, parsed_event.clone(), event).await? {
+                    new_ratings.push(FederationEvent::from(parsed_event));
+                }
             }
 
+            // Deletions can only come from authors we already have ratings of
+            let authors =
+                query::<NostrAuthor>(&dbtx, "SELECT DISTINCT author FROM nostr_votes", &[])
+                    .await?
+                    .into_iter()
+                    .filter_map(|author| PublicKey::from_slice(&author.author).ok())
+                    .collect::<Vec<_>>();
+
+            let mut deletions = vec![];
+            for authors in authors.chunks(AUTHORS_PER_REQUEST) {
+                deletions.extend(
+                    self.fetch_all_events(
+                        &client,
+                        Filter {
+                            ids: None,
+                            authors: Some(authors.iter().copied().collect()),
+                            kinds: Some(vec![Kind::EventDeletion].into_iter().collect()),
+                            search: None,
+                            since: None,
+                            until: None,
+                            limit: None,
+                            generic_tags: HashMap::new(),
+                        },
+                        since,
+                    )
+                    .await?,
+                );
+            }
+
+            let mut deleted_ratings = 0;
+            for deletion in deletions {
+                deleted_ratings += apply_deletion(&dbtx, &deletion).await?;
+            }
+            if deleted_ratings > 0 {
+                info!("Removed {deleted_ratings} deleted ratings");
+            }
+
+            Self::enqueue_webhook_deliveries(&dbtx, &new_ratings).await?;
             dbtx.commit().await?;
+
+            for rating in new_ratings {
+                self.broadcast_event(rating);
+            }
+
+            if let Err(e) = self.sync_federation_announcements(&client, since).await {
+                warn!("Error while syncing federation announcements: {e:?}");
+            }
+
+            if let Err(e) = self.sync_nostr_profiles(&client).await {
+                warn!("Error while syncing nostr profiles: {e:?}");
+            }
+
+            if let Err(e) = self.sync_trusted_contacts(&client).await {
+                warn!("Error while syncing contact lists of trusted pubkeys: {e:?}");
+            }
+
+            since = Some(Timestamp::from(
+                sync_start.as_u64().saturating_sub(SYNC_OVERLAP_SECS),
+            ));
         }
     }
 
@@ -122,45 +188,331 @@ impl FederationObserver {
         struct FederationRatingRow {
             count: i64,
             avg: Option<f64>,
+            weighted_avg: Option<f64>,
+            trusted_count: i64,
+            effective_count: f64,
         }
 
         let query_res = query_one::<FederationRatingRow>(
             &self.connection().await?,
             // language=postgresql
-            "SELECT COUNT(star_vote)::bigint as count, AVG(star_vote)::DOUBLE PRECISION as avg from nostr_votes WHERE federation_id = $1;",
-            &[&federation_id.consensus_encode_to_vec()],
+            "
+            WITH votes AS (SELECT v.star_vote,
+                                  v.author = ANY ($2::BYTEA[]) OR EXISTS (SELECT 1
+                                                                          FROM nostr_contacts c
+                                                                          WHERE c.author = ANY ($2::BYTEA[])
+                                                                            AND v.author = ANY (c.contacts)) AS trusted,
+                                  POWER(0.5::DOUBLE PRECISION,
+                                        EXTRACT(EPOCH FROM (NOW() AT TIME ZONE 'utc') - v.created_at)::DOUBLE PRECISION /
+                                        ($4::DOUBLE PRECISION * 86400)) AS decay
+                           FROM nostr_votes v
+                           WHERE v.federation_id = $1
+                             AND v.star_vote IS NOT NULL),
+                 weighted AS (SELECT star_vote,
+                                     trusted,
+                                     (CASE WHEN trusted THEN 1 ELSE $3::DOUBLE PRECISION END) * decay AS weight
+                              FROM votes)
+            SELECT COUNT(*)::bigint                                                  AS count,
+                   AVG(star_vote)::DOUBLE PRECISION                                  AS avg,
+                   (SUM(star_vote * weight) / NULLIF(SUM(weight), 0))::DOUBLE PRECISION AS weighted_avg,
+                   COUNT(*) FILTER (WHERE trusted)::bigint                           AS trusted_count,
+                   COALESCE(SUM(weight), 0)::DOUBLE PRECISION                        AS effective_count
+            FROM weighted
+            ",
+            &[
+                &federation_id.consensus_encode_to_vec(),
+                &self
+                    .trusted_pubkeys
+                    .iter()
+                    .map(|pubkey| pubkey.to_bytes().to_vec())
+                    .collect::<Vec<_>>(),
+                &UNTRUSTED_RATING_WEIGHT,
+                &(RATING_HALF_LIFE_DAYS as f64),
+            ],
         )
         .await?;
 
         Ok(FederationRating {
             count: query_res.count as u64,
             avg: query_res.avg,
+            weighted_avg: query_res.weighted_avg,
+            weights: RatingWeights {
+                trusted_count: query_res.trusted_count as u64,
+                untrusted_weight: UNTRUSTED_RATING_WEIGHT,
+                half_life_days: RATING_HALF_LIFE_DAYS,
+                effective_count: query_res.effective_count,
+            },
         })
     }
 
-    pub async fn submit_rating(&self, nostr_event: Event) -> anyhow::Result<()> {
-        let parsed = ParsedEvent::try_from(nostr_event.clone())?;
-        let client = self.nostr_relay_client().await?;
+    /// Validates a rating submitted by a user and relays it to our relays
+    pub async fn submit_rating(&self, nostr_event: Event, client_ip: IpAddr) -> anyhow::Result<()> {
+        self.rating_rate_limiter
+            .check(format!("ip:{client_ip}"), RATINGS_PER_IP)?;
 
-        client
-            .send_event(
-                nostr_event.clone(),
-                RelaySendOptions::default().timeout(Some(Duration::from_secs(5))),
-            )
-            .await?;
+        let parsed = self.validate_rating(&nostr_event).await?;
+
+        self.rating_rate_limiter
+            .check(format!("pubkey:{}", nostr_event.pubkey), RATINGS_PER_PUBKEY)?;
+
+        let client = self.nostr_relay_client().await?;
+        self.send_nostr_event(&client, nostr_event.clone()).await?;
 
         let mut conn = self.connection().await?;
         let dbtx = conn.transaction().await?;
-        insert_parsed_event(&dbtx, parsed, nostr_event).await?;
+        let inserted = insert_parsed_event(&dbtx, parsed.clone(), nostr_event).await?;
+        let event = FederationEvent::from(parsed);
+        if inserted {
+            Self::enqueue_webhook_deliveries(&dbtx, std::slice::from_ref(&event)).await?;
+        }
         dbtx.commit().await?;
 
+        if inserted {
+            self.broadcast_event(event);
+        }
+
         Ok(())
     }
+
+    async fn validate_rating(&self, event: &Event) -> anyhow::Result<ParsedEvent> {
+        event.verify().context("Invalid event id or signature")?;
+
+        check_rating_age(event.created_at.as_u64(), Timestamp::now().as_u64())?;
+
+        if self.rating_min_pow > 0 {
+            ensure!(
+                event.check_pow(self.rating_min_pow),
+                "Rating needs a proof of work of at least {} bits",
+                self.rating_min_pow
+            );
+        }
+
+        let parsed = ParsedEvent::try_from(event.clone())?;
+
+        let federation = self
+            .get_federation(parsed.federation_id)
+            .await?
+            .context("Federation not observed")?;
+
+        let network = nostr_network(&config_to_json(federation.config)?)
+            .context("Federation has no wallet module")?;
+        let network_tag = SingleLetterTag::from_char('n').expect("Tag is valid");
+        let event_network = event
+            .tags()
+            .iter()
+            .find(|tag| tag.single_letter_tag() == Some(network_tag))
+            .and_then(|tag| tag.as_vec().get(1).cloned())
+            .context("No network tag found")?;
+        ensure!(
+            event_network == network,
+            "Network tag {event_network} doesn't match the federation's network {network}"
+        );
+
+        Ok(parsed)
+    }
+
+    /// Fetches all events matching the filter created after `since` from every
+    /// relay, relays that fail are skipped
+    pub(super) async fn fetch_all_events(
+        &self,
+        client: &RelayPool,
+        filter: Filter,
+        since: Option<Timestamp>,
+    ) -> anyhow::Result<Vec<Event>> {
+        let mut events = BTreeMap::new();
+        for (relay_url, relay) in client.relays().await {
+            match fetch_relay_events(&relay, &filter, since).await {
+                Ok(relay_events) => {
+                    self.record_relay_events_fetched(&relay_url, relay_events.len())
+                        .await?;
+                    events.extend(relay_events.into_iter().map(|event| (event.id, event)));
+                }
+                Err(e) => {
+                    warn!("Failed to fetch events from {relay_url}: {e:?}");
+                    self.record_relay_error(&relay_url, &e.to_string()).await?;
+                }
+            }
+        }
+
+        Ok(events.into_values().collect())
+    }
+}
+
+/// Fetches all events matching the filter created after `since` by paging
+/// backwards in time, relays limit how many events they return per request
+async fn fetch_relay_events(
+    relay: &Relay,
+    filter: &Filter,
+    since: Option<Timestamp>,
+) -> anyhow::Result<Vec<Event>> {
+    const PAGE_SIZE: usize = 500;
+
+    let mut events = BTreeMap::new();
+    let mut until = None;
+    loop {
+        let page = relay
+            .get_events_of(
+                vec![Filter {
+                    since,
+                    until,
+                    limit: Some(PAGE_SIZE),
+                    ..filter.clone()
+                }],
+                Duration::from_secs(30),
+                FilterOptions::default(),
+            )
+            .await?;
+
+        let page_size = page.len();
+        let oldest = page.iter().map(|event| event.created_at).min();
+        let mut new_events = 0;
+        for event in page {
+            if events.insert(event.id, event).is_none() {
+                new_events += 1;
+            }
+        }
+
+        // `until` is inclusive, so events sharing the oldest timestamp are fetched
+        // again, we are done once a page doesn't contain anything new
+        match oldest {
+            Some(oldest) if page_size >= PAGE_SIZE && new_events > 0 => until = Some(oldest),
+            _ => break,
+        }
+    }
+
+    Ok(events.into_values().collect())
+}
+
+/// Submitted ratings have to be created within [`MAX_RATING_AGE`] before `now`,
+/// allowing for some clock drift
+fn check_rating_age(created_at: u64, now: u64) -> anyhow::Result<()> {
+    ensure!(
+        created_at <= now + MAX_RATING_CLOCK_DRIFT.as_secs(),
+        "Rating is from the future"
+    );
+    ensure!(
+        created_at + MAX_RATING_AGE.as_secs() >= now,
+        "Rating is too old"
+    );
+    Ok(())
+}
+
+/// Determines the IP address of the client that submitted a rating. If the
+/// connection comes from a trusted reverse proxy the rightmost address in
+/// `X-Forwarded-For` that isn't a trusted proxy is used, otherwise all users
+/// would share the proxy's rate limit.
+pub(super) fn client_ip(
+    peer_ip: IpAddr,
+    headers: &HeaderMap,
+    trusted_proxies: &[IpAddr],
+) -> IpAddr {
+    if !trusted_proxies.contains(&peer_ip) {
+        return peer_ip;
+    }
+
+    headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(|header| header.split(','))
+        .filter_map(|ip| ip.trim().parse::<IpAddr>().ok())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .find(|ip| !trusted_proxies.contains(ip))
+        .unwrap_or(peer_ip)
+}
+
+/// Limits how often something can be done per key within a fixed time window
+#[derive(Debug, Default)]
+pub(super) struct RatingRateLimiter {
+    windows: std::sync::Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl RatingRateLimiter {
+    const WINDOW: Duration = Duration::from_secs(60 * 60);
+
+    /// Fails if `key` was already used `limit` times in the current window.
+    /// Expired windows are evicted on every call so the map only holds keys
+    /// seen within the last [`Self::WINDOW`].
+    fn check(&self, key: String, limit: u32) -> Result<(), RateLimited> {
+        self.check_at(key, limit, Instant::now())
+    }
+
+    fn check_at(&self, key: String, limit: u32, now: Instant) -> Result<(), RateLimited> {
+        let mut windows = self.windows.lock().expect("Lock poisoned");
+        windows.retain(|_, (start, _)| now.saturating_duration_since(*start) < Self::WINDOW);
+
+        let (start, count) = windows.entry(key).or_insert((now, 0));
+        if *count >= limit {
+            return Err(RateLimited {
+                retry_after: Self::WINDOW.saturating_sub(now.saturating_duration_since(*start)),
+            });
+        }
+        *count += 1;
+        Ok(())
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.windows.lock().expect("Lock poisoned").len()
+    }
+}
+
+/// Returned when a client submits too many ratings, reported as 429 with a
+/// `Retry-After` header instead of the usual 400
+#[derive(Debug)]
+pub struct RateLimited {
+    pub retry_after: Duration,
+}
+
+impl std::fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Too many ratings submitted, try again later")
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+impl IntoResponse for RateLimited {
+    fn into_response(self) -> Response {
+        // Round up so clients don't retry just before the window ends
+        let retry_after_secs =
+            self.retry_after.as_secs() + u64::from(self.retry_after.subsec_nanos() > 0);
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after_secs.to_string())],
+            format!("Error: {self}"),
+        )
+            .into_response()
+    }
+}
+
+/// Network name as used in the `n` tag of NIP-87 events
+pub(super) fn nostr_network(config: &JsonClientConfig) -> Option<String> {
+    let network = config.modules.values().find_map(|module| {
+        if module.kind().as_str() != "wallet" {
+            return None;
+        }
+        module.value()["network"].as_str().map(ToOwned::to_owned)
+    })?;
+
+    Some(match network.as_str() {
+        "bitcoin" => "mainnet".to_owned(),
+        _ => network,
+    })
+}
+
+#[derive(Debug, Clone, FromRow)]
+struct NostrAuthor {
+    author: Vec<u8>,
 }
 
 #[derive(Debug, Clone)]
 struct ParsedEvent {
     event_id: [u8; 32],
+    author: [u8; 32],
+    created_at: NaiveDateTime,
     federation_id: FederationId,
     star_vote: Option<u8>,
 }
@@ -199,17 +551,31 @@ impl TryFrom<Event> for ParsedEvent {
 
         Ok(ParsedEvent {
             event_id,
+            author: event.pubkey.to_bytes(),
+            created_at: timestamp_to_naive(event.created_at)?,
             federation_id,
             star_vote,
         })
     }
 }
 
+impl From<ParsedEvent> for FederationEvent {
+    fn from(event: ParsedEvent) -> Self {
+        FederationEvent::Rating {
+            federation_id: event.federation_id,
+            event_id: hex::encode(event.event_id),
+            star_vote: event.star_vote,
+        }
+    }
+}
+
+/// Returns `false` if the event was already known, superseded by a newer
+/// rating of the same author or deleted
 async fn insert_parsed_event(
     dbtx: &deadpool_postgres::Transaction<'_>,
     parsed_event: ParsedEvent,
     event: Event,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<bool> {
     debug!(
         "Inserting event {} for federation {}",
         hex::encode(&parsed_event.event_id),
@@ -217,19 +583,145 @@ async fn insert_parsed_event(
     );
 
     let now = chrono::Utc::now().naive_utc();
-    dbtx.execute(
+    let inserted = dbtx.execute(
         // language=postgresql
-        "INSERT INTO nostr_votes (event_id, federation_id, star_vote, event, fetch_time) VALUES ($1, $2, $3, $4, $5) ON CONFLICT DO NOTHING",
+        "
+        INSERT INTO nostr_votes (event_id, federation_id, star_vote, event, fetch_time, author, created_at)
+        SELECT $1, $2, $3, $4, $5, $6, $7
+        WHERE NOT EXISTS (SELECT 1
+                          FROM nostr_vote_deletions
+                          WHERE author = $6
+                            AND (event_id = $1 OR (federation_id = $2 AND created_at >= $7)))
+        ON CONFLICT (author, federation_id) DO UPDATE SET event_id   = excluded.event_id,
+                                                          star_vote  = excluded.star_vote,
+                                                          event      = excluded.event,
+                                                          fetch_time = excluded.fetch_time,
+                                                          created_at = excluded.created_at
+        -- Same tie-break as in the v13 migration
+        WHERE (excluded.created_at, excluded.event_id) > (nostr_votes.created_at, nostr_votes.event_id)
+        ",
         &[
             &parsed_event.event_id.to_vec(),
             &parsed_event.federation_id.consensus_encode_to_vec(),
             &parsed_event.star_vote.map(|vote| vote as i32),
             &serde_json::to_value(event).expect("can be serialized"),
-            &now
+            &now,
+            &parsed_event.author.to_vec(),
+            &parsed_event.created_at,
         ],
     ).await?;
 
-    Ok(())
+    Ok(inserted == 1)
+}
+
+/// Applies a NIP-09 deletion to ratings of its author, returns the number of
+/// deleted ratings
+async fn apply_deletion(
+    dbtx: &deadpool_postgres::Transaction<'_>,
+    deletion: &Event,
+) -> anyhow::Result<u64> {
+    if deletion.kind != Kind::EventDeletion {
+        return Ok(0);
+    }
+
+    let deletion_event_id = deletion.id.to_bytes().to_vec();
+    let author = deletion.pubkey.to_bytes().to_vec();
+    let created_at = timestamp_to_naive(deletion.created_at)?;
+
+    let mut deleted = 0;
+    for tag in deletion.tags() {
+        let tag = tag.as_vec();
+        let (Some(tag_kind), Some(value)) = (tag.first(), tag.get(1)) else {
+            continue;
+        };
+
+        match tag_kind.as_str() {
+            "e" => {
+                let Ok(event_id) = hex::decode(value) else {
+                    continue;
+                };
+
+                dbtx.execute(
+                    "INSERT INTO nostr_vote_deletions (deletion_event_id, author, event_id, created_at) VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING",
+                    &[&deletion_event_id, &author, &event_id, &created_at],
+                )
+                .await?;
+                deleted += dbtx
+                    .execute(
+                        "DELETE FROM nostr_votes WHERE event_id = $1 AND author = $2",
+                        &[&event_id, &author],
+                    )
+                    .await?;
+            }
+            "a" => {
+                let Some(federation_id) = parse_rating_address(value, &author) else {
+                    continue;
+                };
+                let federation_id = federation_id.consensus_encode_to_vec();
+
+                dbtx.execute(
+                    "INSERT INTO nostr_vote_deletions (deletion_event_id, author, federation_id, created_at) VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING",
+                    &[&deletion_event_id, &author, &federation_id, &created_at],
+                )
+                .await?;
+                deleted += dbtx
+                    .execute(
+                        "DELETE FROM nostr_votes WHERE author = $1 AND federation_id = $2 AND created_at <= $3",
+                        &[&author, &federation_id, &created_at],
+                    )
+                    .await?;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(deleted)
+}
+
+/// Parses the address of a rating (`38000:<author>:<federation_id>`) from an
+/// `a` tag, returns `None` if it isn't one or belongs to someone other than
+/// `author` since authors can only delete their own events
+fn parse_rating_address(address: &str, author: &[u8]) -> Option<FederationId> {
+    let mut address = address.splitn(3, ':');
+    let (Some("38000"), Some(address_author), Some(federation_id)) =
+        (address.next(), address.next(), address.next())
+    else {
+        return None;
+    };
+    if hex::decode(address_author).ok()? != author {
+        return None;
+    }
+    federation_id.parse::<FederationId>().ok()
+}
+
+/// Latest event of each author, for replaceable events relays might still
+/// return outdated versions
+pub(super) fn latest_per_author(events: Vec<Event>) -> HashMap<PublicKey, Event> {
+    let mut latest_events = HashMap::<PublicKey, Event>::new();
+    for event in events {
+        match latest_events.get(&event.pubkey) {
+            Some(latest) if latest.created_at >= event.created_at => {}
+            _ => {
+                latest_events.insert(event.pubkey, event);
+            }
+        }
+    }
+    latest_events
+}
+
+pub(super) fn timestamp_to_naive(timestamp: Timestamp) -> anyhow::Result<NaiveDateTime> {
+    Ok(DateTime::from_timestamp(timestamp.as_u64() as i64, 0)
+        .context("Invalid timestamp")?
+        .naive_utc())
+}
+
+/// Comment without the `[n/5]` star rating prefix
+pub(super) fn strip_star_rating(comment: &str) -> &str {
+    let re = Regex::new(r"^\[[0-9]+/5]").expect("valid regex");
+    match re.find(comment) {
+        Some(prefix) => comment[prefix.end()..].trim_start(),
+        None => comment,
+    }
 }
 
 fn extract_star_rating(comment: &str) -> Option<u8> {
@@ -242,3 +734,146 @@ fn extract_star_rating(comment: &str) -> Option<u8> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+    use std::time::{Duration, Instant};
+
+    use axum::http::{header, HeaderMap, StatusCode};
+    use axum::response::IntoResponse;
+    use fedimint_core::config::FederationId;
+    use nostr_sdk::{EventBuilder, Keys, Kind, Timestamp};
+
+    use super::{
+        check_rating_age, client_ip, latest_per_author, parse_rating_address, strip_star_rating,
+        RateLimited, RatingRateLimiter, MAX_RATING_AGE,
+    };
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RatingRateLimiter::default();
+        let start = Instant::now();
+        assert!(limiter.check_at("ip:1.2.3.4".to_owned(), 2, start).is_ok());
+        assert!(limiter.check_at("ip:1.2.3.4".to_owned(), 2, start).is_ok());
+        let limited = limiter
+            .check_at("ip:1.2.3.4".to_owned(), 2, start + Duration::from_secs(60))
+            .unwrap_err();
+        assert_eq!(
+            limited.retry_after,
+            RatingRateLimiter::WINDOW - Duration::from_secs(60)
+        );
+        // Keys are limited independently
+        assert!(limiter.check_at("ip:5.6.7.8".to_owned(), 2, start).is_ok());
+        assert!(limiter.check_at("ip:5.6.7.8".to_owned(), 0, start).is_err());
+        assert_eq!(limiter.len(), 2);
+
+        // Expired windows are evicted and the key can be used again
+        let later = start + RatingRateLimiter::WINDOW;
+        assert!(limiter.check_at("ip:9.9.9.9".to_owned(), 2, later).is_ok());
+        assert_eq!(limiter.len(), 1);
+        assert!(limiter.check_at("ip:1.2.3.4".to_owned(), 2, later).is_ok());
+    }
+
+    #[test]
+    fn test_rate_limited_response() {
+        let response = RateLimited {
+            retry_after: Duration::from_millis(1500),
+        }
+        .into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "2");
+    }
+
+    #[test]
+    fn test_strip_star_rating() {
+        assert_eq!(
+            strip_star_rating("[5/5] Great federation"),
+            "Great federation"
+        );
+        assert_eq!(strip_star_rating("[3/5]"), "");
+        assert_eq!(strip_star_rating("[10/5]   spaces"), "spaces");
+        // Only a leading prefix is a rating
+        assert_eq!(strip_star_rating("Great [5/5]"), "Great [5/5]");
+        assert_eq!(strip_star_rating("[5/10] Great"), "[5/10] Great");
+        assert_eq!(strip_star_rating(""), "");
+    }
+
+    #[test]
+    fn test_latest_per_author() {
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let event = |keys: &Keys, created_at: u64, content: &str| {
+            EventBuilder::new(Kind::Metadata, content, [])
+                .custom_created_at(Timestamp::from(created_at))
+                .to_event(keys)
+                .unwrap()
+        };
+
+        let latest = latest_per_author(vec![
+            event(&alice, 200, "alice new"),
+            event(&alice, 100, "alice old"),
+            event(&bob, 100, "bob old"),
+            event(&bob, 300, "bob new"),
+        ]);
+        assert_eq!(latest.len(), 2);
+        assert_eq!(latest[&alice.public_key()].content, "alice new");
+        assert_eq!(latest[&bob.public_key()].content, "bob new");
+    }
+
+    #[test]
+    fn test_rating_age() {
+        let now = 1_700_000_000;
+        assert!(check_rating_age(now, now).is_ok());
+        assert!(check_rating_age(now - MAX_RATING_AGE.as_secs(), now).is_ok());
+        assert!(check_rating_age(now - MAX_RATING_AGE.as_secs() - 1, now).is_err());
+        assert!(check_rating_age(now + 10 * 60, now).is_ok());
+        assert!(check_rating_age(now + 10 * 60 + 1, now).is_err());
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let proxy: IpAddr = "127.0.0.1".parse().unwrap();
+        let client: IpAddr = "203.0.113.7".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            "198.51.100.1, 203.0.113.7".parse().unwrap(),
+        );
+
+        // Only trusted proxies may set the client address
+        assert_eq!(client_ip(proxy, &headers, &[]), proxy);
+        assert_eq!(client_ip(client, &headers, &[proxy]), client);
+
+        // The leftmost entries can be spoofed by the client
+        assert_eq!(client_ip(proxy, &headers, &[proxy]), client);
+        assert_eq!(client_ip(proxy, &HeaderMap::new(), &[proxy]), proxy);
+    }
+
+    #[test]
+    fn test_parse_rating_address() {
+        let author = [1u8; 32];
+        let federation_id = "ab".repeat(32);
+        let address =
+            |kind: &str, author: &[u8]| format!("{kind}:{}:{federation_id}", hex::encode(author));
+
+        assert_eq!(
+            parse_rating_address(&address("38000", &author), &author),
+            Some(federation_id.parse::<FederationId>().unwrap())
+        );
+        // Someone else's rating can't be deleted
+        assert_eq!(
+            parse_rating_address(&address("38000", &[2u8; 32]), &author),
+            None
+        );
+        assert_eq!(
+            parse_rating_address(&address("30078", &author), &author),
+            None
+        );
+        assert_eq!(
+            parse_rating_address(&format!("38000:{}:invalid", hex::encode(author)), &author),
+            None
+        );
+        assert_eq!(parse_rating_address("38000", &author), None);
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use anyhow::bail;
+use axum::extract::State;
+use axum::Json;
+use chrono::NaiveDateTime;
+use fedimint_core::config::FederationId;
+use fedimint_core::encoding::Encodable;
+use fedimint_core::task::sleep;
+use fedimint_core::PeerId;
+use fmo_api_types::{AlertState, FederationEvent};
+use nostr_sdk::{Event, EventBuilder, Keys, Kind, RelayPool, Tag, ToBech32};
+use postgres_from_row::FromRow;
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::interval;
+use tracing::{debug, info, warn};
+
+use crate::federation::observer::FederationObserver;
+use crate::util::query;
+use crate::AppState;
+
+/// Application-specific data (NIP-78), parameterized replaceable so relays only
+/// keep the latest attestation of each topic and federation
+const ATTESTATION_KIND: Kind = Kind::Custom(30078);
+
+const ATTESTATION_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, Copy)]
+enum AttestationTopic {
+    Session,
+    Deposits,
+    Health,
+    Alerts,
+}
+
+impl AttestationTopic {
+    const ALL: [AttestationTopic; 4] = [
+        AttestationTopic::Session,
+        AttestationTopic::Deposits,
+        AttestationTopic::Health,
+        AttestationTopic::Alerts,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            AttestationTopic::Session => "session",
+            AttestationTopic::Deposits => "deposits",
+            AttestationTopic::Health => "health",
+            AttestationTopic::Alerts => "alerts",
+        }
+    }
+}
+
+/// Public key the observer signs its Nostr events with, `None` if publishing
+/// is disabled
+pub(super) async fn get_nostr_public_key(
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<Option<String>>> {
+    Ok(state
+        .federation_observer
+        .nostr_public_key()
+        .map(|public_key| public_key.to_bech32())
+        .transpose()?
+        .into())
+}
+
+impl FederationObserver {
+    /// Periodically publishes signed attestations of the observed data per
+    /// federation, alerts are additionally published as soon as they change
+    pub(super) async fn publish_nostr_attestations(self, keys: Keys) {
+        const SLEEP_SECS: u64 = 60;
+        loop {
+            let e = self
+                .publish_nostr_attestations_inner(&keys)
+                .await
+                .expect_err("Not expected to exit");
+            warn!("Error while publishing nostr attestations: {e:?}");
+            sleep(Duration::from_secs(SLEEP_SECS)).await;
+        }
+    }
+
+    async fn publish_nostr_attestations_inner(&self, keys: &Keys) -> anyhow::Result<()> {
+        let client = self.nostr_relay_client().await?;
+        let mut interval = interval(ATTESTATION_INTERVAL);
+        let mut events = self.subscribe_events();
+
+        info!(
+            "Publishing nostr attestations as {}",
+            keys.public_key().to_bech32()?
+        );
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    self.update_relay_pool(&client).await?;
+                    for federation in self.list_federations().await? {
+                        for topic in AttestationTopic::ALL {
+                            self.publish_attestation(&client, keys, federation.federation_id, topic)
+                                .await;
+                        }
+                    }
+                }
+                event = events.recv() => match event {
+                    Ok(FederationEvent::Alert { federation_id, .. }) => {
+                        let topic = AttestationTopic::Alerts;
+                        self.publish_attestation(&client, keys, federation_id, topic)
+                            .await;
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => bail!("Event channel closed"),
+                }
+            }
+        }
+    }
+
+    /// Relays being unreachable isn't fatal, so errors are only logged
+    async fn publish_attestation(
+        &self,
+        client: &RelayPool,
+        keys: &Keys,
+        federation_id: FederationId,
+        topic: AttestationTopic,
+    ) {
+        if let Err(e) = self
+            .publish_attestation_inner(client, keys, federation_id, topic)
+            .await
+        {
+            warn!(
+                "Failed to publish {} attestation of {federation_id}: {e:?}",
+                topic.name()
+            );
+        }
+    }
+
+    async fn publish_attestation_inner(
+        &self,
+        client: &RelayPool,
+        keys: &Keys,
+        federation_id: FederationId,
+        topic: AttestationTopic,
+    ) -> anyhow::Result<()> {
+        let content = match topic {
+            AttestationTopic::Session => session_attestation(
+                federation_id,
+                self.federation_session_count(federation_id).await?,
+            ),
+            AttestationTopic::Deposits => json!({
+                "federation_id": federation_id,
+                "deposits_msat": self.get_federation_assets(federation_id).await?.msats,
+            }),
+            AttestationTopic::Health => json!({
+                "federation_id": federation_id,
+                "guardians": self.guardian_health_summary(federation_id).await?,
+            }),
+            AttestationTopic::Alerts => json!({
+                "federation_id": federation_id,
+                "firing": self
+                    .list_alerts(Some(AlertState::Firing), Some(federation_id))
+                    .await?,
+            }),
+        };
+
+        let event = attestation_event(keys, federation_id, topic, &content)?;
+        self.send_nostr_event(client, event).await?;
+
+        debug!("Published {} attestation of {federation_id}", topic.name());
+
+        Ok(())
+    }
+
+    /// Latest health check of each guardian within the last hour
+    async fn guardian_health_summary(
+        &self,
+        federation_id: FederationId,
+    ) -> anyhow::Result<BTreeMap<PeerId, serde_json::Value>> {
+        #[derive(Debug, FromRow)]
+        struct HealthRow {
+            guardian_id: i32,
+            time: NaiveDateTime,
+            online: bool,
+            block_height: Option<i32>,
+            latency_ms: Option<i32>,
+        }
+
+        let checks = query::<HealthRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            SELECT DISTINCT ON (guardian_id) guardian_id,
+                                             time,
+                                             status IS NOT NULL AS online,
+                                             block_height,
+                                             latency_ms
+            FROM guardian_health
+            WHERE federation_id = $1
+              AND time >= $2
+            ORDER BY guardian_id, time DESC
+            ",
+            &[
+                &federation_id.consensus_encode_to_vec(),
+                &(chrono::Utc::now().naive_utc() - chrono::Duration::hours(1)),
+            ],
+        )
+        .await?;
+
+        Ok(checks
+            .into_iter()
+            .map(|check| {
+                (
+                    PeerId::from(check.guardian_id as u16),
+                    json!({
+                        "online": check.online,
+                        "block_height": check.block_height,
+                        "latency_ms": check.latency_ms,
+                        "checked_at": check.time,
+                    }),
+                )
+            })
+            .collect())
+    }
+}
+
+fn session_attestation(federation_id: FederationId, session_count: u64) -> serde_json::Value {
+    json!({
+        "federation_id": federation_id,
+        "session_count": session_count,
+        "latest_session_index": session_count.checked_sub(1),
+    })
+}
+
+fn attestation_event(
+    keys: &Keys,
+    federation_id: FederationId,
+    topic: AttestationTopic,
+    content: &serde_json::Value,
+) -> anyhow::Result<Event> {
+    let federation_id_str = federation_id.to_string();
+    Ok(EventBuilder::new(
+        ATTESTATION_KIND,
+        content.to_string(),
+        [
+            Tag::identifier(format!("fmo:{}:{federation_id_str}", topic.name())),
+            // Single letter tags are indexed by relays, so clients can query all
+            // attestations of a federation
+            Tag::parse(&["f", federation_id_str.as_str()])?,
+            Tag::hashtag("fedimint"),
+        ],
+    )
+    .to_event(keys)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use fedimint_core::config::FederationId;
+    use nostr_sdk::Keys;
+    use serde_json::json;
+
+    use super::{attestation_event, session_attestation, AttestationTopic, ATTESTATION_KIND};
+
+    #[test]
+    fn test_session_attestation() {
+        let federation_id = FederationId::dummy();
+        assert_eq!(
+            session_attestation(federation_id, 10),
+            json!({
+                "federation_id": federation_id,
+                "session_count": 10,
+                "latest_session_index": 9,
+            })
+        );
+        assert_eq!(
+            session_attestation(federation_id, 0)["latest_session_index"],
+            json!(null)
+        );
+    }
+
+    #[test]
+    fn test_attestation_event() {
+        let keys = Keys::generate();
+        let federation_id = FederationId::dummy();
+        let content = session_attestation(federation_id, 10);
+
+        let event =
+            attestation_event(&keys, federation_id, AttestationTopic::Session, &content).unwrap();
+        event.verify().unwrap();
+        assert_eq!(event.kind, ATTESTATION_KIND);
+        assert_eq!(event.pubkey, keys.public_key());
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&event.content).unwrap(),
+            content
+        );
+
+        let tags = event
+            .tags
+            .iter()
+            .map(|tag| tag.as_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tags,
+            vec![
+                vec!["d".to_owned(), format!("fmo:session:{federation_id}")],
+                vec!["f".to_owned(), federation_id.to_string()],
+                vec!["t".to_owned(), "fedimint".to_owned()],
+            ]
+        );
+    }
+}
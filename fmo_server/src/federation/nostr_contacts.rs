@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+use nostr_sdk::{Filter, Kind, RelayPool, SingleLetterTag};
+use tracing::debug;
+
+use crate::federation::nostr::{latest_per_author, timestamp_to_naive};
+use crate::federation::observer::FederationObserver;
+use crate::util::execute;
+
+impl FederationObserver {
+    /// Fetches the NIP-02 contact lists of the trusted pubkeys, authors they
+    /// follow are considered trusted when weighting ratings
+    pub(super) async fn sync_trusted_contacts(&self, client: &RelayPool) -> anyhow::Result<()> {
+        if self.trusted_pubkeys.is_empty() {
+            return Ok(());
+        }
+
+        let events = self
+            .fetch_all_events(
+                client,
+                Filter {
+                    ids: None,
+                    authors: Some(self.trusted_pubkeys.iter().copied().collect()),
+                    kinds: Some(vec![Kind::ContactList].into_iter().collect()),
+                    search: None,
+                    since: None,
+                    until: None,
+                    limit: None,
+                    generic_tags: HashMap::new(),
+                },
+                None,
+            )
+            .await?;
+
+        let contact_tag = SingleLetterTag::from_char('p').expect("Tag is valid");
+        let connection = self.connection().await?;
+        let now = chrono::Utc::now().naive_utc();
+        for (author, contact_list) in latest_per_author(events) {
+            let contacts = contact_list
+                .tags()
+                .iter()
+                .filter(|tag| tag.single_letter_tag() == Some(contact_tag))
+                .filter_map(|tag| hex::decode(tag.as_vec().get(1)?).ok())
+                .filter(|contact| contact.len() == 32)
+                .collect::<Vec<_>>();
+
+            debug!("{author} follows {} pubkeys", contacts.len());
+
+            execute(
+                &connection,
+                // language=postgresql
+                "
+                INSERT INTO nostr_contacts (author, contacts, created_at, fetched_at)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (author) DO UPDATE SET contacts   = excluded.contacts,
+                                                   created_at = excluded.created_at,
+                                                   fetched_at = excluded.fetched_at
+                WHERE excluded.created_at >= nostr_contacts.created_at
+                ",
+                &[
+                    &author.to_bytes().to_vec(),
+                    &contacts,
+                    &timestamp_to_naive(contact_list.created_at)?,
+                    &now,
+                ],
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+}
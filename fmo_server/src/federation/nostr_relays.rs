@@ -0,0 +1,326 @@
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use anyhow::{ensure, Context};
+use axum::extract::{Query, State};
+use axum::Json;
+use chrono::NaiveDateTime;
+use fmo_api_types::{NewNostrRelay, NostrRelay};
+use nostr_sdk::{Event, RelayOptions, RelayPool, RelayPoolOptions, RelaySendOptions, Url};
+use postgres_from_row::FromRow;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::federation::observer::FederationObserver;
+use crate::util::{execute, query, query_one};
+use crate::AppState;
+
+#[derive(Debug, FromRow)]
+struct NostrRelayRow {
+    relay_url: String,
+    added_at: NaiveDateTime,
+    connected: bool,
+    events_fetched: i64,
+    publish_attempts: i64,
+    publish_successes: i64,
+    last_error: Option<String>,
+    last_error_at: Option<NaiveDateTime>,
+}
+
+impl From<NostrRelayRow> for NostrRelay {
+    fn from(row: NostrRelayRow) -> Self {
+        NostrRelay {
+            relay_url: row.relay_url,
+            added_at: row.added_at,
+            connected: row.connected,
+            events_fetched: row.events_fetched as u64,
+            publish_attempts: row.publish_attempts as u64,
+            publish_successes: row.publish_successes as u64,
+            publish_success_rate: (row.publish_attempts > 0)
+                .then(|| row.publish_successes as f64 / row.publish_attempts as f64),
+            last_error: row.last_error,
+            last_error_at: row.last_error_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct RelayParams {
+    relay_url: String,
+}
+
+pub(super) async fn list_nostr_relays(
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<Vec<NostrRelay>>> {
+    Ok(state.federation_observer.list_nostr_relays().await?.into())
+}
+
+pub(super) async fn add_nostr_relay(
+    State(state): State<AppState>,
+    Json(relay): Json<NewNostrRelay>,
+) -> crate::error::Result<Json<NostrRelay>> {
+    Ok(state
+        .federation_observer
+        .add_nostr_relay(&relay.relay_url)
+        .await?
+        .into())
+}
+
+pub(super) async fn remove_nostr_relay(
+    Query(params): Query<RelayParams>,
+    State(state): State<AppState>,
+) -> crate::error::Result<()> {
+    Ok(state
+        .federation_observer
+        .remove_nostr_relay(&params.relay_url)
+        .await?)
+}
+
+impl FederationObserver {
+    pub async fn list_nostr_relays(&self) -> anyhow::Result<Vec<NostrRelay>> {
+        Ok(query::<NostrRelayRow>(
+            &self.connection().await?,
+            "SELECT relay_url, added_at, connected, events_fetched, publish_attempts, publish_successes, last_error, last_error_at FROM nostr_relays ORDER BY relay_url",
+            &[],
+        )
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect())
+    }
+
+    pub async fn add_nostr_relay(&self, relay_url: &str) -> anyhow::Result<NostrRelay> {
+        let relay_url = parse_relay_url(relay_url)?;
+
+        let relay = query_one::<NostrRelayRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            INSERT INTO nostr_relays (relay_url, added_at)
+            VALUES ($1, $2)
+            ON CONFLICT (relay_url) DO UPDATE SET relay_url = excluded.relay_url
+            RETURNING relay_url, added_at, connected, events_fetched, publish_attempts, publish_successes, last_error, last_error_at
+            ",
+            &[&relay_url.to_string(), &chrono::Utc::now().naive_utc()],
+        )
+        .await?;
+
+        info!("Added nostr relay {relay_url}");
+
+        Ok(relay.into())
+    }
+
+    pub async fn remove_nostr_relay(&self, relay_url: &str) -> anyhow::Result<()> {
+        let relay_url = parse_relay_url(relay_url)?;
+
+        let deleted = execute(
+            &self.connection().await?,
+            "DELETE FROM nostr_relays WHERE relay_url = $1",
+            &[&relay_url.to_string()],
+        )
+        .await?;
+        ensure!(deleted == 1, "Relay doesn't exist");
+
+        info!("Removed nostr relay {relay_url}");
+
+        Ok(())
+    }
+
+    pub(super) async fn nostr_relay_client(&self) -> anyhow::Result<RelayPool> {
+        let client = RelayPool::new(RelayPoolOptions::default());
+        self.update_relay_pool(&client).await?;
+
+        let relays = client.relays().await.into_keys().collect::<Vec<_>>();
+        info!(?relays, "Started Nostr client");
+
+        Ok(client)
+    }
+
+    /// Adds relays to and removes them from `client` so it matches the relays
+    /// in the DB, allowing long-running clients to pick up changes
+    pub(super) async fn update_relay_pool(&self, client: &RelayPool) -> anyhow::Result<()> {
+        let relays = query::<NostrRelayRow>(
+            &self.connection().await?,
+            "SELECT relay_url, added_at, connected, events_fetched, publish_attempts, publish_successes, last_error, last_error_at FROM nostr_relays",
+            &[],
+        )
+        .await?
+        .into_iter()
+        .filter_map(|relay| match Url::parse(&relay.relay_url) {
+            Ok(url) => Some(url),
+            Err(e) => {
+                warn!("Ignoring invalid relay URL {}: {e:?}", relay.relay_url);
+                None
+            }
+        })
+        .collect::<BTreeSet<_>>();
+
+        let current_relays = client.relays().await.into_keys().collect::<BTreeSet<_>>();
+
+        for removed_relay in current_relays.difference(&relays) {
+            client.remove_relay(removed_relay.clone()).await?;
+        }
+        for added_relay in relays.difference(&current_relays) {
+            client
+                .add_relay(added_relay.clone(), RelayOptions::default())
+                .await?;
+        }
+        // Only connects relays that aren't connected yet
+        client.connect(Some(Duration::from_secs(5))).await;
+
+        Ok(())
+    }
+
+    /// Saves which relays of `client` are currently connected
+    pub(super) async fn record_relay_status(&self, client: &RelayPool) -> anyhow::Result<()> {
+        let connection = self.connection().await?;
+        for (url, relay) in client.relays().await {
+            execute(
+                &connection,
+                "UPDATE nostr_relays SET connected = $2 WHERE relay_url = $1",
+                &[&url.to_string(), &relay.is_connected().await],
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    pub(super) async fn record_relay_events_fetched(
+        &self,
+        relay_url: &Url,
+        events: usize,
+    ) -> anyhow::Result<()> {
+        execute(
+            &self.connection().await?,
+            "UPDATE nostr_relays SET events_fetched = events_fetched + $2 WHERE relay_url = $1",
+            &[&relay_url.to_string(), &(events as i64)],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    pub(super) async fn record_relay_error(
+        &self,
+        relay_url: &Url,
+        error: &str,
+    ) -> anyhow::Result<()> {
+        execute(
+            &self.connection().await?,
+            "UPDATE nostr_relays SET last_error = $2, last_error_at = $3 WHERE relay_url = $1",
+            &[
+                &relay_url.to_string(),
+                &error,
+                &chrono::Utc::now().naive_utc(),
+            ],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Sends an event to all relays of `client` and records per relay whether
+    /// publishing succeeded
+    pub(super) async fn send_nostr_event(
+        &self,
+        client: &RelayPool,
+        event: Event,
+    ) -> anyhow::Result<()> {
+        let output = client
+            .send_event(
+                event,
+                RelaySendOptions::default().timeout(Some(Duration::from_secs(5))),
+            )
+            .await?;
+
+        let connection = self.connection().await?;
+        for relay_url in &output.success {
+            execute(
+                &connection,
+                "UPDATE nostr_relays SET publish_attempts = publish_attempts + 1, publish_successes = publish_successes + 1 WHERE relay_url = $1",
+                &[&relay_url.to_string()],
+            )
+            .await?;
+        }
+        for (relay_url, error) in &output.failed {
+            execute(
+                &connection,
+                "UPDATE nostr_relays SET publish_attempts = publish_attempts + 1 WHERE relay_url = $1",
+                &[&relay_url.to_string()],
+            )
+            .await?;
+            self.record_relay_error(
+                relay_url,
+                error.as_deref().unwrap_or("Failed to publish event"),
+            )
+            .await?;
+        }
+
+        ensure!(
+            !output.success.is_empty(),
+            "Event wasn't accepted by any relay"
+        );
+
+        Ok(())
+    }
+}
+
+/// Parses and normalizes a relay URL, so it can be compared to the ones in the
+/// DB
+fn parse_relay_url(relay_url: &str) -> anyhow::Result<Url> {
+    let relay_url = Url::parse(relay_url).context("Invalid relay URL")?;
+    ensure!(
+        matches!(relay_url.scheme(), "ws" | "wss"),
+        "Relay URL has to use ws:// or wss://"
+    );
+    Ok(relay_url)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+    use fmo_api_types::NostrRelay;
+
+    use super::{parse_relay_url, NostrRelayRow};
+
+    #[test]
+    fn test_parse_relay_url() {
+        // Normalized the same way as the URLs stored by the v14 migration
+        assert_eq!(
+            parse_relay_url("wss://relay.example.com").unwrap().as_str(),
+            "wss://relay.example.com/"
+        );
+        assert_eq!(
+            parse_relay_url("ws://relay.example.com/nostr")
+                .unwrap()
+                .as_str(),
+            "ws://relay.example.com/nostr"
+        );
+        assert!(parse_relay_url("https://relay.example.com").is_err());
+        assert!(parse_relay_url("relay.example.com").is_err());
+    }
+
+    #[test]
+    fn test_relay_stats() {
+        let row = |publish_attempts, publish_successes| NostrRelayRow {
+            relay_url: "wss://relay.example.com/".to_owned(),
+            added_at: NaiveDateTime::default(),
+            connected: true,
+            events_fetched: 42,
+            publish_attempts,
+            publish_successes,
+            last_error: None,
+            last_error_at: None,
+        };
+
+        let relay = NostrRelay::from(row(4, 3));
+        assert_eq!(relay.events_fetched, 42);
+        assert_eq!(relay.publish_attempts, 4);
+        assert_eq!(relay.publish_successes, 3);
+        assert_eq!(relay.publish_success_rate, Some(0.75));
+
+        // No rate before anything was published
+        assert_eq!(NostrRelay::from(row(0, 0)).publish_success_rate, None);
+    }
+}
@@ -1,8 +1,11 @@
+use std::collections::BTreeMap;
+use std::net::IpAddr;
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime};
 
-use anyhow::ensure;
-use bitcoin::hashes::Hash;
+use bitcoin::hashes::cmp::fixed_time_eq;
+use bitcoin::hashes::{sha256, Hash};
 use bitcoin::{Address, OutPoint, Txid};
 use chrono::{DateTime, NaiveDate};
 use deadpool_postgres::{GenericClient, Runtime, Transaction};
@@ -11,6 +14,7 @@ use fedimint_core::config::{ClientConfig, FederationId};
 use fedimint_core::core::DynModuleConsensusItem;
 use fedimint_core::encoding::Encodable;
 use fedimint_core::epoch::ConsensusItem;
+use fedimint_core::module::registry::ModuleDecoderRegistry;
 use fedimint_core::session_outcome::SessionOutcome;
 use fedimint_core::task::TaskGroup;
 use fedimint_core::util::{retry, ConstantBackoff, FibonacciBackoff};
@@ -20,42 +24,75 @@ use fedimint_ln_common::contracts::{Contract, IdentifiableContract};
 use fedimint_ln_common::{LightningInput, LightningOutput, LightningOutputV0};
 use fedimint_mint_common::{MintInput, MintOutput};
 use fedimint_wallet_common::{WalletConsensusItem, WalletInput, WalletOutput, WalletOutputV0};
-use fmo_api_types::{FederationActivity, FederationSummary, FederationUtxo, FedimintTotals};
+use fmo_api_types::{
+    FederationActivity, FederationEvent, FederationSummary, FederationUtxo, FedimintTotals,
+};
 use futures::future::join_all;
+use futures::stream::BoxStream;
 use futures::StreamExt;
 use postgres_from_row::FromRow;
+use tokio::sync::broadcast;
+use tokio::task::JoinError;
 use tokio::time::sleep;
-use tokio_postgres::NoTls;
+use tokio_postgres::{NoTls, Row};
 use tracing::log::info;
 use tracing::{debug, error, warn};
 
 use crate::federation::db::Federation;
+use crate::federation::nostr::RatingRateLimiter;
 use crate::federation::{db, decoders_from_config, instance_to_kind};
 use crate::util::{execute, query, query_one, query_opt, query_value};
 
+/// Number of events slow subscribers may lag behind before missing some
+const EVENT_BUFFER_SIZE: usize = 1024;
+
+/// Time without a new session after which an observer is considered stalled
+const STALL_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
 #[derive(Debug, Clone)]
 pub struct FederationObserver {
     connection_pool: deadpool_postgres::Pool,
     admin_auth: String,
-    task_group: TaskGroup,
+    pub(super) task_group: TaskGroup,
+    /// Tasks observing a single federation, so they can be stopped when the
+    /// federation is removed
+    federation_task_groups: Arc<Mutex<BTreeMap<FederationId, TaskGroup>>>,
+    /// Live events of all observed federations, see [`FederationEvent`]
+    events: broadcast::Sender<FederationEvent>,
+    /// Key used to sign the observer's own Nostr events, publishing is
+    /// disabled if `None`
+    nostr_keys: Option<nostr_sdk::Keys>,
+    /// Minimum NIP-13 proof of work of submitted ratings, disabled if `0`
+    pub(super) rating_min_pow: u8,
+    pub(super) rating_rate_limiter: Arc<RatingRateLimiter>,
+    /// Start observing mainnet federations announced on Nostr without review
+    pub(super) auto_add_discovered_federations: bool,
+    /// Ratings by these pubkeys and the ones they follow are weighted fully
+    pub(super) trusted_pubkeys: Vec<nostr_sdk::PublicKey>,
+    /// Reverse proxies whose `X-Forwarded-For` header is used to rate limit
+    /// rating submissions per client
+    pub(super) trusted_proxies: Vec<IpAddr>,
 }
 
 impl FederationObserver {
-    pub async fn new(database: &str, admin_auth: &str) -> anyhow::Result<FederationObserver> {
-        let connection_pool = {
-            let mut pool_config = deadpool_postgres::Config::default();
-            pool_config.url = Some(database.to_owned());
-            pool_config.create_pool(Some(Runtime::Tokio1), NoTls)
-        }?;
-
+    pub async fn new(
+        database: &str,
+        admin_auth: &str,
+        nostr_keys: Option<nostr_sdk::Keys>,
+        rating_min_pow: u8,
+        auto_add_discovered_federations: bool,
+        trusted_pubkeys: Vec<nostr_sdk::PublicKey>,
+        trusted_proxies: Vec<IpAddr>,
+    ) -> anyhow::Result<FederationObserver> {
         let slf = FederationObserver {
-            connection_pool,
-            admin_auth: admin_auth.to_owned(),
-            task_group: Default::default(),
+            nostr_keys,
+            rating_min_pow,
+            auto_add_discovered_federations,
+            trusted_pubkeys,
+            trusted_proxies,
+            ..Self::connect(database, admin_auth).await?
         };
 
-        slf.setup_schema().await?;
-
         for federation in slf.list_federations().await? {
             slf.spawn_observer(federation).await;
         }
@@ -64,15 +101,71 @@ impl FederationObserver {
             .spawn_cancellable("fetch block times", Self::fetch_block_times(slf.clone()));
         slf.task_group
             .spawn_cancellable("sync nostr events", Self::sync_nostr_events(slf.clone()));
+        slf.task_group.spawn_cancellable(
+            "fetch withdrawal details",
+            Self::fetch_withdrawal_details(slf.clone()),
+        );
+        slf.task_group.spawn_cancellable(
+            "watch federation configs",
+            Self::watch_federation_configs(slf.clone()),
+        );
+        slf.task_group
+            .spawn_cancellable("deliver webhooks", Self::deliver_webhooks(slf.clone()));
+        if let Some(nostr_keys) = slf.nostr_keys.clone() {
+            slf.task_group.spawn_cancellable(
+                "publish nostr attestations",
+                Self::publish_nostr_attestations(slf.clone(), nostr_keys),
+            );
+        }
+
+        for federation_id in slf.unfinished_reindex_jobs().await? {
+            slf.spawn_reindex(federation_id);
+        }
+
+        Ok(slf)
+    }
+
+    /// Connects to and migrates the database without starting any background
+    /// tasks, used by CLI commands that operate on the database directly
+    pub async fn connect(database: &str, admin_auth: &str) -> anyhow::Result<FederationObserver> {
+        let connection_pool = {
+            let pool_config = deadpool_postgres::Config {
+                url: Some(database.to_owned()),
+                ..Default::default()
+            };
+            pool_config.create_pool(Some(Runtime::Tokio1), NoTls)
+        }?;
+
+        let slf = FederationObserver {
+            connection_pool,
+            admin_auth: admin_auth.to_owned(),
+            task_group: Default::default(),
+            federation_task_groups: Default::default(),
+            events: broadcast::channel(EVENT_BUFFER_SIZE).0,
+            nostr_keys: None,
+            rating_min_pow: 0,
+            rating_rate_limiter: Default::default(),
+            auto_add_discovered_federations: false,
+            trusted_pubkeys: vec![],
+            trusted_proxies: vec![],
+        };
+
+        slf.setup_schema().await?;
 
         Ok(slf)
     }
 
     async fn spawn_observer(&self, federation: Federation) {
+        let task_group = TaskGroup::new();
+        self.federation_task_groups
+            .lock()
+            .expect("Lock poisoned")
+            .insert(federation.federation_id, task_group.clone());
+
         let slf = self.clone();
 
         let federation_inner = federation.clone();
-        self.task_group.spawn_cancellable(
+        task_group.spawn_cancellable(
             format!("Observer for {}", federation_inner.federation_id),
             async move {
                 loop {
@@ -90,7 +183,7 @@ impl FederationObserver {
         );
 
         let slf = self.clone();
-        self.task_group.spawn_cancellable(
+        task_group.spawn_cancellable(
             format!("Health Monitor for {}", federation.federation_id),
             async move {
                 loop {
@@ -105,6 +198,24 @@ impl FederationObserver {
         );
     }
 
+    /// Stops all tasks observing the federation, returns `false` if there were
+    /// none
+    pub(super) fn stop_observer(&self, federation_id: FederationId) -> bool {
+        let task_group = self
+            .federation_task_groups
+            .lock()
+            .expect("Lock poisoned")
+            .remove(&federation_id);
+
+        match task_group {
+            Some(task_group) => {
+                task_group.shutdown();
+                true
+            }
+            None => false,
+        }
+    }
+
     async fn setup_schema(&self) -> anyhow::Result<()> {
         execute(
             &self.connection().await?,
@@ -168,16 +279,83 @@ impl FederationObserver {
                 5,
                 include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/schema/v5.sql")),
             ),
+            (
+                6,
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/schema/v6.sql")),
+            ),
+            (
+                7,
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/schema/v7.sql")),
+            ),
+            (
+                8,
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/schema/v8.sql")),
+            ),
+            (
+                9,
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/schema/v9.sql")),
+            ),
+            (
+                10,
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/schema/v10.sql")),
+            ),
+            (
+                11,
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/schema/v11.sql")),
+            ),
+            (
+                12,
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/schema/v12.sql")),
+            ),
+            (
+                13,
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/schema/v13.sql")),
+            ),
+            (
+                14,
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/schema/v14.sql")),
+            ),
+            (
+                15,
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/schema/v15.sql")),
+            ),
+            (
+                16,
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/schema/v16.sql")),
+            ),
+            (
+                17,
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/schema/v17.sql")),
+            ),
+            (
+                18,
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/schema/v18.sql")),
+            ),
+            (
+                19,
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/schema/v19.sql")),
+            ),
         ];
 
-        for (version, migration) in migration_map.iter() {
-            if *version > schema_version {
-                let mut conn = self.connection().await?;
-                let transaction = conn.transaction().await?;
+        let pending_migrations = migration_map
+            .iter()
+            .filter(|(version, _)| *version > schema_version)
+            .collect::<Vec<_>>();
+
+        if !pending_migrations.is_empty() {
+            let mut conn = self.connection().await?;
+            let transaction = conn.transaction().await?;
+            for (_, migration) in &pending_migrations {
                 transaction.batch_execute(migration).await?;
-                self.handle_backfill(*version, &transaction).await?;
-                transaction.commit().await?;
             }
+            // Backfills re-process stored sessions, which writes to tables of later
+            // migrations, so they can only run once all of them were applied
+            let applied_versions = pending_migrations
+                .iter()
+                .map(|(version, _)| *version)
+                .collect::<Vec<_>>();
+            self.run_backfills(&applied_versions, &transaction).await?;
+            transaction.commit().await?;
         }
 
         if query_value::<i64>(
@@ -201,79 +379,187 @@ impl FederationObserver {
         Ok(())
     }
 
-    async fn backfill_v2_migration_wallet_data(
+    /// Runs the backfills of all `applied_versions`, parsing every stored
+    /// session at most once
+    async fn run_backfills(
         &self,
+        applied_versions: &[i32],
         dbtx: &Transaction<'_>,
     ) -> anyhow::Result<()> {
-        info!("Beginning backfill for v2 wallet migration data, this may take a long time");
+        // Re-processing whole sessions also fills the tables of the v8 and v9
+        // backfills
+        let wallet_data = applied_versions.contains(&2);
+        let feerate_votes = !wallet_data && applied_versions.contains(&8);
+        let session_peer_items = !wallet_data && applied_versions.contains(&9);
+
+        // Queried in the migration transaction, the table might have just been
+        // created by it
+        let federations = query::<Federation>(dbtx, "SELECT * FROM federations", &[]).await?;
+
+        if wallet_data || feerate_votes || session_peer_items {
+            info!("Beginning backfill of session data, this may take a long time");
+
+            for fed in &federations {
+                let mut parsing_stream = Self::parse_session_outcomes(fed, dbtx).await?;
+                while let Some(outcome) = parsing_stream.next().await.transpose()? {
+                    let session_index = outcome.session_index as u64;
+
+                    if wallet_data {
+                        self.process_session(
+                            fed.federation_id,
+                            fed.config.clone(),
+                            session_index,
+                            outcome.data,
+                            dbtx,
+                        )
+                        .await?;
+                        continue;
+                    }
 
-        let num_cpus = std::thread::available_parallelism()
-            .map(|non_zero_cpus| non_zero_cpus.get())
-            .unwrap_or(12);
+                    if session_peer_items {
+                        Self::insert_session_peer_items(
+                            dbtx,
+                            fed.federation_id,
+                            session_index,
+                            &outcome.data,
+                        )
+                        .await?;
+                    }
 
-        for fed in self.list_federations().await? {
-            info!(
-                "Parsing all session outcomes for fed: {}",
-                fed.federation_id
-            );
-            let decoders = decoders_from_config(&fed.config);
-            let session_outcome_rows = dbtx
-                .query(
-                    "SELECT * FROM sessions WHERE federation_id = $1",
-                    &[&fed.federation_id.consensus_encode_to_vec()],
-                )
-                .await?;
+                    if feerate_votes {
+                        Self::insert_session_feerate_votes(dbtx, fed, session_index, outcome.data)
+                            .await?;
+                    }
+                }
+            }
+        }
 
-            let rows_count = session_outcome_rows.len();
-
-            // take advantage of all cores, otherwise backfilling can take a long time
-            let mut parsing_stream =
-                futures::stream::iter(session_outcome_rows.into_iter().enumerate())
-                    .map(|(row_idx, row)| {
-                        let decoders_clone = decoders.clone();
-                        tokio::task::spawn(async move {
-                            if row_idx % 1000 == 0 {
-                                let percentage = (row_idx as f64) / (rows_count as f64);
-                                let percentage_str = format!("{:.2}%", percentage * 100.0);
-                                info!(
-                                    "parsing session index: {:?}/{:?} ({})",
-                                    row_idx, rows_count, percentage_str
-                                );
-                            }
-                            db::SessionOutcome::from_row_with_decoders(
-                                &row,
-                                &decoders_clone.clone(),
-                            )
-                        })
-                    })
-                    .buffered(num_cpus)
-                    .boxed();
-
-            while let Some(outcome) = parsing_stream.next().await.transpose()? {
-                self.process_session(
+        // Reads the withdrawal signatures that re-processing sessions might have
+        // just inserted
+        if applied_versions.contains(&7) {
+            Self::backfill_v7_withdrawal_threshold_sessions(&federations, dbtx).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn insert_session_feerate_votes(
+        dbtx: &Transaction<'_>,
+        fed: &Federation,
+        session_index: u64,
+        session_outcome: SessionOutcome,
+    ) -> anyhow::Result<()> {
+        for (item_idx, item) in session_outcome.items.into_iter().enumerate() {
+            let ConsensusItem::Module(module_ci) = item.item else {
+                continue;
+            };
+
+            if instance_to_kind(&fed.config, module_ci.module_instance_id()) != "wallet" {
+                continue;
+            }
+
+            if let Some(WalletConsensusItem::Feerate(feerate)) =
+                module_ci.as_any().downcast_ref::<WalletConsensusItem>()
+            {
+                Self::insert_feerate_vote(
+                    dbtx,
                     fed.federation_id,
-                    fed.config.clone(),
-                    outcome.session_index as u64,
-                    outcome.data,
-                    &dbtx,
+                    session_index,
+                    item_idx as u64,
+                    item.peer,
+                    feerate.sats_per_kvb,
                 )
                 .await?;
             }
         }
+
         Ok(())
     }
 
-    async fn handle_backfill(&self, version: i32, dbtx: &Transaction<'_>) -> anyhow::Result<()> {
-        match version {
-            2 => Ok(self.backfill_v2_migration_wallet_data(dbtx).await?),
-            _ => Ok(()),
-        }
+    /// Parses all stored session outcomes of a federation in parallel, yielding
+    /// them in the order they were stored
+    async fn parse_session_outcomes(
+        fed: &Federation,
+        dbtx: &Transaction<'_>,
+    ) -> anyhow::Result<BoxStream<'static, Result<db::SessionOutcome, JoinError>>> {
+        info!(
+            "Parsing all session outcomes for fed: {}",
+            fed.federation_id
+        );
+
+        let session_outcome_rows = dbtx
+            .query(
+                "SELECT * FROM sessions WHERE federation_id = $1 ORDER BY session_index",
+                &[&fed.federation_id.consensus_encode_to_vec()],
+            )
+            .await?;
+
+        Ok(Self::parse_session_rows(
+            session_outcome_rows,
+            decoders_from_config(&fed.config),
+        ))
     }
 
-    pub(super) async fn connection(&self) -> anyhow::Result<deadpool_postgres::Object> {
+    /// Parses rows of the `sessions` table in parallel, yielding them in order
+    pub(super) fn parse_session_rows(
+        session_outcome_rows: Vec<Row>,
+        decoders: ModuleDecoderRegistry,
+    ) -> BoxStream<'static, Result<db::SessionOutcome, JoinError>> {
+        let num_cpus = std::thread::available_parallelism()
+            .map(|non_zero_cpus| non_zero_cpus.get())
+            .unwrap_or(12);
+
+        let rows_count = session_outcome_rows.len();
+
+        // take advantage of all cores, otherwise backfilling can take a long time
+        futures::stream::iter(session_outcome_rows.into_iter().enumerate())
+            .map(move |(row_idx, row)| {
+                let decoders_clone = decoders.clone();
+                tokio::task::spawn(async move {
+                    if row_idx % 1000 == 0 {
+                        let percentage = (row_idx as f64) / (rows_count as f64);
+                        let percentage_str = format!("{:.2}%", percentage * 100.0);
+                        info!(
+                            "parsing session index: {:?}/{:?} ({})",
+                            row_idx, rows_count, percentage_str
+                        );
+                    }
+                    db::SessionOutcome::from_row_with_decoders(&row, &decoders_clone.clone())
+                })
+            })
+            .buffered(num_cpus)
+            .boxed()
+    }
+
+    pub(crate) async fn connection(&self) -> anyhow::Result<deadpool_postgres::Object> {
         Ok(self.connection_pool.get().await?)
     }
 
+    pub fn subscribe_events(&self) -> broadcast::Receiver<FederationEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn nostr_public_key(&self) -> Option<nostr_sdk::PublicKey> {
+        self.nostr_keys.as_ref().map(nostr_sdk::Keys::public_key)
+    }
+
+    /// Enqueues webhook deliveries for an event that isn't tied to a DB
+    /// transaction and broadcasts it to live subscribers
+    pub(super) async fn publish_event(&self, event: FederationEvent) -> anyhow::Result<()> {
+        Self::enqueue_webhook_deliveries(&self.connection().await?, std::slice::from_ref(&event))
+            .await?;
+        self.broadcast_event(event);
+        Ok(())
+    }
+
+    /// Sends an event to live subscribers only, webhook deliveries have to be
+    /// enqueued beforehand with [`Self::enqueue_webhook_deliveries`] in the
+    /// transaction that produced the event
+    pub(super) fn broadcast_event(&self, event: FederationEvent) {
+        // Only fails if there are no subscribers, which is fine
+        let _ = self.events.send(event);
+    }
+
     pub async fn list_federations(&self) -> anyhow::Result<Vec<db::Federation>> {
         Ok(query(&self.connection().await?, "SELECT * FROM federations", &[]).await?)
     }
@@ -373,10 +659,24 @@ impl FederationObserver {
     }
 
     pub async fn add_federation(&self, invite: &InviteCode) -> anyhow::Result<FederationId> {
+        if let Some(federation) = self.download_federation(invite).await? {
+            self.spawn_observer(federation).await;
+        }
+
+        Ok(invite.federation_id())
+    }
+
+    /// Downloads and stores the config of a federation that isn't observed yet
+    /// without starting to observe it. Returns `None` if the federation is
+    /// already known.
+    pub async fn download_federation(
+        &self,
+        invite: &InviteCode,
+    ) -> anyhow::Result<Option<Federation>> {
         let federation_id = invite.federation_id();
 
         if self.get_federation(federation_id).await?.is_some() {
-            return Ok(federation_id);
+            return Ok(None);
         }
 
         let config = ClientConfig::download_from_invite_code(invite).await?;
@@ -392,19 +692,21 @@ impl FederationObserver {
             )
             .await?;
 
-        self.spawn_observer(Federation {
+        Ok(Some(Federation {
             federation_id,
             config,
-        })
-        .await;
-
-        Ok(federation_id)
+        }))
     }
 
-    // FIXME: use middleware for auth and get it out of here
-    pub fn check_auth(&self, bearer_token: &str) -> anyhow::Result<()> {
-        ensure!(self.admin_auth == bearer_token, "Invalid bearer token");
-        Ok(())
+    /// Whether `bearer_token` is the admin token from the server config, see
+    /// [`crate::auth`] for API keys
+    pub fn is_admin_token(&self, bearer_token: &str) -> bool {
+        // Comparing hashes in constant time doesn't leak how much of the token
+        // matched or its length
+        fixed_time_eq(
+            &sha256::Hash::hash(self.admin_auth.as_bytes())[..],
+            &sha256::Hash::hash(bearer_token.as_bytes())[..],
+        )
     }
 
     async fn fetch_block_times(self) {
@@ -520,9 +822,48 @@ impl FederationObserver {
             })
             .buffered(32);
 
+        // Sessions that were already finished when we started are history, so they
+        // don't produce live events
+        let first_live_session = match api.session_count().await {
+            Ok(session_count) => session_count,
+            Err(e) => {
+                warn!("Failed to fetch session count of {federation_id}: {e:?}");
+                next_session
+            }
+        };
+
         let mut timer = SystemTime::now();
         let mut last_session = next_session;
-        while let Some((session_index, signed_session_outcome)) = session_stream.next().await {
+        let mut expected_session = next_session;
+        let mut stalled = false;
+        loop {
+            let (session_index, signed_session_outcome) =
+                match tokio::time::timeout(STALL_TIMEOUT, session_stream.next()).await {
+                    Ok(Some(session)) => session,
+                    Ok(None) => break,
+                    Err(_) => {
+                        if !stalled {
+                            warn!("No new session of {federation_id} for {STALL_TIMEOUT:?}");
+                            self.publish_event(FederationEvent::ObserverStalled {
+                                federation_id,
+                                session_index: expected_session,
+                                stalled_secs: STALL_TIMEOUT.as_secs(),
+                            })
+                            .await?;
+                            stalled = true;
+                        }
+                        continue;
+                    }
+                };
+            stalled = false;
+            expected_session = session_index + 1;
+
+            // Sessions have to be processed in order, so new ones have to wait till a
+            // running reindex caught up with all stored ones
+            while self.reindex_in_progress(federation_id).await? {
+                sleep(Duration::from_secs(10)).await;
+            }
+
             let mut connection = self.connection().await?;
             let dbtx = connection.transaction().await?;
             self.process_session(
@@ -533,8 +874,18 @@ impl FederationObserver {
                 &dbtx,
             )
             .await?;
+            let events = if session_index >= first_live_session {
+                Self::session_events(&dbtx, federation_id, session_index).await?
+            } else {
+                vec![]
+            };
+            Self::enqueue_webhook_deliveries(&dbtx, &events).await?;
             dbtx.commit().await?;
 
+            for event in events {
+                self.broadcast_event(event);
+            }
+
             let elapsed = timer.elapsed().unwrap_or_default();
             if elapsed >= Duration::from_secs(5) {
                 let sessions_synced = session_index - last_session;
@@ -554,7 +905,7 @@ impl FederationObserver {
         unreachable!("Session stream should never end")
     }
 
-    async fn process_session(
+    pub(super) async fn process_session(
         &self,
         federation_id: FederationId,
         config: ClientConfig,
@@ -572,6 +923,14 @@ impl FederationObserver {
         )
         .await?;
 
+        Self::insert_session_peer_items(
+            dbtx,
+            federation_id,
+            session_index,
+            &signed_session_outcome,
+        )
+        .await?;
+
         for (item_idx, item) in signed_session_outcome.items.into_iter().enumerate() {
             match item.item {
                 ConsensusItem::Transaction(transaction) => {
@@ -607,7 +966,7 @@ impl FederationObserver {
         Ok(())
     }
 
-    async fn refresh_views(&self) -> anyhow::Result<()> {
+    pub(super) async fn refresh_views(&self) -> anyhow::Result<()> {
         info!("Refreshing views");
         self.connection()
             .await?
@@ -615,6 +974,8 @@ impl FederationObserver {
                 "
                 REFRESH MATERIALIZED VIEW CONCURRENTLY session_times;
                 REFRESH MATERIALIZED VIEW CONCURRENTLY utxos;
+                REFRESH MATERIALIZED VIEW CONCURRENTLY transaction_fees;
+                REFRESH MATERIALIZED VIEW CONCURRENTLY guardian_inactive_ranges;
                 ",
             )
             .await?;
@@ -624,6 +985,33 @@ impl FederationObserver {
         Ok(())
     }
 
+    async fn insert_session_peer_items(
+        dbtx: &Transaction<'_>,
+        federation_id: FederationId,
+        session_index: u64,
+        session_outcome: &SessionOutcome,
+    ) -> Result<(), tokio_postgres::Error> {
+        let mut peer_items = BTreeMap::<PeerId, i32>::new();
+        for item in &session_outcome.items {
+            *peer_items.entry(item.peer).or_default() += 1;
+        }
+
+        for (peer_id, items) in peer_items {
+            dbtx.execute(
+                "INSERT INTO session_peer_items VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING",
+                &[
+                    &federation_id.consensus_encode_to_vec(),
+                    &(session_index as i32),
+                    &(peer_id.to_usize() as i32),
+                    &items,
+                ],
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
     async fn process_transaction(
         dbtx: &Transaction<'_>,
         federation_id: FederationId,
@@ -944,81 +1332,50 @@ impl FederationObserver {
                     .await?
                     .get::<_, i32>("num_sigs") as usize;
 
-                // 3n + 1 <= num_peers
-                // n <= (num_peers - 1) / 3
-                // threshold = num_peers - floor((num_peers - 1) / 3)
-                let threshold = {
-                    let num_peers = config.global.api_endpoints.len();
-                    num_peers - (num_peers - 1) / 3
-                };
-
-                if num_sigs < threshold {
+                if num_sigs < signature_threshold(config) {
                     return Ok(());
                 }
 
                 // at this point, the transaction reached threshold and should broadcast
 
-                let esplora_txid = esplora_client::Txid::from_str(peg_out_txid.as_str())
-                    .expect("Couldn't create esplora txid");
-
-                let builder = esplora_client::Builder::new("https://mempool.space/api");
-                let client = builder
-                    .build_async()
-                    .expect("Failed to build esplora client");
-
-                let fetched_tx = retry(
-                    format!("fetching tx from esplora"),
-                    FibonacciBackoff::default()
-                        .with_min_delay(Duration::from_secs(30))
-                        .with_max_delay(Duration::from_secs(60 * 30))
-                        .with_max_times(usize::MAX),
-                    || async {
-                        client.get_tx_no_opt(&esplora_txid).await.map_err(|e| {
-                            warn!("failed to fetch tx: {e:?}");
-                            anyhow::anyhow!("failed fetching tx from esplora")
-                        })
-                    },
+                dbtx.execute(
+                    "
+                    UPDATE wallet_withdrawal_transactions
+                    SET threshold_session_index = COALESCE(threshold_session_index, $2)
+                    WHERE on_chain_txid = $1
+                    ",
+                    &[&peg_out_txid_encoded, &(session_index as i32)],
                 )
-                .await
-                .expect("Reached usize::MAX retries");
+                .await?;
 
-                for input in fetched_tx.input {
-                    let prev_out_txid = fedimint_core::TransactionId::from_str(
-                        input.previous_output.txid.to_string().as_str(),
+                // On-chain data survives reindexing, so we only have to fetch it once
+                let tx_recorded = dbtx
+                    .query_one(
+                        "
+                        SELECT EXISTS (SELECT 1
+                                       FROM wallet_withdrawal_transaction_outputs
+                                       WHERE on_chain_txid = $1) AS recorded
+                        ",
+                        &[&peg_out_txid_encoded],
                     )
-                    .expect("Invalid txid")
-                    .consensus_encode_to_vec();
+                    .await?
+                    .get::<_, bool>("recorded");
 
-                    dbtx.execute(
-                        "INSERT INTO wallet_withdrawal_transaction_inputs VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
-                        &[
-                            &prev_out_txid,
-                            &(input.previous_output.vout as i32),
-                            &peg_out_txid_encoded,
-                        ],
-                    )
-                    .await?;
+                if !tx_recorded {
+                    Self::fetch_withdrawal_transaction(dbtx, &peg_out_txid, &peg_out_txid_encoded)
+                        .await?;
                 }
 
-                for (out_idx, output) in fetched_tx.output.iter().enumerate() {
-                    let address = bitcoin::Address::from_script(
-                        bitcoin::Script::from_bytes(output.script_pubkey.as_bytes()),
-                        bitcoin::Network::Bitcoin,
-                    )
-                    .expect("Invalid bitcoin address");
-
-                    dbtx.execute(
-                        "INSERT INTO wallet_withdrawal_transaction_outputs VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING",
-                        &[
-                            &peg_out_txid_encoded,
-                            &(out_idx as i32),
-                            &address.to_string(),
-                            &((output.value.to_sat() as i64) * 1000),
-
-                        ],
+                let output_addresses = dbtx
+                    .query(
+                        "SELECT address FROM wallet_withdrawal_transaction_outputs WHERE on_chain_txid = $1 ORDER BY on_chain_vout",
+                        &[&peg_out_txid_encoded],
                     )
                     .await?;
 
+                for row in output_addresses {
+                    let address = row.get::<_, String>("address");
+
                     // update federation_txid if we found a matching withdrawal address
                     dbtx.execute(
                         "
@@ -1039,11 +1396,22 @@ impl FederationObserver {
                         WHERE on_chain_txid = $2
                           AND federation_txid IS NULL
                         ",
-                        &[&address.to_string(), &peg_out_txid_encoded],
+                        &[&address, &peg_out_txid_encoded],
                     )
                     .await?;
                 }
             }
+            WalletConsensusItem::Feerate(feerate) => {
+                Self::insert_feerate_vote(
+                    dbtx,
+                    federation_id,
+                    session_index,
+                    item_index,
+                    peer_id,
+                    feerate.sats_per_kvb,
+                )
+                .await?;
+            }
             _ => {
                 // other WalletConsesnsusItems are not needed yet
             }
@@ -1052,6 +1420,104 @@ impl FederationObserver {
         Ok(())
     }
 
+    async fn fetch_withdrawal_transaction(
+        dbtx: &Transaction<'_>,
+        peg_out_txid: &str,
+        peg_out_txid_encoded: &Vec<u8>,
+    ) -> Result<(), tokio_postgres::Error> {
+        let esplora_txid =
+            esplora_client::Txid::from_str(peg_out_txid).expect("Couldn't create esplora txid");
+
+        let builder = esplora_client::Builder::new("https://mempool.space/api");
+        let client = builder
+            .build_async()
+            .expect("Failed to build esplora client");
+
+        let fetched_tx = retry(
+            "fetching tx from esplora".to_owned(),
+            FibonacciBackoff::default()
+                .with_min_delay(Duration::from_secs(30))
+                .with_max_delay(Duration::from_secs(60 * 30))
+                .with_max_times(usize::MAX),
+            || async {
+                client.get_tx_no_opt(&esplora_txid).await.map_err(|e| {
+                    warn!("failed to fetch tx: {e:?}");
+                    anyhow::anyhow!("failed fetching tx from esplora")
+                })
+            },
+        )
+        .await
+        .expect("Reached usize::MAX retries");
+
+        dbtx.execute(
+            "UPDATE wallet_withdrawal_transactions SET weight = $2 WHERE on_chain_txid = $1",
+            &[peg_out_txid_encoded, &(fetched_tx.weight().to_wu() as i32)],
+        )
+        .await?;
+
+        for input in fetched_tx.input {
+            let prev_out_txid = fedimint_core::TransactionId::from_str(
+                input.previous_output.txid.to_string().as_str(),
+            )
+            .expect("Invalid txid")
+            .consensus_encode_to_vec();
+
+            dbtx.execute(
+                "INSERT INTO wallet_withdrawal_transaction_inputs VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+                &[
+                    &prev_out_txid,
+                    &(input.previous_output.vout as i32),
+                    peg_out_txid_encoded,
+                ],
+            )
+            .await?;
+        }
+
+        for (out_idx, output) in fetched_tx.output.iter().enumerate() {
+            let address = bitcoin::Address::from_script(
+                bitcoin::Script::from_bytes(output.script_pubkey.as_bytes()),
+                bitcoin::Network::Bitcoin,
+            )
+            .expect("Invalid bitcoin address");
+
+            dbtx.execute(
+                "INSERT INTO wallet_withdrawal_transaction_outputs VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING",
+                &[
+                    peg_out_txid_encoded,
+                    &(out_idx as i32),
+                    &address.to_string(),
+                    &((output.value.to_sat() as i64) * 1000),
+                ],
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn insert_feerate_vote(
+        dbtx: &Transaction<'_>,
+        federation_id: FederationId,
+        session_index: u64,
+        item_index: u64,
+        peer_id: PeerId,
+        sats_per_kvb: u64,
+    ) -> Result<(), tokio_postgres::Error> {
+        dbtx.execute(
+            "INSERT INTO feerate_votes VALUES ($1, $2, $3, $4, $5) ON CONFLICT DO NOTHING",
+            &[
+                &federation_id.consensus_encode_to_vec(),
+                &(session_index as i32),
+                &(item_index as i32),
+                &(peer_id.to_usize() as i32),
+                &(sats_per_kvb as i64),
+            ],
+        )
+        .await?;
+
+        Ok(())
+    }
+
     pub async fn get_federation_assets(
         &self,
         federation_id: FederationId,
@@ -1140,6 +1606,20 @@ impl FederationObserver {
     }
 }
 
+/// Number of peg-out signatures required to finalize a peg-out transaction
+pub(super) fn signature_threshold(config: &ClientConfig) -> usize {
+    consensus_threshold(config.global.api_endpoints.len())
+}
+
+/// Number of guardians that have to be online for the federation to reach
+/// consensus
+pub(crate) fn consensus_threshold(num_peers: usize) -> usize {
+    // 3n + 1 <= num_peers
+    // n <= (num_peers - 1) / 3
+    // threshold = num_peers - floor((num_peers - 1) / 3)
+    num_peers - (num_peers - 1) / 3
+}
+
 fn last_n_day_iter(now: NaiveDate, days: u32) -> impl Iterator<Item = NaiveDate> {
     (0..days)
         .rev()
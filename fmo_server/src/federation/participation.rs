@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use fedimint_core::config::FederationId;
+use fedimint_core::encoding::Encodable;
+use fedimint_core::PeerId;
+use fmo_api_types::{FederationParticipation, GuardianParticipation, SessionRange};
+use postgres_from_row::FromRow;
+use serde::Deserialize;
+
+use crate::federation::observer::FederationObserver;
+use crate::util::{query, query_one};
+use crate::AppState;
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ParticipationParams {
+    /// Shortest stretch of inactive sessions to report, single missed sessions
+    /// are common when a guardian simply had nothing to propose
+    #[serde(default = "default_min_inactive_sessions")]
+    min_inactive_sessions: u32,
+}
+
+fn default_min_inactive_sessions() -> u32 {
+    10
+}
+
+pub(super) async fn get_guardian_participation(
+    Path(federation_id): Path<FederationId>,
+    Query(params): Query<ParticipationParams>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<FederationParticipation>> {
+    Ok(state
+        .federation_observer
+        .guardian_participation(federation_id, params.min_inactive_sessions)
+        .await?
+        .into())
+}
+
+impl FederationObserver {
+    pub async fn guardian_participation(
+        &self,
+        federation_id: FederationId,
+        min_inactive_sessions: u32,
+    ) -> anyhow::Result<FederationParticipation> {
+        #[derive(Debug, FromRow)]
+        struct PeerTotalsRow {
+            peer_id: i32,
+            items: i64,
+            sessions: i64,
+        }
+
+        #[derive(Debug, FromRow)]
+        struct ActiveSessionsRow {
+            sessions: i64,
+            first_session_index: Option<i32>,
+            last_session_index: Option<i32>,
+        }
+
+        #[derive(Debug, FromRow)]
+        struct InactiveRangeRow {
+            peer_id: i32,
+            start_session_index: i32,
+            end_session_index: i32,
+            sessions: i64,
+        }
+
+        let config = self
+            .get_federation(federation_id)
+            .await?
+            .context("Federation doesn't exist")?
+            .config;
+
+        let conn = self.connection().await?;
+        let federation_id_bytes = federation_id.consensus_encode_to_vec();
+
+        let active_sessions = query_one::<ActiveSessionsRow>(
+            &conn,
+            // language=postgresql
+            "
+            SELECT COUNT(DISTINCT session_index)::bigint AS sessions,
+                   MIN(session_index)                   AS first_session_index,
+                   MAX(session_index)                   AS last_session_index
+            FROM session_peer_items
+            WHERE federation_id = $1
+            ",
+            &[&federation_id_bytes],
+        )
+        .await?;
+
+        let peer_totals = query::<PeerTotalsRow>(
+            &conn,
+            // language=postgresql
+            "
+            SELECT peer_id,
+                   SUM(items)::bigint AS items,
+                   COUNT(*)::bigint   AS sessions
+            FROM session_peer_items
+            WHERE federation_id = $1
+            GROUP BY peer_id
+            ",
+            &[&federation_id_bytes],
+        )
+        .await?
+        .into_iter()
+        .map(|row| (PeerId::from(row.peer_id as u16), row))
+        .collect::<BTreeMap<_, _>>();
+
+        // Materialized since finding them means checking every session for every
+        // peer, so they are only as recent as the last view refresh
+        let inactive_ranges = query::<InactiveRangeRow>(
+            &conn,
+            // language=postgresql
+            "
+            SELECT peer_id, start_session_index, end_session_index, sessions
+            FROM guardian_inactive_ranges
+            WHERE federation_id = $1
+              AND sessions >= $2
+            ORDER BY peer_id, start_session_index DESC
+            ",
+            &[&federation_id_bytes, &(min_inactive_sessions as i64)],
+        )
+        .await?;
+
+        let total_items = peer_totals.values().map(|row| row.items).sum::<i64>();
+        let mut guardians = config
+            .global
+            .api_endpoints
+            .keys()
+            .map(|peer_id| {
+                let totals = peer_totals.get(peer_id);
+                let items = totals.map_or(0, |row| row.items);
+                (
+                    *peer_id,
+                    GuardianParticipation {
+                        items: items as u64,
+                        proposal_share: if total_items == 0 {
+                            0.0
+                        } else {
+                            items as f64 / total_items as f64
+                        },
+                        sessions_contributed: totals.map_or(0, |row| row.sessions as u64),
+                        // Guardians that never proposed anything aren't in the materialized view
+                        inactive_ranges: match (
+                            totals,
+                            active_sessions.first_session_index,
+                            active_sessions.last_session_index,
+                        ) {
+                            (None, Some(start), Some(end))
+                                if active_sessions.sessions >= min_inactive_sessions as i64 =>
+                            {
+                                vec![SessionRange {
+                                    start_session_index: start as u64,
+                                    end_session_index: end as u64,
+                                    sessions: active_sessions.sessions as u64,
+                                }]
+                            }
+                            _ => vec![],
+                        },
+                    },
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        for range in inactive_ranges {
+            if let Some(guardian) = guardians.get_mut(&PeerId::from(range.peer_id as u16)) {
+                guardian.inactive_ranges.push(SessionRange {
+                    start_session_index: range.start_session_index as u64,
+                    end_session_index: range.end_session_index as u64,
+                    sessions: range.sessions as u64,
+                });
+            }
+        }
+
+        Ok(FederationParticipation {
+            active_sessions: active_sessions.sessions as u64,
+            guardians,
+        })
+    }
+}
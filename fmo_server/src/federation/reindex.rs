@@ -0,0 +1,281 @@
+use anyhow::{ensure, Context};
+use axum::extract::{Path, State};
+use axum::Json;
+use chrono::NaiveDateTime;
+use deadpool_postgres::Transaction;
+use fedimint_core::config::FederationId;
+use fedimint_core::encoding::Encodable;
+use fmo_api_types::ReindexJob;
+use futures::StreamExt;
+use postgres_from_row::FromRow;
+use tracing::{error, info};
+
+use crate::federation::decoders_from_config;
+use crate::federation::observer::FederationObserver;
+use crate::util::{decode_db_value, query, query_value};
+use crate::AppState;
+
+/// Number of sessions processed per database transaction, progress is saved
+/// after each batch
+const REINDEX_BATCH_SIZE: i64 = 1000;
+
+/// Tables that are populated by `process_session` and can be rebuilt from the
+/// `sessions` table, in an order that satisfies foreign keys. Peg-out
+/// transactions and their inputs/outputs are kept since they were fetched from
+/// an explorer and can't be reconstructed from sessions.
+// language=postgresql
+const CLEAR_DERIVED_TABLES: &str = "
+    DELETE FROM wallet_peg_ins WHERE federation_id = $1;
+    DELETE FROM wallet_withdrawal_addresses WHERE federation_id = $1;
+    DELETE FROM wallet_withdrawal_signatures
+    WHERE on_chain_txid IN (SELECT on_chain_txid FROM wallet_withdrawal_transactions WHERE federation_id = $1);
+    DELETE FROM ln_contracts WHERE federation_id = $1;
+    DELETE FROM transaction_inputs WHERE federation_id = $1;
+    DELETE FROM transaction_outputs WHERE federation_id = $1;
+    DELETE FROM transactions WHERE federation_id = $1;
+    DELETE FROM block_height_votes WHERE federation_id = $1;
+    DELETE FROM feerate_votes WHERE federation_id = $1;
+    DELETE FROM session_peer_items WHERE federation_id = $1;
+";
+
+pub(super) async fn list_reindex_jobs(
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<Vec<ReindexJob>>> {
+    Ok(state.federation_observer.reindex_jobs().await?.into())
+}
+
+pub(super) async fn reindex_all_federations(
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<Vec<FederationId>>> {
+    let mut federation_ids = vec![];
+    for federation in state.federation_observer.list_federations().await? {
+        state
+            .federation_observer
+            .start_reindex(federation.federation_id)
+            .await?;
+        state
+            .federation_observer
+            .spawn_reindex(federation.federation_id);
+        federation_ids.push(federation.federation_id);
+    }
+
+    Ok(federation_ids.into())
+}
+
+pub(super) async fn reindex_federation(
+    Path(federation_id): Path<FederationId>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<FederationId>> {
+    state
+        .federation_observer
+        .start_reindex(federation_id)
+        .await?;
+    state.federation_observer.spawn_reindex(federation_id);
+
+    Ok(federation_id.into())
+}
+
+impl FederationObserver {
+    /// Clears all derived data of a federation and registers a reindex job for
+    /// it. The actual reindexing is done by
+    /// [`FederationObserver::run_reindex`].
+    pub async fn start_reindex(&self, federation_id: FederationId) -> anyhow::Result<()> {
+        self.get_federation(federation_id)
+            .await?
+            .context("Federation doesn't exist")?;
+
+        ensure!(
+            !self.reindex_in_progress(federation_id).await?,
+            "Reindex of {federation_id} is already in progress"
+        );
+
+        let federation_id_bytes = federation_id.consensus_encode_to_vec();
+        let mut conn = self.connection().await?;
+        let dbtx = conn.transaction().await?;
+
+        // Pauses the live observer before we start deleting data
+        dbtx.execute(
+            "
+            INSERT INTO reindex_jobs VALUES ($1, 0, NOW(), NULL)
+            ON CONFLICT (federation_id) DO UPDATE SET next_session_index = 0, started_at = NOW(), finished_at = NULL
+            ",
+            &[&federation_id_bytes],
+        )
+        .await?;
+
+        clear_derived_tables(&dbtx, federation_id).await?;
+
+        dbtx.commit().await?;
+
+        info!("Cleared derived data of {federation_id}, reindex scheduled");
+
+        Ok(())
+    }
+
+    pub(super) fn spawn_reindex(&self, federation_id: FederationId) {
+        let slf = self.clone();
+        self.task_group
+            .spawn_cancellable(format!("Reindex {federation_id}"), async move {
+                if let Err(e) = slf.run_reindex(federation_id).await {
+                    error!(
+                        "Reindex of {federation_id} failed, restart the server to resume: {e:?}"
+                    );
+                }
+            });
+    }
+
+    /// Processes all stored sessions of a federation starting from where the
+    /// last run of its reindex job stopped
+    pub async fn run_reindex(&self, federation_id: FederationId) -> anyhow::Result<()> {
+        let config = self
+            .get_federation(federation_id)
+            .await?
+            .context("Federation doesn't exist")?
+            .config;
+        let decoders = decoders_from_config(&config);
+        let federation_id_bytes = federation_id.consensus_encode_to_vec();
+
+        loop {
+            let mut conn = self.connection().await?;
+            let dbtx = conn.transaction().await?;
+
+            let next_session_index = dbtx
+                .query_opt(
+                    "SELECT next_session_index FROM reindex_jobs WHERE federation_id = $1 AND finished_at IS NULL FOR UPDATE",
+                    &[&federation_id_bytes],
+                )
+                .await?
+                .context("No reindex job in progress")?
+                .get::<_, i32>("next_session_index");
+
+            let session_rows = dbtx
+                .query(
+                    "
+                    SELECT *
+                    FROM sessions
+                    WHERE federation_id = $1
+                      AND session_index >= $2
+                    ORDER BY session_index
+                    LIMIT $3
+                    ",
+                    &[
+                        &federation_id_bytes,
+                        &next_session_index,
+                        &REINDEX_BATCH_SIZE,
+                    ],
+                )
+                .await?;
+
+            if session_rows.is_empty() {
+                dbtx.execute(
+                    "UPDATE reindex_jobs SET finished_at = NOW() WHERE federation_id = $1",
+                    &[&federation_id_bytes],
+                )
+                .await?;
+                dbtx.commit().await?;
+                break;
+            }
+
+            let mut last_session_index = next_session_index;
+            let mut parsing_stream = Self::parse_session_rows(session_rows, decoders.clone());
+            while let Some(outcome) = parsing_stream.next().await.transpose()? {
+                last_session_index = outcome.session_index;
+                self.process_session(
+                    federation_id,
+                    config.clone(),
+                    outcome.session_index as u64,
+                    outcome.data,
+                    &dbtx,
+                )
+                .await?;
+            }
+
+            dbtx.execute(
+                "UPDATE reindex_jobs SET next_session_index = $2 WHERE federation_id = $1",
+                &[&federation_id_bytes, &(last_session_index + 1)],
+            )
+            .await?;
+            dbtx.commit().await?;
+
+            info!("Reindexed {federation_id} up to session {last_session_index}");
+        }
+
+        self.refresh_views().await?;
+        info!("Reindex of {federation_id} finished");
+
+        Ok(())
+    }
+
+    pub async fn reindex_in_progress(&self, federation_id: FederationId) -> anyhow::Result<bool> {
+        query_value::<bool>(
+            &self.connection().await?,
+            "SELECT EXISTS (SELECT 1 FROM reindex_jobs WHERE federation_id = $1 AND finished_at IS NULL)",
+            &[&federation_id.consensus_encode_to_vec()],
+        )
+        .await
+    }
+
+    pub async fn unfinished_reindex_jobs(&self) -> anyhow::Result<Vec<FederationId>> {
+        Ok(self
+            .reindex_jobs()
+            .await?
+            .into_iter()
+            .filter(|job| job.finished_at.is_none())
+            .map(|job| job.federation_id)
+            .collect())
+    }
+
+    pub async fn reindex_jobs(&self) -> anyhow::Result<Vec<ReindexJob>> {
+        #[derive(Debug, FromRow)]
+        struct ReindexJobRow {
+            federation_id: Vec<u8>,
+            next_session_index: i32,
+            session_count: i64,
+            started_at: NaiveDateTime,
+            finished_at: Option<NaiveDateTime>,
+        }
+
+        let jobs = query::<ReindexJobRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            SELECT rj.federation_id,
+                   rj.next_session_index,
+                   (SELECT COUNT(*) FROM sessions s WHERE s.federation_id = rj.federation_id)::bigint AS session_count,
+                   rj.started_at,
+                   rj.finished_at
+            FROM reindex_jobs rj
+            ORDER BY rj.started_at
+            ",
+            &[],
+        )
+        .await?;
+
+        Ok(jobs
+            .into_iter()
+            .map(|job| ReindexJob {
+                federation_id: decode_db_value(job.federation_id),
+                next_session_index: job.next_session_index as u64,
+                session_count: job.session_count as u64,
+                started_at: job.started_at,
+                finished_at: job.finished_at,
+            })
+            .collect())
+    }
+}
+
+pub(super) async fn clear_derived_tables(
+    dbtx: &Transaction<'_>,
+    federation_id: FederationId,
+) -> anyhow::Result<()> {
+    let federation_id_bytes = federation_id.consensus_encode_to_vec();
+    for statement in CLEAR_DERIVED_TABLES
+        .split(';')
+        .map(str::trim)
+        .filter(|statement| !statement.is_empty())
+    {
+        dbtx.execute(statement, &[&federation_id_bytes]).await?;
+    }
+
+    Ok(())
+}
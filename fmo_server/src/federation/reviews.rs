@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use chrono::NaiveDateTime;
+use fedimint_core::config::FederationId;
+use fedimint_core::encoding::Encodable;
+use fmo_api_types::{FederationReview, FederationReviews};
+use nostr_sdk::{Filter, Kind, PublicKey, RelayPool};
+use postgres_from_row::FromRow;
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::federation::nostr::{
+    latest_per_author, strip_star_rating, timestamp_to_naive, AUTHORS_PER_REQUEST,
+};
+use crate::federation::observer::FederationObserver;
+use crate::util::{execute, query, query_value};
+use crate::AppState;
+
+/// Profiles are re-fetched after this time to pick up changes
+const PROFILE_REFRESH_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Reviews returned per page at most
+const MAX_REVIEWS_LIMIT: u32 = 100;
+
+#[derive(Debug, FromRow)]
+struct ReviewRow {
+    event_id: Vec<u8>,
+    author: Vec<u8>,
+    star_vote: Option<i32>,
+    content: String,
+    created_at: NaiveDateTime,
+    metadata: Option<serde_json::Value>,
+}
+
+impl From<ReviewRow> for FederationReview {
+    fn from(row: ReviewRow) -> Self {
+        FederationReview {
+            event_id: hex::encode(row.event_id),
+            author: hex::encode(row.author),
+            star_vote: row.star_vote.map(|vote| vote as u8),
+            comment: strip_star_rating(&row.content).to_owned(),
+            created_at: row.created_at,
+            // Profiles are published by the authors, so malformed ones are ignored
+            author_profile: row
+                .metadata
+                .and_then(|metadata| serde_json::from_value(metadata).ok()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ReviewsParams {
+    #[serde(default = "default_reviews_limit")]
+    limit: u32,
+    #[serde(default)]
+    offset: u32,
+}
+
+fn default_reviews_limit() -> u32 {
+    20
+}
+
+pub(super) async fn list_reviews(
+    Path(federation_id): Path<FederationId>,
+    Query(params): Query<ReviewsParams>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<FederationReviews>> {
+    Ok(state
+        .federation_observer
+        .federation_reviews(federation_id, params.limit, params.offset)
+        .await?
+        .into())
+}
+
+impl FederationObserver {
+    /// Reviews of a federation, newest first
+    pub async fn federation_reviews(
+        &self,
+        federation_id: FederationId,
+        limit: u32,
+        offset: u32,
+    ) -> anyhow::Result<FederationReviews> {
+        self.get_federation(federation_id)
+            .await?
+            .context("Federation doesn't exist")?;
+
+        let connection = self.connection().await?;
+        let federation_id = federation_id.consensus_encode_to_vec();
+
+        let total = query_value::<i64>(
+            &connection,
+            "SELECT COUNT(*) FROM nostr_votes WHERE federation_id = $1",
+            &[&federation_id],
+        )
+        .await?;
+
+        let reviews = query::<ReviewRow>(
+            &connection,
+            // language=postgresql
+            "
+            SELECT v.event_id, v.author, v.star_vote, v.event ->> 'content' AS content, v.created_at, p.metadata
+            FROM nostr_votes v
+                     LEFT JOIN nostr_profiles p ON v.author = p.author
+            WHERE v.federation_id = $1
+            ORDER BY v.created_at DESC
+            LIMIT $2 OFFSET $3
+            ",
+            &[
+                &federation_id,
+                &(limit.min(MAX_REVIEWS_LIMIT) as i64),
+                &(offset as i64),
+            ],
+        )
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+        Ok(FederationReviews {
+            total: total as u64,
+            reviews,
+        })
+    }
+
+    /// Fetches the profiles of rating authors that weren't fetched yet or are
+    /// outdated
+    pub(super) async fn sync_nostr_profiles(&self, client: &RelayPool) -> anyhow::Result<()> {
+        #[derive(Debug, FromRow)]
+        struct AuthorRow {
+            author: Vec<u8>,
+        }
+
+        let connection = self.connection().await?;
+        let now = chrono::Utc::now().naive_utc();
+        let refresh_before =
+            now - chrono::Duration::from_std(PROFILE_REFRESH_INTERVAL).expect("Fits into chrono");
+
+        let authors = query::<AuthorRow>(
+            &connection,
+            // language=postgresql
+            "
+            SELECT DISTINCT v.author
+            FROM nostr_votes v
+                     LEFT JOIN nostr_profiles p ON v.author = p.author
+            WHERE p.fetched_at IS NULL
+               OR p.fetched_at < $1
+            ",
+            &[&refresh_before],
+        )
+        .await?
+        .into_iter()
+        .filter_map(|row| PublicKey::from_slice(&row.author).ok())
+        .collect::<Vec<_>>();
+
+        if authors.is_empty() {
+            return Ok(());
+        }
+
+        let mut events = vec![];
+        for authors in authors.chunks(AUTHORS_PER_REQUEST) {
+            events.extend(
+                self.fetch_all_events(
+                    client,
+                    Filter {
+                        ids: None,
+                        authors: Some(authors.iter().copied().collect()),
+                        kinds: Some(vec![Kind::Metadata].into_iter().collect()),
+                        search: None,
+                        since: None,
+                        until: None,
+                        limit: None,
+                        generic_tags: HashMap::new(),
+                    },
+                    None,
+                )
+                .await?,
+            );
+        }
+        let profiles = latest_per_author(events);
+
+        debug!(
+            "Fetched {} profiles of {} authors",
+            profiles.len(),
+            authors.len()
+        );
+
+        for author in authors {
+            let profile = profiles.get(&author);
+            let metadata = profile
+                .and_then(|event| serde_json::from_str::<serde_json::Value>(&event.content).ok());
+            let created_at = profile
+                .map(|event| timestamp_to_naive(event.created_at))
+                .transpose()?;
+
+            execute(
+                &connection,
+                // language=postgresql
+                "
+                INSERT INTO nostr_profiles (author, metadata, created_at, fetched_at)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (author) DO UPDATE
+                    SET metadata   = COALESCE(excluded.metadata, nostr_profiles.metadata),
+                        created_at = COALESCE(excluded.created_at, nostr_profiles.created_at),
+                        fetched_at = excluded.fetched_at
+                ",
+                &[&author.to_bytes().to_vec(), &metadata, &created_at, &now],
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+    use fmo_api_types::FederationReview;
+    use serde_json::json;
+
+    use super::ReviewRow;
+
+    fn review_row(content: &str, metadata: Option<serde_json::Value>) -> ReviewRow {
+        ReviewRow {
+            event_id: vec![0xab; 32],
+            author: vec![0xcd; 32],
+            star_vote: Some(4),
+            content: content.to_owned(),
+            created_at: NaiveDateTime::default(),
+            metadata,
+        }
+    }
+
+    #[test]
+    fn test_review_from_row() {
+        let review = FederationReview::from(review_row(
+            "[4/5] Fast and reliable",
+            Some(json!({"name": "alice", "display_name": "Alice", "about": "ignored"})),
+        ));
+        assert_eq!(review.event_id, "ab".repeat(32));
+        assert_eq!(review.author, "cd".repeat(32));
+        assert_eq!(review.star_vote, Some(4));
+        assert_eq!(review.comment, "Fast and reliable");
+        let profile = review.author_profile.unwrap();
+        assert_eq!(profile.name.as_deref(), Some("alice"));
+        assert_eq!(profile.display_name.as_deref(), Some("Alice"));
+        assert_eq!(profile.picture, None);
+
+        // Malformed profiles and missing ones are treated the same
+        let review = FederationReview::from(review_row("Great", Some(json!({"name": 42}))));
+        assert_eq!(review.comment, "Great");
+        assert!(review.author_profile.is_none());
+        assert!(FederationReview::from(review_row("Great", None))
+            .author_profile
+            .is_none());
+    }
+}
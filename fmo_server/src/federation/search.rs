@@ -0,0 +1,252 @@
+use std::str::FromStr;
+
+use axum::extract::{Query, State};
+use axum::Json;
+use fedimint_core::api::InviteCode;
+use fedimint_core::config::FederationId;
+use fedimint_core::encoding::Decodable;
+use fmo_api_types::SearchMatch;
+use postgres_from_row::FromRow;
+use serde::Deserialize;
+
+use crate::federation::observer::FederationObserver;
+use crate::util::{decode_db_value, query};
+use crate::AppState;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SearchParams {
+    q: String,
+}
+
+pub(crate) async fn search(
+    Query(params): Query<SearchParams>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<Vec<SearchMatch>>> {
+    Ok(state.federation_observer.search(&params.q).await?.into())
+}
+
+impl FederationObserver {
+    /// Tries to interpret `query` as any identifier we know about and returns
+    /// all matching objects across observed federations. A single query can
+    /// match multiple kinds of objects, e.g. a 32 byte hash could be a
+    /// federation id, a fedimint transaction id or a Lightning payment hash.
+    pub async fn search(&self, query: &str) -> anyhow::Result<Vec<SearchMatch>> {
+        let query = query.trim();
+        let mut matches = Vec::new();
+
+        if let Ok(invite) = InviteCode::from_str(query) {
+            matches.push(self.federation_match(invite.federation_id()).await?);
+        }
+
+        if let Ok(session_index) = query.parse::<u32>() {
+            matches.extend(self.search_sessions(session_index).await?);
+        }
+
+        if let Some(hash) = hex::decode(query).ok().filter(|bytes| bytes.len() == 32) {
+            matches.extend(self.search_hash(&hash).await?);
+        }
+
+        if let Ok(address) = bitcoin::Address::from_str(query) {
+            matches.extend(
+                self.search_address(&address.assume_checked().to_string())
+                    .await?,
+            );
+        }
+
+        Ok(matches)
+    }
+
+    async fn federation_match(&self, federation_id: FederationId) -> anyhow::Result<SearchMatch> {
+        let federation = self.get_federation(federation_id).await?;
+        Ok(SearchMatch::Federation {
+            federation_id,
+            name: federation.as_ref().and_then(|federation| {
+                federation
+                    .config
+                    .global
+                    .federation_name()
+                    .map(ToOwned::to_owned)
+            }),
+            observed: federation.is_some(),
+        })
+    }
+
+    async fn search_sessions(&self, session_index: u32) -> anyhow::Result<Vec<SearchMatch>> {
+        #[derive(Debug, FromRow)]
+        struct SessionRow {
+            federation_id: Vec<u8>,
+        }
+
+        Ok(query::<SessionRow>(
+            &self.connection().await?,
+            "SELECT federation_id FROM sessions WHERE session_index = $1",
+            &[&(session_index as i32)],
+        )
+        .await?
+        .into_iter()
+        .map(|row| SearchMatch::Session {
+            federation_id: decode_db_value(row.federation_id),
+            session_index: session_index.into(),
+        })
+        .collect())
+    }
+
+    async fn search_hash(&self, hash: &[u8]) -> anyhow::Result<Vec<SearchMatch>> {
+        #[derive(Debug, FromRow)]
+        struct TransactionRow {
+            federation_id: Vec<u8>,
+            session_index: i32,
+        }
+
+        #[derive(Debug, FromRow)]
+        struct ContractRow {
+            federation_id: Vec<u8>,
+            contract_id: Vec<u8>,
+            payment_hash: Vec<u8>,
+            contract_type: String,
+        }
+
+        #[derive(Debug, FromRow)]
+        struct PegInRow {
+            federation_id: Vec<u8>,
+            on_chain_vout: i32,
+            txid: Vec<u8>,
+        }
+
+        #[derive(Debug, FromRow)]
+        struct PegOutRow {
+            federation_id: Vec<u8>,
+            federation_txid: Option<Vec<u8>>,
+        }
+
+        let conn = self.connection().await?;
+        let hash_param = hash.to_vec();
+        let mut matches = Vec::new();
+
+        // Federation ids are hashes too
+        if let Ok(federation_id) =
+            FederationId::consensus_decode_vec(hash_param.clone(), &Default::default())
+        {
+            if self.get_federation(federation_id).await?.is_some() {
+                matches.push(self.federation_match(federation_id).await?);
+            }
+        }
+
+        matches.extend(
+            query::<TransactionRow>(
+                &conn,
+                "SELECT federation_id, session_index FROM transactions WHERE txid = $1",
+                &[&hash_param],
+            )
+            .await?
+            .into_iter()
+            .map(|row| SearchMatch::Transaction {
+                federation_id: decode_db_value(row.federation_id),
+                txid: decode_db_value(hash_param.clone()),
+                session_index: row.session_index as u64,
+            }),
+        );
+
+        matches.extend(
+            query::<ContractRow>(
+                &conn,
+                // language=postgresql
+                "
+                SELECT federation_id, contract_id, payment_hash, type AS contract_type
+                FROM ln_contracts
+                WHERE contract_id = $1 OR payment_hash = $1
+                ",
+                &[&hash_param],
+            )
+            .await?
+            .into_iter()
+            .map(|row| SearchMatch::LightningContract {
+                federation_id: decode_db_value(row.federation_id),
+                contract_id: hex::encode(row.contract_id),
+                payment_hash: hex::encode(row.payment_hash),
+                contract_type: row.contract_type,
+            }),
+        );
+
+        // On-chain txids are stored in display byte order, see `process_transaction`
+        matches.extend(
+            query::<PegInRow>(
+                &conn,
+                "SELECT federation_id, on_chain_vout, txid FROM wallet_peg_ins WHERE on_chain_txid = $1",
+                &[&hash_param],
+            )
+            .await?
+            .into_iter()
+            .map(|row| SearchMatch::PegIn {
+                federation_id: decode_db_value(row.federation_id),
+                on_chain_txid: hex::encode(hash),
+                on_chain_vout: row.on_chain_vout as u32,
+                txid: decode_db_value(row.txid),
+            }),
+        );
+
+        matches.extend(
+            query::<PegOutRow>(
+                &conn,
+                "SELECT federation_id, federation_txid FROM wallet_withdrawal_transactions WHERE on_chain_txid = $1",
+                &[&hash_param],
+            )
+            .await?
+            .into_iter()
+            .map(|row| SearchMatch::PegOut {
+                federation_id: decode_db_value(row.federation_id),
+                on_chain_txid: hex::encode(hash),
+                txid: row.federation_txid.map(decode_db_value),
+            }),
+        );
+
+        Ok(matches)
+    }
+
+    async fn search_address(&self, address: &str) -> anyhow::Result<Vec<SearchMatch>> {
+        #[derive(Debug, FromRow)]
+        struct DepositAddressRow {
+            federation_id: Vec<u8>,
+        }
+
+        #[derive(Debug, FromRow)]
+        struct WithdrawalAddressRow {
+            federation_id: Vec<u8>,
+            txid: Vec<u8>,
+        }
+
+        let conn = self.connection().await?;
+        let mut matches = Vec::new();
+
+        matches.extend(
+            query::<DepositAddressRow>(
+                &conn,
+                "SELECT DISTINCT federation_id FROM wallet_peg_ins WHERE address = $1",
+                &[&address],
+            )
+            .await?
+            .into_iter()
+            .map(|row| SearchMatch::DepositAddress {
+                federation_id: decode_db_value(row.federation_id),
+                address: address.to_owned(),
+            }),
+        );
+
+        matches.extend(
+            query::<WithdrawalAddressRow>(
+                &conn,
+                "SELECT federation_id, txid FROM wallet_withdrawal_addresses WHERE address = $1",
+                &[&address],
+            )
+            .await?
+            .into_iter()
+            .map(|row| SearchMatch::WithdrawalAddress {
+                federation_id: decode_db_value(row.federation_id),
+                address: address.to_owned(),
+                txid: decode_db_value(row.txid),
+            }),
+        );
+
+        Ok(matches)
+    }
+}
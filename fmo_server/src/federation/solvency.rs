@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use axum::extract::{Path, State};
+use axum::Json;
+use chrono::NaiveDate;
+use fedimint_core::config::FederationId;
+use fedimint_core::encoding::Encodable;
+use fedimint_core::Amount;
+use fmo_api_types::FederationBalanceSheet;
+use postgres_from_row::FromRow;
+
+use crate::federation::observer::FederationObserver;
+use crate::util::{query, query_one};
+use crate::AppState;
+
+pub(super) async fn get_federation_solvency(
+    Path(federation_id): Path<FederationId>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<FederationBalanceSheet>> {
+    Ok(state
+        .federation_observer
+        .federation_balance_sheet(federation_id)
+        .await?
+        .into())
+}
+
+pub(super) async fn get_federation_solvency_history(
+    Path(federation_id): Path<FederationId>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<BTreeMap<NaiveDate, FederationBalanceSheet>>> {
+    Ok(state
+        .federation_observer
+        .federation_balance_sheet_history(federation_id)
+        .await?
+        .into())
+}
+
+impl FederationObserver {
+    /// Current balance sheet, assets are taken from the reconstructed UTXO set
+    pub async fn federation_balance_sheet(
+        &self,
+        federation_id: FederationId,
+    ) -> anyhow::Result<FederationBalanceSheet> {
+        #[derive(Debug, FromRow)]
+        struct BalanceSheetRow {
+            assets: i64,
+            mint_issued: i64,
+            mint_redeemed: i64,
+            ln_funded: i64,
+            ln_claimed: i64,
+        }
+
+        self.get_federation(federation_id)
+            .await?
+            .context("Federation doesn't exist")?;
+
+        let row = query_one::<BalanceSheetRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            SELECT (SELECT COALESCE(SUM(amount_msat), 0)
+                    FROM utxos
+                    WHERE federation_id = $1)::bigint AS assets,
+                   (SELECT COALESCE(SUM(amount_msat), 0)
+                    FROM transaction_outputs
+                    WHERE federation_id = $1 AND kind = 'mint')::bigint AS mint_issued,
+                   (SELECT COALESCE(SUM(amount_msat), 0)
+                    FROM transaction_inputs
+                    WHERE federation_id = $1 AND kind = 'mint')::bigint AS mint_redeemed,
+                   (SELECT COALESCE(SUM(amount_msat), 0)
+                    FROM transaction_outputs
+                    WHERE federation_id = $1 AND kind = 'ln')::bigint AS ln_funded,
+                   (SELECT COALESCE(SUM(amount_msat), 0)
+                    FROM transaction_inputs
+                    WHERE federation_id = $1 AND kind = 'ln')::bigint AS ln_claimed
+            ",
+            &[&federation_id.consensus_encode_to_vec()],
+        )
+        .await?;
+
+        Ok(balance_sheet(
+            row.assets,
+            row.mint_issued - row.mint_redeemed,
+            row.ln_funded - row.ln_claimed,
+        ))
+    }
+
+    /// Daily end-of-day balance sheets. Since the UTXO set isn't versioned,
+    /// assets are approximated by the net wallet module flows, which is what
+    /// the UTXO set converges to once all peg-outs confirmed.
+    pub async fn federation_balance_sheet_history(
+        &self,
+        federation_id: FederationId,
+    ) -> anyhow::Result<BTreeMap<NaiveDate, FederationBalanceSheet>> {
+        #[derive(Debug, FromRow)]
+        struct ModuleFlowRow {
+            date: Option<NaiveDate>,
+            kind: String,
+            amount_in: i64,
+            amount_out: i64,
+        }
+
+        self.get_federation(federation_id)
+            .await?
+            .context("Federation doesn't exist")?;
+
+        // Sessions before the first block height vote have no timestamp, they
+        // are sorted first and thus become part of the opening balance
+        let flows = query::<ModuleFlowRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            WITH flows AS (SELECT ti.federation_id, ti.txid, ti.kind, ti.amount_msat AS amount_in, 0 AS amount_out
+                           FROM transaction_inputs ti
+                           WHERE ti.federation_id = $1
+                           UNION ALL
+                           SELECT tout.federation_id, tout.txid, tout.kind, 0 AS amount_in, tout.amount_msat AS amount_out
+                           FROM transaction_outputs tout
+                           WHERE tout.federation_id = $1)
+            SELECT DATE(st.estimated_session_timestamp)     AS date,
+                   f.kind,
+                   COALESCE(SUM(f.amount_in), 0)::bigint  AS amount_in,
+                   COALESCE(SUM(f.amount_out), 0)::bigint AS amount_out
+            FROM flows f
+                     JOIN transactions t ON f.federation_id = t.federation_id AND f.txid = t.txid
+                     JOIN session_times st ON t.federation_id = st.federation_id AND t.session_index = st.session_index
+            GROUP BY date, f.kind
+            ORDER BY date NULLS FIRST
+            ",
+            &[&federation_id.consensus_encode_to_vec()],
+        )
+        .await?;
+
+        let mut history = BTreeMap::new();
+        let (mut assets, mut ecash, mut lightning) = (0i64, 0i64, 0i64);
+        for flow in flows {
+            match flow.kind.as_str() {
+                // Peg-ins are wallet inputs, peg-outs wallet outputs
+                "wallet" => assets += flow.amount_in - flow.amount_out,
+                // Issuing e-cash is a mint output, redeeming it a mint input
+                "mint" => ecash += flow.amount_out - flow.amount_in,
+                // Funding a contract is an output, claiming or refunding it an input
+                "ln" => lightning += flow.amount_out - flow.amount_in,
+                _ => {}
+            }
+
+            // Rows are ordered by date, so the last write per date is the end-of-day state
+            if let Some(date) = flow.date {
+                history.insert(date, balance_sheet(assets, ecash, lightning));
+            }
+        }
+
+        Ok(history)
+    }
+}
+
+fn balance_sheet(
+    assets_msat: i64,
+    ecash_liabilities_msat: i64,
+    lightning_liabilities_msat: i64,
+) -> FederationBalanceSheet {
+    FederationBalanceSheet {
+        assets: Amount::from_msats(assets_msat.max(0) as u64),
+        ecash_liabilities: Amount::from_msats(ecash_liabilities_msat.max(0) as u64),
+        lightning_liabilities: Amount::from_msats(lightning_liabilities_msat.max(0) as u64),
+        surplus_msat: assets_msat - ecash_liabilities_msat - lightning_liabilities_msat,
+    }
+}
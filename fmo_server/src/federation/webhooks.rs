@@ -0,0 +1,475 @@
+use std::time::Duration;
+
+use anyhow::{ensure, Context};
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use bitcoin::hashes::hmac::{Hmac, HmacEngine};
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use chrono::NaiveDateTime;
+use deadpool_postgres::GenericClient;
+use fedimint_core::encoding::Encodable;
+use fedimint_core::Amount;
+use fmo_api_types::{
+    FederationEvent, NewWebhook, Webhook, WebhookDelivery, FEDERATION_EVENT_KINDS,
+};
+use postgres_from_row::FromRow;
+use serde::Deserialize;
+use tokio::time::sleep;
+use tracing::{debug, info, warn};
+
+use crate::federation::observer::FederationObserver;
+use crate::util::{decode_db_value, execute, query, query_one};
+use crate::AppState;
+
+/// Deliveries that failed this many times are given up on
+const MAX_DELIVERY_ATTEMPTS: i32 = 10;
+
+/// Delay before the first retry, doubles with every failed attempt
+const RETRY_BASE_DELAY_SECS: i64 = 30;
+
+/// Upper bound of the retry delay, so raising [`MAX_DELIVERY_ATTEMPTS`] can't
+/// push retries out indefinitely
+const MAX_RETRY_DELAY_SECS: i64 = 24 * 60 * 60;
+
+#[derive(Debug, FromRow)]
+struct WebhookRow {
+    webhook_id: i32,
+    url: String,
+    federation_id: Option<Vec<u8>>,
+    events: Vec<String>,
+    min_withdrawal_msat: Option<i64>,
+    created_at: NaiveDateTime,
+}
+
+impl From<WebhookRow> for Webhook {
+    fn from(row: WebhookRow) -> Self {
+        Webhook {
+            webhook_id: row.webhook_id as u32,
+            url: row.url,
+            federation_id: row.federation_id.map(decode_db_value),
+            events: row.events,
+            min_withdrawal_amount: row
+                .min_withdrawal_msat
+                .map(|msat| Amount::from_msats(msat as u64)),
+            created_at: row.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct DeliveryLogParams {
+    #[serde(default = "default_delivery_log_limit")]
+    limit: u32,
+}
+
+fn default_delivery_log_limit() -> u32 {
+    100
+}
+
+pub(super) async fn list_webhooks(
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<Vec<Webhook>>> {
+    Ok(state.federation_observer.list_webhooks().await?.into())
+}
+
+pub(super) async fn create_webhook(
+    State(state): State<AppState>,
+    Json(webhook): Json<NewWebhook>,
+) -> crate::error::Result<Json<Webhook>> {
+    Ok(state
+        .federation_observer
+        .create_webhook(webhook)
+        .await?
+        .into())
+}
+
+pub(super) async fn delete_webhook(
+    Path(webhook_id): Path<u32>,
+    State(state): State<AppState>,
+) -> crate::error::Result<()> {
+    Ok(state.federation_observer.delete_webhook(webhook_id).await?)
+}
+
+pub(super) async fn list_webhook_deliveries(
+    Path(webhook_id): Path<u32>,
+    Query(params): Query<DeliveryLogParams>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<Vec<WebhookDelivery>>> {
+    Ok(state
+        .federation_observer
+        .webhook_deliveries(webhook_id, params.limit)
+        .await?
+        .into())
+}
+
+impl FederationObserver {
+    pub async fn list_webhooks(&self) -> anyhow::Result<Vec<Webhook>> {
+        Ok(query::<WebhookRow>(
+            &self.connection().await?,
+            "SELECT webhook_id, url, federation_id, events, min_withdrawal_msat, created_at FROM webhooks ORDER BY webhook_id",
+            &[],
+        )
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect())
+    }
+
+    pub async fn create_webhook(&self, webhook: NewWebhook) -> anyhow::Result<Webhook> {
+        reqwest::Url::parse(&webhook.url).context("Invalid webhook URL")?;
+        ensure!(
+            !webhook.secret.is_empty(),
+            "Webhook secret must not be empty"
+        );
+        ensure!(!webhook.events.is_empty(), "No events to deliver given");
+        for event in &webhook.events {
+            ensure!(
+                FEDERATION_EVENT_KINDS.contains(&event.as_str()),
+                "Unknown event {event}, expected one of {FEDERATION_EVENT_KINDS:?}"
+            );
+        }
+        if let Some(federation_id) = webhook.federation_id {
+            self.get_federation(federation_id)
+                .await?
+                .context("Federation doesn't exist")?;
+        }
+
+        let webhook = query_one::<WebhookRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            INSERT INTO webhooks (url, secret, federation_id, events, min_withdrawal_msat, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING webhook_id, url, federation_id, events, min_withdrawal_msat, created_at
+            ",
+            &[
+                &webhook.url,
+                &webhook.secret,
+                &webhook
+                    .federation_id
+                    .map(|federation_id| federation_id.consensus_encode_to_vec()),
+                &webhook.events,
+                &webhook
+                    .min_withdrawal_amount
+                    .map(|amount| amount.msats as i64),
+                &chrono::Utc::now().naive_utc(),
+            ],
+        )
+        .await?;
+
+        info!(
+            "Registered webhook {} for {}",
+            webhook.webhook_id, webhook.url
+        );
+
+        Ok(webhook.into())
+    }
+
+    pub async fn delete_webhook(&self, webhook_id: u32) -> anyhow::Result<()> {
+        let deleted = execute(
+            &self.connection().await?,
+            "DELETE FROM webhooks WHERE webhook_id = $1",
+            &[&(webhook_id as i32)],
+        )
+        .await?;
+        ensure!(deleted == 1, "Webhook doesn't exist");
+
+        Ok(())
+    }
+
+    /// Most recent deliveries of a webhook, newest first
+    pub async fn webhook_deliveries(
+        &self,
+        webhook_id: u32,
+        limit: u32,
+    ) -> anyhow::Result<Vec<WebhookDelivery>> {
+        #[derive(Debug, FromRow)]
+        struct DeliveryRow {
+            delivery_id: i64,
+            webhook_id: i32,
+            event: serde_json::Value,
+            created_at: NaiveDateTime,
+            attempts: i32,
+            next_attempt_at: Option<NaiveDateTime>,
+            delivered_at: Option<NaiveDateTime>,
+            last_status: Option<i32>,
+            last_error: Option<String>,
+        }
+
+        query::<DeliveryRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            SELECT delivery_id,
+                   webhook_id,
+                   event,
+                   created_at,
+                   attempts,
+                   next_attempt_at,
+                   delivered_at,
+                   last_status,
+                   last_error
+            FROM webhook_deliveries
+            WHERE webhook_id = $1
+            ORDER BY delivery_id DESC
+            LIMIT $2
+            ",
+            &[&(webhook_id as i32), &(limit as i64)],
+        )
+        .await?
+        .into_iter()
+        .map(|row| {
+            Ok(WebhookDelivery {
+                delivery_id: row.delivery_id as u64,
+                webhook_id: row.webhook_id as u32,
+                event: serde_json::from_value(row.event)?,
+                created_at: row.created_at,
+                attempts: row.attempts as u32,
+                next_attempt_at: row.next_attempt_at,
+                delivered_at: row.delivered_at,
+                last_status: row.last_status.map(|status| status as u16),
+                last_error: row.last_error,
+            })
+        })
+        .collect()
+    }
+
+    /// Persists a delivery for every webhook interested in one of the events.
+    /// Has to be called with the transaction that stores whatever caused the
+    /// events, so deliveries are neither lost nor duplicated if the server
+    /// stops in between. The actual requests are made by
+    /// [`Self::deliver_webhooks`].
+    pub(super) async fn enqueue_webhook_deliveries(
+        dbtx: &impl GenericClient,
+        events: &[FederationEvent],
+    ) -> anyhow::Result<()> {
+        let now = chrono::Utc::now().naive_utc();
+        for event in events {
+            let withdrawal_amount_msat = match event {
+                FederationEvent::PegOut { amount, .. } => Some(amount.msats as i64),
+                _ => None,
+            };
+
+            let enqueued = execute(
+                dbtx,
+                // language=postgresql
+                "
+                INSERT INTO webhook_deliveries (webhook_id, event, created_at, next_attempt_at)
+                SELECT webhook_id, $1, $2, $2
+                FROM webhooks
+                WHERE $3 = ANY (events)
+                  AND (federation_id IS NULL OR federation_id = $4)
+                  AND ($5::BIGINT IS NULL OR min_withdrawal_msat IS NULL OR $5 >= min_withdrawal_msat)
+                ",
+                &[
+                    &serde_json::to_value(event).expect("Can be serialized"),
+                    &now,
+                    &event.kind(),
+                    &event.federation_id().consensus_encode_to_vec(),
+                    &withdrawal_amount_msat,
+                ],
+            )
+            .await?;
+
+            if enqueued > 0 {
+                debug!(
+                    "Enqueued {enqueued} webhook deliveries for {} event",
+                    event.kind()
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Posts pending deliveries, failed ones are retried with exponential
+    /// backoff
+    pub(super) async fn deliver_webhooks(self) {
+        const SLEEP_SECS: u64 = 10;
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("Valid client config");
+
+        loop {
+            if let Err(e) = self.deliver_webhooks_inner(&client).await {
+                warn!("Error while delivering webhooks: {e:?}");
+            }
+            sleep(Duration::from_secs(SLEEP_SECS)).await;
+        }
+    }
+
+    async fn deliver_webhooks_inner(&self, client: &reqwest::Client) -> anyhow::Result<()> {
+        #[derive(Debug, FromRow)]
+        struct PendingDelivery {
+            delivery_id: i64,
+            url: String,
+            secret: String,
+            event: serde_json::Value,
+            attempts: i32,
+        }
+
+        let pending_deliveries = query::<PendingDelivery>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            SELECT wd.delivery_id, w.url, w.secret, wd.event, wd.attempts
+            FROM webhook_deliveries wd
+                     JOIN webhooks w ON wd.webhook_id = w.webhook_id
+            WHERE wd.next_attempt_at <= $1
+            ORDER BY wd.next_attempt_at
+            LIMIT 100
+            ",
+            &[&chrono::Utc::now().naive_utc()],
+        )
+        .await?;
+
+        for delivery in pending_deliveries {
+            let payload = serde_json::to_vec(&delivery.event).expect("Can be serialized");
+            let event_kind = delivery.event["type"].as_str().unwrap_or_default();
+
+            let response = client
+                .post(&delivery.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header("X-FMO-Event", event_kind)
+                .header("X-FMO-Delivery", delivery.delivery_id.to_string())
+                .header(
+                    "X-FMO-Signature",
+                    format!("sha256={}", sign_payload(&delivery.secret, &payload)),
+                )
+                .body(payload)
+                .send()
+                .await;
+
+            let (status, error) = match response {
+                Ok(response) if response.status().is_success() => (Some(response.status()), None),
+                Ok(response) => (
+                    Some(response.status()),
+                    Some(format!("Unexpected response status {}", response.status())),
+                ),
+                Err(e) => (e.status(), Some(e.to_string())),
+            };
+
+            let attempts = delivery.attempts + 1;
+            let now = chrono::Utc::now().naive_utc();
+            let (delivered_at, next_attempt_at) = match &error {
+                None => (Some(now), None),
+                Some(_) => {
+                    let next_attempt_at = next_attempt_at(attempts, now);
+                    if next_attempt_at.is_none() {
+                        warn!(
+                            "Giving up on webhook delivery {} to {} after {attempts} attempts",
+                            delivery.delivery_id, delivery.url
+                        );
+                    }
+                    (None, next_attempt_at)
+                }
+            };
+
+            execute(
+                &self.connection().await?,
+                // language=postgresql
+                "
+                UPDATE webhook_deliveries
+                SET attempts        = $2,
+                    next_attempt_at = $3,
+                    delivered_at    = $4,
+                    last_status     = $5,
+                    last_error      = $6
+                WHERE delivery_id = $1
+                ",
+                &[
+                    &delivery.delivery_id,
+                    &attempts,
+                    &next_attempt_at,
+                    &delivered_at,
+                    &status.map(|status| status.as_u16() as i32),
+                    &error,
+                ],
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// When to retry a delivery that failed for the `attempts`th time, `None` if
+/// it should be given up on
+fn next_attempt_at(attempts: i32, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    if attempts >= MAX_DELIVERY_ATTEMPTS {
+        return None;
+    }
+
+    Some(now + chrono::Duration::seconds(retry_delay_secs(attempts)))
+}
+
+/// Exponential backoff after the `attempts`th failure, capped at
+/// [`MAX_RETRY_DELAY_SECS`] instead of overflowing
+fn retry_delay_secs(attempts: i32) -> i64 {
+    let exponent = u32::try_from(attempts.saturating_sub(1)).unwrap_or(0);
+    2i64.checked_pow(exponent)
+        .and_then(|factor| RETRY_BASE_DELAY_SECS.checked_mul(factor))
+        .map_or(MAX_RETRY_DELAY_SECS, |delay| {
+            delay.min(MAX_RETRY_DELAY_SECS)
+        })
+}
+
+/// Hex encoded HMAC-SHA256 of the payload, sent in the `X-FMO-Signature` header
+/// so receivers can authenticate deliveries
+fn sign_payload(secret: &str, payload: &[u8]) -> String {
+    let mut engine = HmacEngine::<sha256::Hash>::new(secret.as_bytes());
+    engine.input(payload);
+    Hmac::<sha256::Hash>::from_engine(engine).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDateTime;
+
+    use super::{
+        next_attempt_at, retry_delay_secs, sign_payload, MAX_DELIVERY_ATTEMPTS,
+        MAX_RETRY_DELAY_SECS, RETRY_BASE_DELAY_SECS,
+    };
+
+    #[test]
+    fn test_next_attempt_at() {
+        let now = NaiveDateTime::default();
+        let delay = |attempts| {
+            next_attempt_at(attempts, now)
+                .map(|next_attempt_at| (next_attempt_at - now).num_seconds())
+        };
+
+        assert_eq!(delay(1), Some(RETRY_BASE_DELAY_SECS));
+        assert_eq!(delay(2), Some(2 * RETRY_BASE_DELAY_SECS));
+        assert_eq!(delay(3), Some(4 * RETRY_BASE_DELAY_SECS));
+        assert_eq!(
+            delay(MAX_DELIVERY_ATTEMPTS - 1),
+            Some(256 * RETRY_BASE_DELAY_SECS)
+        );
+        // Failed deliveries are given up on after the last attempt
+        assert_eq!(delay(MAX_DELIVERY_ATTEMPTS), None);
+        assert_eq!(delay(i32::MAX), None);
+    }
+
+    #[test]
+    fn test_retry_delay_secs() {
+        assert_eq!(retry_delay_secs(0), RETRY_BASE_DELAY_SECS);
+        assert_eq!(retry_delay_secs(i32::MIN), RETRY_BASE_DELAY_SECS);
+        assert_eq!(retry_delay_secs(12), 2048 * RETRY_BASE_DELAY_SECS);
+        // Delays that would exceed the cap or overflow are capped instead
+        assert_eq!(retry_delay_secs(13), MAX_RETRY_DELAY_SECS);
+        assert_eq!(retry_delay_secs(64), MAX_RETRY_DELAY_SECS);
+        assert_eq!(retry_delay_secs(i32::MAX), MAX_RETRY_DELAY_SECS);
+    }
+
+    #[test]
+    fn test_sign_payload() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign_payload("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
@@ -0,0 +1,324 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::extract::{Path, State};
+use axum::Json;
+use chrono::NaiveDateTime;
+use deadpool_postgres::Transaction;
+use fedimint_core::config::FederationId;
+use fedimint_core::encoding::Encodable;
+use fmo_api_types::{FederationWithdrawal, Percentiles, WithdrawalStatistics};
+use postgres_from_row::FromRow;
+use tokio::time::sleep;
+use tracing::{debug, info, warn};
+
+use crate::federation::db::Federation;
+use crate::federation::observer::{signature_threshold, FederationObserver};
+use crate::util::{decode_db_value, execute, query};
+use crate::AppState;
+
+/// Polling for details of an unconfirmed withdrawal stops after this many
+/// attempts, about three weeks with the backoff below
+const MAX_WITHDRAWAL_DETAILS_ATTEMPTS: u32 = 30;
+
+/// Longest time between two attempts to fetch details of a withdrawal
+const MAX_WITHDRAWAL_DETAILS_BACKOFF: chrono::Duration = chrono::Duration::days(1);
+
+pub(super) async fn list_withdrawals(
+    Path(federation_id): Path<FederationId>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<Vec<FederationWithdrawal>>> {
+    Ok(state
+        .federation_observer
+        .federation_withdrawals(federation_id)
+        .await?
+        .into())
+}
+
+pub(super) async fn withdrawal_statistics(
+    Path(federation_id): Path<FederationId>,
+    State(state): State<AppState>,
+) -> crate::error::Result<Json<WithdrawalStatistics>> {
+    let withdrawals = state
+        .federation_observer
+        .federation_withdrawals(federation_id)
+        .await?;
+
+    let percentiles_of = |extract: fn(&FederationWithdrawal) -> Option<f64>| {
+        percentiles(withdrawals.iter().filter_map(extract).collect())
+    };
+
+    Ok(WithdrawalStatistics {
+        count: withdrawals.len() as u64,
+        fee_sat: percentiles_of(|w| w.fee_sat.map(|fee| fee as f64)),
+        feerate_sat_per_vb: percentiles_of(|w| w.feerate_sat_per_vb),
+        signing_time_secs: percentiles_of(|w| w.signing_time_secs.map(|secs| secs as f64)),
+        confirmation_time_secs: percentiles_of(|w| {
+            w.confirmation_time_secs.map(|secs| secs as f64)
+        }),
+    }
+    .into())
+}
+
+impl FederationObserver {
+    /// Fee and latency data of all peg-outs that reached the signature
+    /// threshold. Timestamps are estimated from block height votes, so the
+    /// latencies have a resolution of roughly one block.
+    pub async fn federation_withdrawals(
+        &self,
+        federation_id: FederationId,
+    ) -> anyhow::Result<Vec<FederationWithdrawal>> {
+        #[derive(Debug, FromRow)]
+        struct WithdrawalStatsRow {
+            on_chain_txid: Vec<u8>,
+            federation_txid: Option<Vec<u8>>,
+            weight: Option<i32>,
+            fee_sat: Option<i64>,
+            request_time: Option<NaiveDateTime>,
+            threshold_time: Option<NaiveDateTime>,
+            confirmation_time: Option<NaiveDateTime>,
+        }
+
+        self.get_federation(federation_id)
+            .await?
+            .context("Federation doesn't exist")?;
+
+        let rows = query::<WithdrawalStatsRow>(
+            &self.connection().await?,
+            // language=postgresql
+            "
+            SELECT on_chain_txid,
+                   federation_txid,
+                   weight,
+                   fee_sat,
+                   request_time,
+                   threshold_time,
+                   confirmation_time
+            FROM wallet_withdrawal_stats
+            WHERE federation_id = $1
+              AND threshold_session_index IS NOT NULL
+            ORDER BY threshold_session_index
+            ",
+            &[&federation_id.consensus_encode_to_vec()],
+        )
+        .await?;
+
+        let secs_between = |from: Option<NaiveDateTime>, to: Option<NaiveDateTime>| {
+            Some((to? - from?).num_seconds())
+        };
+
+        Ok(rows
+            .into_iter()
+            .map(|row| FederationWithdrawal {
+                // On-chain txids are stored in display byte order
+                on_chain_txid: hex::encode(&row.on_chain_txid),
+                txid: row.federation_txid.map(decode_db_value),
+                fee_sat: row.fee_sat.map(|fee| fee as u64),
+                feerate_sat_per_vb: row
+                    .fee_sat
+                    .zip(row.weight)
+                    .map(|(fee, weight)| fee as f64 / (weight as f64 / 4.0)),
+                signing_time_secs: secs_between(row.request_time, row.threshold_time),
+                confirmation_time_secs: secs_between(row.request_time, row.confirmation_time),
+            })
+            .collect())
+    }
+
+    /// Fills in on-chain data of peg-out transactions that isn't available yet
+    /// when they are first seen, most importantly their confirmation height.
+    pub(super) async fn fetch_withdrawal_details(self) {
+        const SLEEP_SECS: u64 = 60;
+        loop {
+            if let Err(e) = self.fetch_withdrawal_details_inner().await {
+                warn!("Error while fetching withdrawal details: {e:?}");
+            }
+            sleep(Duration::from_secs(SLEEP_SECS)).await;
+        }
+    }
+
+    async fn fetch_withdrawal_details_inner(&self) -> anyhow::Result<()> {
+        #[derive(Debug, FromRow)]
+        struct PendingWithdrawal {
+            on_chain_txid: Vec<u8>,
+            details_attempts: i32,
+        }
+
+        let pending_withdrawals = query::<PendingWithdrawal>(
+            &self.connection().await?,
+            "
+            SELECT on_chain_txid, details_attempts
+            FROM wallet_withdrawal_transactions
+            WHERE threshold_session_index IS NOT NULL
+              AND (weight IS NULL OR confirmation_block_height IS NULL)
+              AND details_abandoned_at IS NULL
+              AND (details_next_attempt_at IS NULL OR details_next_attempt_at <= $1)
+            ",
+            &[&chrono::Utc::now().naive_utc()],
+        )
+        .await?;
+
+        if pending_withdrawals.is_empty() {
+            return Ok(());
+        }
+
+        info!(
+            "Fetching details of {} pending withdrawals",
+            pending_withdrawals.len()
+        );
+
+        let esplora_client =
+            esplora_client::Builder::new("https://mempool.space/api").build_async()?;
+
+        for withdrawal in pending_withdrawals {
+            let txid = esplora_client::Txid::from_str(&hex::encode(&withdrawal.on_chain_txid))?;
+
+            let confirmed = match futures::try_join!(
+                esplora_client.get_tx_no_opt(&txid),
+                esplora_client.get_tx_status(&txid)
+            ) {
+                Ok((tx, status)) => {
+                    execute(
+                        &self.connection().await?,
+                        "
+                        UPDATE wallet_withdrawal_transactions
+                        SET weight = $2,
+                            confirmation_block_height = $3
+                        WHERE on_chain_txid = $1
+                        ",
+                        &[
+                            &withdrawal.on_chain_txid,
+                            &(tx.weight().to_wu() as i32),
+                            &status.block_height.map(|height| height as i32),
+                        ],
+                    )
+                    .await?;
+                    status.block_height.is_some()
+                }
+                Err(e) => {
+                    // Transactions that were replaced will never confirm, we don't want them to
+                    // block the others
+                    debug!("Failed to fetch withdrawal {txid}: {e:?}");
+                    false
+                }
+            };
+
+            if !confirmed {
+                self.schedule_withdrawal_details_retry(
+                    &withdrawal.on_chain_txid,
+                    withdrawal.details_attempts as u32 + 1,
+                )
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Backs off exponentially from polling a withdrawal that isn't confirmed
+    /// yet and gives up on it after [`MAX_WITHDRAWAL_DETAILS_ATTEMPTS`], since
+    /// it was most likely replaced or dropped from the mempool
+    async fn schedule_withdrawal_details_retry(
+        &self,
+        on_chain_txid: &[u8],
+        attempts: u32,
+    ) -> anyhow::Result<()> {
+        let now = chrono::Utc::now().naive_utc();
+        let backoff =
+            chrono::Duration::minutes(1 << attempts.min(20)).min(MAX_WITHDRAWAL_DETAILS_BACKOFF);
+        let abandoned_at = (attempts >= MAX_WITHDRAWAL_DETAILS_ATTEMPTS).then_some(now);
+        if abandoned_at.is_some() {
+            warn!(
+                "Giving up on fetching details of withdrawal {} after {attempts} attempts, it was probably replaced",
+                hex::encode(on_chain_txid)
+            );
+        }
+
+        execute(
+            &self.connection().await?,
+            "
+            UPDATE wallet_withdrawal_transactions
+            SET details_attempts = $2,
+                details_next_attempt_at = $3,
+                details_abandoned_at = $4
+            WHERE on_chain_txid = $1
+            ",
+            &[
+                &on_chain_txid,
+                &(attempts as i32),
+                &(now + backoff),
+                &abandoned_at,
+            ],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    pub(super) async fn backfill_v7_withdrawal_threshold_sessions(
+        federations: &[Federation],
+        dbtx: &Transaction<'_>,
+    ) -> anyhow::Result<()> {
+        info!("Backfilling peg-out signature threshold sessions");
+
+        for federation in federations {
+            let threshold = signature_threshold(&federation.config);
+            dbtx.execute(
+                "
+                UPDATE wallet_withdrawal_transactions wwt
+                SET threshold_session_index = (SELECT wws.session_index
+                                               FROM wallet_withdrawal_signatures wws
+                                               WHERE wws.on_chain_txid = wwt.on_chain_txid
+                                               ORDER BY wws.session_index, wws.item_index
+                                               OFFSET $2 LIMIT 1)
+                WHERE wwt.federation_id = $1
+                ",
+                &[
+                    &federation.federation_id.consensus_encode_to_vec(),
+                    &((threshold - 1) as i64),
+                ],
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Nearest-rank percentiles
+fn percentiles(mut values: Vec<f64>) -> Percentiles {
+    values.sort_by(|a, b| a.partial_cmp(b).expect("No NaNs expected"));
+
+    let percentile = |p: f64| {
+        let rank = ((p * values.len() as f64).ceil() as usize).max(1);
+        values.get(rank - 1).copied()
+    };
+
+    Percentiles {
+        samples: values.len() as u64,
+        p50: percentile(0.5),
+        p90: percentile(0.9),
+        p99: percentile(0.99),
+        max: values.last().copied(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::percentiles;
+
+    #[test]
+    fn test_percentiles() {
+        let empty = percentiles(vec![]);
+        assert_eq!(empty.samples, 0);
+        assert_eq!(empty.p50, None);
+        assert_eq!(empty.max, None);
+
+        let values = percentiles((1..=100).rev().map(f64::from).collect());
+        assert_eq!(values.samples, 100);
+        assert_eq!(values.p50, Some(50.0));
+        assert_eq!(values.p90, Some(90.0));
+        assert_eq!(values.p99, Some(99.0));
+        assert_eq!(values.max, Some(100.0));
+    }
+}
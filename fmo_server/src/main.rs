@@ -1,17 +1,29 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
 use anyhow::Context;
 use axum::routing::get;
-use axum::Router;
+use axum::{middleware, Router};
+use clap::Parser;
 use tower_http::cors::CorsLayer;
 use tracing::info;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
 
+use crate::auth::{audit_admin_actions, get_auth_routes};
+use crate::cli::{Cli, Command};
 use crate::config::meta::MetaOverrideCache;
+use crate::config::status::FederationStatusCache;
 use crate::config::{get_config_routes, FederationConfigCache};
+use crate::federation::alerts::get_alerts_routes;
 use crate::federation::get_federations_routes;
 use crate::federation::observer::FederationObserver;
+use crate::federation::search::search;
 
+/// Admin authentication using API keys and audit logging
+mod auth;
+mod cli;
 /// Fedimint config fetching service implementation
 mod config;
 /// `anyhow`-based error handling for axum
@@ -20,17 +32,22 @@ mod federation;
 mod meta;
 mod util;
 
+/// Age after which configs cached by the config inspector are refreshed
+const DEFAULT_CONFIG_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
 #[derive(Debug, Clone)]
 struct AppState {
     federation_config_cache: FederationConfigCache,
     meta_override_cache: MetaOverrideCache,
+    federation_status_cache: FederationStatusCache,
     federation_observer: FederationObserver,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::registry()
-        .with(tracing_subscriber::fmt::layer())
+        // stdout is reserved for command output, e.g. exports
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
         .with(
             EnvFilter::builder()
                 .with_default_directive("info".parse().unwrap())
@@ -39,31 +56,96 @@ async fn main() -> anyhow::Result<()> {
         )
         .init();
 
+    match Cli::parse().command.unwrap_or(Command::Serve) {
+        Command::Serve => serve().await,
+        command => cli::run(command).await,
+    }
+}
+
+async fn serve() -> anyhow::Result<()> {
     let bind_address = dotenv::var("FO_BIND").unwrap_or_else(|_| "127.0.0.1:3000".to_owned());
     info!("Starting API server on {bind_address}");
 
+    let federation_observer = FederationObserver::new(
+        &dotenv::var("FO_DATABASE").context("No FO_DATABASE provided")?,
+        &dotenv::var("FO_ADMIN_AUTH").context("No FO_ADMIN_AUTH provided")?,
+        dotenv::var("FO_NOSTR_SECRET_KEY")
+            .ok()
+            .map(nostr_sdk::Keys::parse)
+            .transpose()
+            .context("Invalid FO_NOSTR_SECRET_KEY")?,
+        dotenv::var("FO_NOSTR_RATING_POW")
+            .ok()
+            .map(|pow| pow.parse())
+            .transpose()
+            .context("Invalid FO_NOSTR_RATING_POW")?
+            .unwrap_or(0),
+        dotenv::var("FO_AUTO_ADD_FEDERATIONS")
+            .ok()
+            .map(|auto_add| auto_add.parse())
+            .transpose()
+            .context("Invalid FO_AUTO_ADD_FEDERATIONS, expected true or false")?
+            .unwrap_or(false),
+        dotenv::var("FO_NOSTR_TRUSTED_PUBKEYS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|pubkey| !pubkey.is_empty())
+            .map(nostr_sdk::PublicKey::parse)
+            .collect::<Result<Vec<_>, _>>()
+            .context("Invalid FO_NOSTR_TRUSTED_PUBKEYS")?,
+        dotenv::var("FO_TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|proxy| !proxy.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<_>, _>>()
+            .context("Invalid FO_TRUSTED_PROXIES")?,
+    )
+    .await?;
+    let config_cache_ttl = dotenv::var("FO_CONFIG_CACHE_TTL")
+        .ok()
+        .map(|ttl| ttl.parse())
+        .transpose()
+        .context("Invalid FO_CONFIG_CACHE_TTL, expected seconds")?
+        .map_or(DEFAULT_CONFIG_CACHE_TTL, Duration::from_secs);
+
+    let federation_config_cache =
+        FederationConfigCache::new(federation_observer.clone(), config_cache_ttl);
+    tokio::spawn(federation_config_cache.clone().refresh_stale_configs());
+
+    let state = AppState {
+        federation_config_cache,
+        meta_override_cache: Default::default(),
+        federation_status_cache: Default::default(),
+        federation_observer,
+    };
+
     let app = Router::new()
         .route("/health", get(|| async { "Server is up and running!" }))
         .nest("/config", get_config_routes())
         .nest("/federations", get_federations_routes())
+        .nest("/alerts", get_alerts_routes())
+        .nest("/auth", get_auth_routes())
+        .route("/search", get(search))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            audit_admin_actions,
+        ))
         .layer(CorsLayer::permissive())
-        .with_state(AppState {
-            federation_config_cache: Default::default(),
-            meta_override_cache: Default::default(),
-            federation_observer: FederationObserver::new(
-                &dotenv::var("FO_DATABASE").context("No FO_DATABASE provided")?,
-                &dotenv::var("FO_ADMIN_AUTH").context("No FO_ADMIN_AUTH provided")?,
-            )
-            .await?,
-        });
+        .with_state(state);
 
     let listener = tokio::net::TcpListener::bind(bind_address)
         .await
         .context("Binding to port")?;
 
-    axum::serve(listener, app)
-        .await
-        .context("Starting axum server")?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .context("Starting axum server")?;
 
     Ok(())
 }
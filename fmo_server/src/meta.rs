@@ -1,47 +1,260 @@
+use anyhow::Context;
 use axum::Json;
 use fedimint_core::config::{JsonClientConfig, META_OVERRIDE_URL_KEY};
+use fmo_api_types::{FederationMeta, MetaIssue, MetaIssueKind, MetaSource, MetaValidationReport};
+use serde_json::json;
 use tracing::debug;
 use tracing::log::warn;
 
-use crate::config::meta::{parse_meta_lenient, MetaFields};
+use crate::config::meta::{parse_meta_lenient, MetaFields, MetaOverrideCache};
 use crate::AppState;
 
 pub async fn federation_meta(
     cfg: &JsonClientConfig,
     state: &AppState,
 ) -> crate::error::Result<Json<MetaFields>> {
-    let meta_fields_config = parse_meta_lenient(
+    let meta_fields = match federation_meta_fields(cfg, &state.meta_override_cache).await {
+        Ok(meta_fields) => meta_fields,
+        Err(e) => {
+            warn!("{e:?}");
+            config_meta_fields(cfg)
+        }
+    };
+
+    Ok(meta_fields.into())
+}
+
+/// Meta fields from the config merged with the ones from the override file, if
+/// any. Fails if the override file can't be fetched.
+pub async fn federation_meta_fields(
+    cfg: &JsonClientConfig,
+    meta_override_cache: &MetaOverrideCache,
+) -> anyhow::Result<MetaFields> {
+    let meta_fields_config = config_meta_fields(cfg);
+
+    let Some(override_url) = meta_fields_config
+        .get(META_OVERRIDE_URL_KEY)
+        .or_else(|| meta_fields_config.get("meta_external_url")) // Fedi legacy field
+        .and_then(|url| url.as_str().map(ToOwned::to_owned))
+    else {
+        return Ok(meta_fields_config);
+    };
+
+    debug!("fetching {override_url}");
+    let meta_override = meta_override_cache
+        .fetch_meta_cached(&override_url, cfg.global.calculate_federation_id())
+        .await
+        .with_context(|| format!("Failed to fetch meta fields from {override_url}"))?;
+
+    Ok(meta_fields_config
+        .into_iter()
+        .chain(meta_override)
+        .collect::<MetaFields>())
+}
+
+fn config_meta_fields(cfg: &JsonClientConfig) -> MetaFields {
+    parse_meta_lenient(
+        cfg.global
+            .meta
+            .iter()
+            .map(|(key, value)| (key.to_owned(), value.to_owned().into())),
+    )
+}
+
+/// Checks the config and override meta fields against [`FederationMeta`],
+/// reporting everything clients would ignore or fail to parse
+pub async fn validate_meta(
+    cfg: &JsonClientConfig,
+    meta_override_cache: &MetaOverrideCache,
+) -> MetaValidationReport {
+    let mut valid_fields = serde_json::Map::new();
+    let mut issues = Vec::new();
+
+    validate_meta_fields(
         cfg.global
             .meta
             .iter()
             .map(|(key, value)| (key.to_owned(), value.to_owned().into())),
+        MetaSource::Config,
+        &mut valid_fields,
+        &mut issues,
     );
 
-    let meta_fields = if let Some(override_url) = meta_fields_config
-        .get(META_OVERRIDE_URL_KEY)
-        .or_else(|| meta_fields_config.get("meta_external_url")) // Fedi legacy field
-        .and_then(|url| url.as_str().map(ToOwned::to_owned))
-    {
-        debug!("fetching {override_url}");
-        let meta_override = match state
-            .meta_override_cache
-            .fetch_meta_cached(&override_url, cfg.global.calculate_federation_id())
+    let override_url = [META_OVERRIDE_URL_KEY, "meta_external_url"]
+        .into_iter()
+        .find_map(|key| Some((key, cfg.global.meta.get(key)?)));
+    if let Some((override_key, override_url)) = override_url {
+        match meta_override_cache
+            .fetch_meta_raw_cached(override_url, cfg.global.calculate_federation_id())
             .await
         {
-            Ok(meta) => meta,
-            Err(e) => {
-                warn!("Failed to fetch meta fields from {override_url}: {e:?}");
-                return Ok(meta_fields_config.into());
+            Ok(override_fields) => validate_meta_fields(
+                override_fields,
+                MetaSource::Override,
+                &mut valid_fields,
+                &mut issues,
+            ),
+            Err(e) => issues.push(MetaIssue {
+                key: override_key.to_owned(),
+                source: MetaSource::Config,
+                kind: MetaIssueKind::OverrideUnavailable,
+                message: format!("Failed to fetch meta fields from {override_url}: {e:#}"),
+            }),
+        }
+    }
+
+    MetaValidationReport {
+        federation_id: cfg.global.calculate_federation_id(),
+        valid: issues.is_empty(),
+        meta: serde_json::from_value(valid_fields.into())
+            .expect("Only fields that can be parsed are kept"),
+        issues,
+    }
+}
+
+/// Adds the fields that can be parsed into [`FederationMeta`] to
+/// `valid_fields`, later sources overriding earlier ones, and records issues
+/// for all others
+fn validate_meta_fields(
+    fields: impl IntoIterator<Item = (String, serde_json::Value)>,
+    source: MetaSource,
+    valid_fields: &mut serde_json::Map<String, serde_json::Value>,
+    issues: &mut Vec<MetaIssue>,
+) {
+    for (key, value) in fields {
+        let mut issue = |kind, message| {
+            issues.push(MetaIssue {
+                key: key.clone(),
+                source,
+                kind,
+                message,
+            })
+        };
+
+        // Clients only accept strings, containing JSON for non-string values
+        let Some(value_string) = value.as_str() else {
+            issue(
+                MetaIssueKind::Dropped,
+                format!("Value has to be a string, e.g. {:?}", value.to_string()),
+            );
+            continue;
+        };
+
+        if !FederationMeta::KNOWN_FIELDS.contains(&key.as_str()) {
+            issue(
+                MetaIssueKind::UnknownKey,
+                "Not a well-known meta field".to_owned(),
+            );
+            continue;
+        }
+
+        // Same as `parse_meta_lenient`, but string fields may contain something
+        // that looks like JSON, e.g. a numeric federation name
+        let lenient_value = serde_json::from_str(value_string)
+            .unwrap_or_else(|_| serde_json::Value::String(value_string.to_owned()));
+        let parse_field = |value: &serde_json::Value| {
+            serde_json::from_value::<FederationMeta>(json!({ &key: value })).map(|_| ())
+        };
+        match parse_field(&lenient_value) {
+            Ok(()) => {
+                valid_fields.insert(key, lenient_value);
+            }
+            Err(_) if parse_field(&value).is_ok() => {
+                valid_fields.insert(key, value);
             }
+            Err(e) => issue(MetaIssueKind::WrongType, e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fmo_api_types::{FederationMeta, MetaIssueKind, MetaSource};
+    use serde_json::json;
+
+    use super::validate_meta_fields;
+
+    #[test]
+    fn test_known_fields() {
+        // No `..Default::default()` so new fields have to be added here
+        let meta = FederationMeta {
+            federation_name: Some(String::new()),
+            federation_icon_url: Some(String::new()),
+            welcome_message: Some(String::new()),
+            federation_expiry_timestamp: Some(0),
+            vetted_gateways: Some(vec![]),
+            meta_override_url: Some(String::new()),
+            popup_end_timestamp: Some(0),
+            popup_countdown_message: Some(String::new()),
+            meta_external_url: Some(String::new()),
+            fedi_pinned_message: Some(String::new()),
+            fedi_federation_icon_url: Some(String::new()),
+            fedi_tos_url: Some(String::new()),
+            fedi_default_currency: Some(String::new()),
+            fedi_popup_end_timestamp: Some(0),
+            fedi_popup_countdown_message: Some(String::new()),
+            fedi_invite_codes_disabled: Some(false),
+            fedi_new_members_disabled: Some(false),
+            fedi_max_invoice_msats: Some(0),
+            fedi_max_balance_msats: Some(0),
         };
 
-        meta_fields_config
-            .into_iter()
-            .chain(meta_override)
-            .collect::<MetaFields>()
-    } else {
-        meta_fields_config
-    };
+        let serde_json::Value::Object(fields) = serde_json::to_value(meta).unwrap() else {
+            panic!("FederationMeta has to serialize to an object");
+        };
+        let mut serialized_keys = fields.keys().map(String::as_str).collect::<Vec<_>>();
+        let mut known_fields = FederationMeta::KNOWN_FIELDS.to_vec();
+        serialized_keys.sort_unstable();
+        known_fields.sort_unstable();
 
-    Ok(meta_fields.into())
+        assert_eq!(serialized_keys, known_fields);
+    }
+
+    #[test]
+    fn test_validate_meta_fields() {
+        let mut valid_fields = serde_json::Map::new();
+        let mut issues = Vec::new();
+
+        validate_meta_fields(
+            [
+                ("federation_name", json!("1234")),
+                ("federation_expiry_timestamp", json!("1700000000")),
+                ("vetted_gateways", json!("[\"gw1\",\"gw2\"]")),
+                ("popup_end_timestamp", json!(1700000000)),
+                ("fedi:max_balance_msats", json!("lots")),
+                ("custom_field", json!("value")),
+            ]
+            .map(|(key, value)| (key.to_owned(), value)),
+            MetaSource::Override,
+            &mut valid_fields,
+            &mut issues,
+        );
+
+        let meta: FederationMeta = serde_json::from_value(valid_fields.into()).unwrap();
+        assert_eq!(
+            meta,
+            FederationMeta {
+                federation_name: Some("1234".to_owned()),
+                federation_expiry_timestamp: Some(1700000000),
+                vetted_gateways: Some(vec!["gw1".to_owned(), "gw2".to_owned()]),
+                ..Default::default()
+            }
+        );
+
+        let issues = issues
+            .iter()
+            .map(|issue| {
+                assert_eq!(issue.source, MetaSource::Override);
+                (issue.key.as_str(), issue.kind)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            vec![
+                ("popup_end_timestamp", MetaIssueKind::Dropped),
+                ("fedi:max_balance_msats", MetaIssueKind::WrongType),
+                ("custom_field", MetaIssueKind::UnknownKey),
+            ]
+        );
+    }
 }
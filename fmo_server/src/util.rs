@@ -1,7 +1,7 @@
 use deadpool_postgres::GenericClient;
 use fedimint_core::config::{ClientConfig, ClientModuleConfig, JsonClientConfig, JsonWithKind};
 use fedimint_core::core::{ModuleInstanceId, ModuleKind};
-use fedimint_core::encoding::DynRawFallback;
+use fedimint_core::encoding::{Decodable, DynRawFallback};
 use fedimint_core::module::registry::ModuleDecoderRegistry;
 use fedimint_core::module::CommonModuleInit;
 use fedimint_ln_common::LightningCommonInit;
@@ -74,6 +74,12 @@ pub fn get_decoders(
     .with_fallback()
 }
 
+/// Decodes a consensus encoded value stored in the DB, panicking if it's
+/// invalid
+pub fn decode_db_value<T: Decodable>(bytes: Vec<u8>) -> T {
+    T::consensus_decode_vec(bytes, &Default::default()).expect("Invalid data in DB")
+}
+
 pub async fn execute(
     conn: &impl GenericClient,
     sql: &str,
@@ -133,3 +139,14 @@ where
         .map(T::try_from_row)
         .collect::<Result<_, _>>()?)
 }
+
+/// Picks the consensus value from the latest vote of each guardian the same way
+/// the wallet module does for block heights and feerates. The wallet module
+/// additionally pads missing votes with a default value we don't know, so this
+/// may differ while guardians are missing.
+pub fn consensus_median<T: Ord>(votes: impl IntoIterator<Item = T>) -> Option<T> {
+    let mut votes = votes.into_iter().collect::<Vec<_>>();
+    votes.sort_unstable();
+    let median_idx = votes.len() / 2;
+    votes.into_iter().nth(median_idx)
+}